@@ -1,7 +1,7 @@
 #![no_std]
 
 use framebuffer::raw::write::RawWriter;
-use mem::{map::MemoryMap, paging::ptm::PageTableManager};
+use mem::{map::MemoryMap, paging::ptm::PageTableManager, PhysicalAddress};
 
 #[derive(Debug)]
 pub struct BootInfo {
@@ -9,4 +9,13 @@ pub struct BootInfo {
     pub writer: Option<RawWriter>,
     pub ptm: Option<PageTableManager>,
     pub rsdp: *const u8,
+    pub initrd: Initrd,
+}
+
+/// Physical memory range of the initial ramdisk archive, see [`mem::map::MemoryType::Initrd`].
+#[derive(Copy, Clone, Debug)]
+pub struct Initrd {
+    pub address: PhysicalAddress,
+    /// Length of the archive in bytes
+    pub length: usize,
 }
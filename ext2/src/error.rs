@@ -0,0 +1,15 @@
+use vfs::error::VfsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Ext2Error {
+    #[error("{0}")]
+    Vfs(#[from] VfsError),
+    #[error("Invalid superblock magic: {0:#x}.")]
+    InvalidMagic(u16),
+    #[error("Unsupported revision: {0}.")]
+    UnsupportedRevision(u32),
+    #[error("Unsupported incompatible features: {0:#x}.")]
+    UnsupportedFeatures(u32),
+    #[error("Invalid superblock: {0}.")]
+    InvalidParameter(&'static str),
+}
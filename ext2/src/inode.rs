@@ -0,0 +1,235 @@
+use alloc::{rc::Rc, string::String, vec, vec::Vec};
+use vfs::{
+    error::VfsError,
+    inode::{DirEntry, Inode, InodeType, Metadata},
+};
+
+use crate::volume::{INCOMPAT_FILETYPE, Volume};
+
+/// Inode number of the root directory.
+pub(crate) const ROOT_INO: u32 = 2;
+
+// file type bits of the mode
+const S_IFMT: u16 = 0xf000;
+const S_IFCHR: u16 = 0x2000;
+const S_IFDIR: u16 = 0x4000;
+const S_IFBLK: u16 = 0x6000;
+const S_IFREG: u16 = 0x8000;
+const S_IFLNK: u16 = 0xa000;
+
+/// Symbolic links with shorter targets store them in the block pointers of the inode.
+const FAST_SYMLINK_MAX: u64 = 60;
+
+/// Size of the fixed part of a directory entry.
+const DIR_ENTRY_HEADER: usize = 8;
+
+/// Fields of an inode used for reading.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RawInode {
+    pub(crate) mode: u16,
+    size_low: u32,
+    size_high: u32,
+    pub(crate) links: u16,
+    /// Number of 512 byte sectors allocated for the inode, including indirect blocks.
+    sectors: u32,
+    /// Block of the extended attributes.
+    file_acl: u32,
+    /// Direct, indirect, double indirect and triple indirect block pointers.
+    pub(crate) blocks: [u32; 15],
+}
+
+impl RawInode {
+    pub(crate) fn parse(raw: &[u8]) -> RawInode {
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap_or_default())
+        };
+
+        let mut blocks = [0; 15];
+        for (index, block) in blocks.iter_mut().enumerate() {
+            *block = u32_at(40 + index * 4);
+        }
+
+        RawInode {
+            mode: u16::from_le_bytes([raw[0], raw[1]]),
+            size_low: u32_at(4),
+            size_high: u32_at(108),
+            links: u16::from_le_bytes([raw[26], raw[27]]),
+            sectors: u32_at(28),
+            file_acl: u32_at(104),
+            blocks,
+        }
+    }
+
+    pub(crate) fn kind(&self) -> InodeType {
+        match self.mode & S_IFMT {
+            S_IFDIR => InodeType::Directory,
+            S_IFLNK => InodeType::Symlink,
+            S_IFCHR => InodeType::CharDevice,
+            S_IFBLK => InodeType::BlockDevice,
+            _ => InodeType::File,
+        }
+    }
+
+    /// Returns the size in bytes. The upper 32 bits are only used by regular files of volumes
+    /// supporting large files.
+    pub(crate) fn size(&self, large_files: bool) -> u64 {
+        match large_files && self.mode & S_IFMT == S_IFREG {
+            true => (self.size_high as u64) << 32 | self.size_low as u64,
+            false => self.size_low as u64,
+        }
+    }
+}
+
+/// File of an ext2 volume. Since the volume is read-only, the inode is read once.
+#[derive(Debug)]
+pub(crate) struct Ext2Inode {
+    volume: Rc<Volume>,
+    ino: u32,
+    raw: RawInode,
+}
+
+impl Ext2Inode {
+    pub(crate) fn new(volume: Rc<Volume>, ino: u32) -> Result<Ext2Inode, VfsError> {
+        let raw = volume.inode(ino)?;
+        if raw.links == 0 {
+            return Err(VfsError::NotFound);
+        }
+        Ok(Ext2Inode { volume, ino, raw })
+    }
+
+    fn size(&self) -> u64 {
+        self.raw.size(self.volume.superblock().has_large_files())
+    }
+
+    /// Returns the entries of a directory including `.` and `..`, which are stored in a linked
+    /// list within each block.
+    fn entries(&self) -> Result<Vec<(String, u32, u8)>, VfsError> {
+        if self.raw.kind() != InodeType::Directory {
+            return Err(VfsError::NotDirectory);
+        }
+
+        let file_type = self.volume.superblock().incompat & INCOMPAT_FILETYPE != 0;
+        let block_size = self.volume.block_size();
+        let mut block = vec![0; block_size];
+        let mut entries = Vec::new();
+
+        for index in 0..self.size().div_ceil(block_size as u64) {
+            let physical = self.volume.map_block(&self.raw, index)?;
+            self.volume.read_block(physical, &mut block)?;
+
+            let mut offset = 0;
+            while offset + DIR_ENTRY_HEADER <= block_size {
+                let entry = &block[offset..];
+                let ino = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+                let length = u16::from_le_bytes([entry[4], entry[5]]) as usize;
+                let name_length = match file_type {
+                    true => entry[6] as usize,
+                    false => u16::from_le_bytes([entry[6], entry[7]]) as usize,
+                };
+                if length < DIR_ENTRY_HEADER
+                    || offset + length > block_size
+                    || DIR_ENTRY_HEADER + name_length > length
+                {
+                    return Err(VfsError::Io);
+                }
+
+                // unused entries have inode 0
+                if ino != 0 {
+                    let name = &entry[DIR_ENTRY_HEADER..DIR_ENTRY_HEADER + name_length];
+                    let kind = if file_type { entry[7] } else { 0 };
+                    entries.push((String::from_utf8_lossy(name).into_owned(), ino, kind));
+                }
+                offset += length;
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+impl Inode for Ext2Inode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        Ok(Metadata {
+            ino: self.ino as u64,
+            kind: self.raw.kind(),
+            size: self.size(),
+            mode: self.raw.mode & 0o7777,
+            links: self.raw.links as u32,
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        if self.raw.kind() != InodeType::File {
+            return Err(VfsError::NotSupported);
+        }
+
+        let size = self.size();
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let length = buffer.len().min((size - offset) as usize);
+        self.volume
+            .read_data(&self.raw, offset, &mut buffer[..length])?;
+        Ok(length)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        let (_, ino, _) = self
+            .entries()?
+            .into_iter()
+            .find(|(entry, _, _)| entry == name)
+            .ok_or(VfsError::NotFound)?;
+        Ok(Rc::new(Ext2Inode::new(self.volume.clone(), ino)?))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        self.entries()?
+            .into_iter()
+            .filter(|(name, _, _)| name != "." && name != "..")
+            .map(|(name, ino, kind)| {
+                let kind = match kind {
+                    1 => InodeType::File,
+                    2 => InodeType::Directory,
+                    3 => InodeType::CharDevice,
+                    4 => InodeType::BlockDevice,
+                    7 => InodeType::Symlink,
+                    // the type is unknown without the file type feature
+                    _ => self.volume.inode(ino)?.kind(),
+                };
+                Ok(DirEntry {
+                    name,
+                    ino: ino as u64,
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    fn read_link(&self) -> Result<String, VfsError> {
+        if self.raw.kind() != InodeType::Symlink {
+            return Err(VfsError::NotSymlink);
+        }
+
+        let size = self.size();
+        let acl_sectors = match self.raw.file_acl {
+            0 => 0,
+            _ => self.volume.block_size() as u32 / 512,
+        };
+
+        let target = if size < FAST_SYMLINK_MAX && self.raw.sectors == acl_sectors {
+            self.raw
+                .blocks
+                .iter()
+                .flat_map(|block| block.to_le_bytes())
+                .take(size as usize)
+                .collect()
+        } else {
+            let mut target = vec![0; size as usize];
+            self.volume.read_data(&self.raw, 0, &mut target)?;
+            target
+        };
+
+        Ok(String::from_utf8_lossy(&target).into_owned())
+    }
+}
@@ -0,0 +1,48 @@
+#![no_std]
+
+//! Read-only ext2 filesystem on top of a [`vfs::block::BlockDevice`]. Files are located through
+//! the direct and indirect block maps of their inodes, symbolic links are supported.
+//!
+//! The filesystem does not depend on the kernel, it is tested on the host against images created
+//! by `mke2fs`.
+
+use alloc::rc::Rc;
+use error::Ext2Error;
+use inode::{Ext2Inode, ROOT_INO};
+use vfs::{
+    block::BlockDevice,
+    error::VfsError,
+    inode::{FileSystem, Inode},
+};
+use volume::Volume;
+
+pub mod error;
+mod inode;
+mod volume;
+
+extern crate alloc;
+
+/// Mounted ext2 volume.
+#[derive(Debug)]
+pub struct Ext2FileSystem {
+    volume: Rc<Volume>,
+}
+
+impl Ext2FileSystem {
+    /// Mounts the volume stored on the device, whose superblock is located at byte 1024.
+    pub fn new(device: Rc<dyn BlockDevice>) -> Result<Ext2FileSystem, Ext2Error> {
+        Ok(Ext2FileSystem {
+            volume: Rc::new(Volume::new(device)?),
+        })
+    }
+}
+
+impl FileSystem for Ext2FileSystem {
+    fn name(&self) -> &str {
+        "ext2"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(Rc::new(Ext2Inode::new(self.volume.clone(), ROOT_INO)?))
+    }
+}
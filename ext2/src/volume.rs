@@ -0,0 +1,255 @@
+use core::fmt::Debug;
+
+use alloc::{rc::Rc, vec, vec::Vec};
+use vfs::{block::BlockDevice, error::VfsError};
+
+use crate::{error::Ext2Error, inode::RawInode};
+
+/// Offset of the superblock from the start of the device.
+const SUPERBLOCK_OFFSET: u64 = 1024;
+const SUPERBLOCK_SIZE: usize = 1024;
+const MAGIC: u16 = 0xef53;
+
+/// Revision with fixed inode sizes and without feature flags.
+const GOOD_OLD_REVISION: u32 = 0;
+const DYNAMIC_REVISION: u32 = 1;
+const GOOD_OLD_INODE_SIZE: u16 = 128;
+
+/// Directory entries store the type of the file.
+pub(crate) const INCOMPAT_FILETYPE: u32 = 0x0002;
+/// Incompatible features that do not affect reading.
+const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE;
+/// Regular files store the upper 32 bits of their size.
+const RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+
+const GROUP_DESCRIPTOR_SIZE: usize = 32;
+
+/// Number of block pointers stored directly in an inode.
+pub(crate) const DIRECT_BLOCKS: usize = 12;
+const INDIRECT_BLOCK: usize = 12;
+const DOUBLE_INDIRECT_BLOCK: usize = 13;
+const TRIPLE_INDIRECT_BLOCK: usize = 14;
+
+/// Fields of the superblock used for reading.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Superblock {
+    pub(crate) inodes: u32,
+    pub(crate) blocks: u32,
+    pub(crate) first_data_block: u32,
+    pub(crate) block_size: u32,
+    pub(crate) blocks_per_group: u32,
+    pub(crate) inodes_per_group: u32,
+    pub(crate) inode_size: u16,
+    pub(crate) incompat: u32,
+    pub(crate) ro_compat: u32,
+}
+
+impl Superblock {
+    fn read(device: &dyn BlockDevice) -> Result<Superblock, Ext2Error> {
+        let mut block = [0; SUPERBLOCK_SIZE];
+        device.read_at(SUPERBLOCK_OFFSET, &mut block)?;
+
+        let u16_at = |offset: usize| u16::from_le_bytes([block[offset], block[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap_or_default())
+        };
+
+        let magic = u16_at(56);
+        if magic != MAGIC {
+            return Err(Ext2Error::InvalidMagic(magic));
+        }
+
+        let revision = u32_at(76);
+        let (inode_size, incompat, ro_compat) = match revision {
+            GOOD_OLD_REVISION => (GOOD_OLD_INODE_SIZE, 0, 0),
+            DYNAMIC_REVISION => (u16_at(88), u32_at(96), u32_at(100)),
+            _ => return Err(Ext2Error::UnsupportedRevision(revision)),
+        };
+        if incompat & !INCOMPAT_SUPPORTED != 0 {
+            return Err(Ext2Error::UnsupportedFeatures(
+                incompat & !INCOMPAT_SUPPORTED,
+            ));
+        }
+
+        let log_block_size = u32_at(24);
+        if log_block_size > 6 {
+            return Err(Ext2Error::InvalidParameter("block size"));
+        }
+        if inode_size < GOOD_OLD_INODE_SIZE || !inode_size.is_power_of_two() {
+            return Err(Ext2Error::InvalidParameter("inode size"));
+        }
+
+        let superblock = Superblock {
+            inodes: u32_at(0),
+            blocks: u32_at(4),
+            first_data_block: u32_at(20),
+            block_size: 1024 << log_block_size,
+            blocks_per_group: u32_at(32),
+            inodes_per_group: u32_at(40),
+            inode_size,
+            incompat,
+            ro_compat,
+        };
+        if superblock.blocks_per_group == 0 || superblock.inodes_per_group == 0 {
+            return Err(Ext2Error::InvalidParameter("blocks or inodes per group"));
+        }
+        if superblock.blocks as u64 * superblock.block_size as u64 > device.size() {
+            return Err(Ext2Error::InvalidParameter("block count"));
+        }
+
+        Ok(superblock)
+    }
+
+    pub(crate) fn has_large_files(&self) -> bool {
+        self.ro_compat & RO_COMPAT_LARGE_FILE != 0
+    }
+}
+
+/// Mounted volume, which reads inodes and maps their blocks.
+pub(crate) struct Volume {
+    device: Rc<dyn BlockDevice>,
+    superblock: Superblock,
+    /// First block of the inode table of each block group.
+    inode_tables: Vec<u32>,
+}
+
+impl Debug for Volume {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Volume")
+            .field("superblock", &self.superblock)
+            .finish()
+    }
+}
+
+impl Volume {
+    pub(crate) fn new(device: Rc<dyn BlockDevice>) -> Result<Volume, Ext2Error> {
+        let superblock = Superblock::read(device.as_ref())?;
+
+        let groups = superblock.inodes.div_ceil(superblock.inodes_per_group) as usize;
+        let mut descriptors = vec![0; groups * GROUP_DESCRIPTOR_SIZE];
+        // the descriptor table follows the block containing the superblock
+        let table = (superblock.first_data_block as u64 + 1) * superblock.block_size as u64;
+        device.read_at(table, &mut descriptors)?;
+
+        let inode_tables = descriptors
+            .chunks_exact(GROUP_DESCRIPTOR_SIZE)
+            .map(|descriptor| u32::from_le_bytes(descriptor[8..12].try_into().unwrap_or_default()))
+            .collect::<Vec<_>>();
+        if inode_tables
+            .iter()
+            .any(|block| *block == 0 || *block >= superblock.blocks)
+        {
+            return Err(Ext2Error::InvalidParameter("inode table"));
+        }
+
+        Ok(Volume {
+            device,
+            superblock,
+            inode_tables,
+        })
+    }
+
+    pub(crate) fn superblock(&self) -> &Superblock {
+        &self.superblock
+    }
+
+    pub(crate) fn block_size(&self) -> usize {
+        self.superblock.block_size as usize
+    }
+
+    /// Reads the inode with the number, which starts at 1.
+    pub(crate) fn inode(&self, ino: u32) -> Result<RawInode, VfsError> {
+        if ino == 0 || ino > self.superblock.inodes {
+            return Err(VfsError::NotFound);
+        }
+
+        let group = ((ino - 1) / self.superblock.inodes_per_group) as usize;
+        let index = ((ino - 1) % self.superblock.inodes_per_group) as u64;
+        let table = *self.inode_tables.get(group).ok_or(VfsError::Io)?;
+        let offset = table as u64 * self.superblock.block_size as u64
+            + index * self.superblock.inode_size as u64;
+
+        let mut raw = [0; GOOD_OLD_INODE_SIZE as usize];
+        self.device.read_at(offset, &mut raw)?;
+        Ok(RawInode::parse(&raw))
+    }
+
+    /// Reads the block into the buffer, which has the size of a block. Block 0 denotes a hole and
+    /// reads as zeros.
+    pub(crate) fn read_block(&self, block: u32, buffer: &mut [u8]) -> Result<(), VfsError> {
+        if block == 0 {
+            buffer.fill(0);
+            return Ok(());
+        }
+        if block >= self.superblock.blocks {
+            return Err(VfsError::Io);
+        }
+        self.device
+            .read_at(block as u64 * self.superblock.block_size as u64, buffer)
+    }
+
+    /// Returns the block storing the logical block `index` of the inode, which is 0 for holes.
+    pub(crate) fn map_block(&self, inode: &RawInode, index: u64) -> Result<u32, VfsError> {
+        let per_block = (self.superblock.block_size / 4) as u64;
+
+        if index < DIRECT_BLOCKS as u64 {
+            return Ok(inode.blocks[index as usize]);
+        }
+
+        // levels of indirection and the index relative to the first block of the level
+        let mut index = index - DIRECT_BLOCKS as u64;
+        let (level, root) = if index < per_block {
+            (1, inode.blocks[INDIRECT_BLOCK])
+        } else if index - per_block < per_block * per_block {
+            index -= per_block;
+            (2, inode.blocks[DOUBLE_INDIRECT_BLOCK])
+        } else if index - per_block - per_block * per_block < per_block.pow(3) {
+            index -= per_block + per_block * per_block;
+            (3, inode.blocks[TRIPLE_INDIRECT_BLOCK])
+        } else {
+            return Err(VfsError::InvalidArgument);
+        };
+
+        let mut block = root;
+        for depth in (0..level).rev() {
+            if block == 0 {
+                return Ok(0);
+            }
+            let slot = (index / per_block.pow(depth)) % per_block;
+            let mut pointer = [0; 4];
+            if block >= self.superblock.blocks {
+                return Err(VfsError::Io);
+            }
+            self.device.read_at(
+                block as u64 * self.superblock.block_size as u64 + slot * 4,
+                &mut pointer,
+            )?;
+            block = u32::from_le_bytes(pointer);
+        }
+        Ok(block)
+    }
+
+    /// Reads the data of the inode starting at `offset` into the buffer, which must lie within
+    /// the size of the inode.
+    pub(crate) fn read_data(
+        &self,
+        inode: &RawInode,
+        offset: u64,
+        buffer: &mut [u8],
+    ) -> Result<(), VfsError> {
+        let block_size = self.block_size();
+        let mut block = vec![0; block_size];
+        let mut done = 0;
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let within = (position % block_size as u64) as usize;
+            let count = (buffer.len() - done).min(block_size - within);
+
+            let physical = self.map_block(inode, position / block_size as u64)?;
+            self.read_block(physical, &mut block)?;
+            buffer[done..done + count].copy_from_slice(&block[within..within + count]);
+            done += count;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! Reads images created by `mke2fs` from a directory tree, which must be installed on the host.
+
+use std::{
+    fs,
+    os::unix,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
+
+use ext2::Ext2FileSystem;
+use vfs::{
+    block::MemoryDevice, error::VfsError, file::OpenFlags, inode::InodeType, mount::MountTable,
+};
+
+const IMAGE_SIZE: &str = "8M";
+const LONG_TARGET: &str = "directory/with/a/rather/long/name/to/not/fit/into/the/inode/target";
+
+fn pattern(length: usize, seed: u8) -> Vec<u8> {
+    (0..length)
+        .map(|index| (index as u8).wrapping_mul(31).wrapping_add(seed))
+        .collect()
+}
+
+/// Creates the directory tree the images are populated with.
+fn populate(root: &Path) {
+    fs::create_dir_all(root.join(LONG_TARGET)).unwrap();
+    fs::write(root.join(LONG_TARGET).join("end"), b"end").unwrap();
+    fs::write(root.join("hello.txt"), b"Hello, world!\n").unwrap();
+    fs::write(root.join("empty"), b"").unwrap();
+    // large enough for double indirect blocks with 1 KiB blocks
+    fs::write(root.join("large.bin"), pattern(400 * 1024 + 123, 7)).unwrap();
+
+    fs::create_dir(root.join("many")).unwrap();
+    for index in 0..200 {
+        fs::write(
+            root.join(format!("many/file-{index}")),
+            pattern(index, index as u8),
+        )
+        .unwrap();
+    }
+
+    unix::fs::symlink("hello.txt", root.join("fast")).unwrap();
+    unix::fs::symlink(LONG_TARGET, root.join("slow")).unwrap();
+    unix::fs::symlink("../hello.txt", root.join("many/up")).unwrap();
+}
+
+/// Creates an image with the block size from the directory tree. Returns `None` if `mke2fs` is
+/// not installed.
+fn image(name: &str, block_size: usize) -> Option<(PathBuf, MountTable)> {
+    let directory = std::env::temp_dir().join(format!("ext2-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    let tree = directory.join("tree");
+    fs::create_dir_all(&tree).unwrap();
+    populate(&tree);
+
+    let image = directory.join("image");
+    let status = Command::new("mke2fs")
+        .args([
+            "-q",
+            "-F",
+            "-t",
+            "ext2",
+            "-b",
+            &block_size.to_string(),
+            "-d",
+        ])
+        .arg(&tree)
+        .arg(&image)
+        .arg(IMAGE_SIZE)
+        .status();
+    match status {
+        Ok(status) => assert!(status.success(), "mke2fs failed: {status}"),
+        Err(err) => {
+            eprintln!("skipping ext2 test, mke2fs is not available: {err}");
+            return None;
+        }
+    }
+
+    let device = MemoryDevice::new(fs::read(&image).unwrap(), 512).unwrap();
+    let fs = Ext2FileSystem::new(Rc::new(device)).unwrap();
+    let mut vfs = MountTable::new();
+    vfs.mount("/", Rc::new(fs)).unwrap();
+    Some((directory, vfs))
+}
+
+fn check(name: &str, block_size: usize) {
+    let Some((directory, vfs)) = image(name, block_size) else {
+        return;
+    };
+
+    assert_eq!(vfs.read_to_end("/hello.txt").unwrap(), b"Hello, world!\n");
+    assert_eq!(vfs.read_to_end("/empty").unwrap(), b"");
+    assert_eq!(
+        vfs.read_to_end("/large.bin").unwrap(),
+        pattern(400 * 1024 + 123, 7)
+    );
+
+    let mut names: Vec<String> = vfs
+        .read_dir("/")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "directory",
+            "empty",
+            "fast",
+            "hello.txt",
+            "large.bin",
+            "lost+found",
+            "many",
+            "slow"
+        ]
+    );
+
+    let entries = vfs.read_dir("/many").unwrap();
+    assert_eq!(entries.len(), 201);
+    for index in 0..200 {
+        let path = format!("/many/file-{index}");
+        assert_eq!(vfs.read_to_end(&path).unwrap(), pattern(index, index as u8));
+    }
+
+    let metadata = vfs.resolve_link("/slow").unwrap().metadata().unwrap();
+    assert_eq!(metadata.kind, InodeType::Symlink);
+    assert_eq!(metadata.size, LONG_TARGET.len() as u64);
+    assert_eq!(
+        vfs.resolve_link("/slow").unwrap().read_link().unwrap(),
+        LONG_TARGET
+    );
+    assert_eq!(vfs.read_to_end("/slow/end").unwrap(), b"end");
+    assert_eq!(vfs.read_to_end("/fast").unwrap(), b"Hello, world!\n");
+    assert_eq!(vfs.read_to_end("/many/up").unwrap(), b"Hello, world!\n");
+
+    let metadata = vfs.resolve("/many").unwrap().metadata().unwrap();
+    assert_eq!(metadata.kind, InodeType::Directory);
+    assert_eq!(vfs.resolve("/missing").err(), Some(VfsError::NotFound));
+
+    let mut file = vfs
+        .open("/hello.txt", OpenFlags::READ | OpenFlags::WRITE)
+        .unwrap();
+    assert_eq!(file.write(b"x"), Err(VfsError::ReadOnly));
+    assert_eq!(
+        vfs.create("/new", InodeType::File).err(),
+        Some(VfsError::ReadOnly)
+    );
+    assert_eq!(vfs.unlink("/hello.txt"), Err(VfsError::ReadOnly));
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn block_size_1024() {
+    check("1024", 1024);
+}
+
+#[test]
+fn block_size_4096() {
+    check("4096", 4096);
+}
@@ -0,0 +1,184 @@
+use vfs::block::BlockDevice;
+
+use crate::{dir::Directory, error::FatError};
+
+/// Size of the boot sector, which is read before the sector size is known.
+const BOOT_SECTOR_SIZE: usize = 512;
+const SIGNATURE: [u8; 2] = [0x55, 0xaa];
+
+/// Volumes with less clusters use FAT12, see the FAT specification.
+const FAT12_CLUSTERS_MAX: u32 = 4084;
+/// Volumes with less clusters use FAT16, see the FAT specification.
+const FAT16_CLUSTERS_MAX: u32 = 65524;
+
+/// Size of an entry of the file allocation table, which depends on the number of clusters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    /// Size of a table entry in nibbles.
+    fn entry_nibbles(self) -> u32 {
+        match self {
+            FatType::Fat12 => 3,
+            FatType::Fat16 => 4,
+            FatType::Fat32 => 8,
+        }
+    }
+}
+
+/// Geometry of a volume parsed from its BIOS parameter block. All offsets are in bytes from the
+/// start of the device.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Layout {
+    pub(crate) fat_type: FatType,
+    pub(crate) cluster_size: u32,
+    /// Number of data clusters, which are numbered starting at 2.
+    pub(crate) clusters: u32,
+    /// Offset of the first file allocation table.
+    pub(crate) fat_offset: u64,
+    /// Size of a file allocation table.
+    pub(crate) fat_size: u64,
+    pub(crate) fat_count: u8,
+    /// Table read from, if the tables are not mirrored.
+    pub(crate) active_fat: Option<u8>,
+    pub(crate) root: Directory,
+    /// Offset of cluster 2.
+    pub(crate) data_offset: u64,
+    /// Offset of the FSInfo sector of FAT32 volumes.
+    pub(crate) fs_info: Option<u64>,
+}
+
+impl Layout {
+    /// Reads the BIOS parameter block from the boot sector of the device.
+    pub(crate) fn read(device: &dyn BlockDevice) -> Result<Layout, FatError> {
+        let mut sector = [0; BOOT_SECTOR_SIZE];
+        device.read_at(0, &mut sector)?;
+        if sector[510..512] != SIGNATURE {
+            return Err(FatError::MissingSignature);
+        }
+
+        let u16_at = |offset: usize| u16::from_le_bytes([sector[offset], sector[offset + 1]]);
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap_or_default())
+        };
+
+        let sector_size = u16_at(11) as u32;
+        if !matches!(sector_size, 512 | 1024 | 2048 | 4096) {
+            return Err(FatError::InvalidParameter("bytes per sector"));
+        }
+        let sectors_per_cluster = sector[13] as u32;
+        if !sectors_per_cluster.is_power_of_two() {
+            return Err(FatError::InvalidParameter("sectors per cluster"));
+        }
+        let reserved = u16_at(14) as u32;
+        if reserved == 0 {
+            return Err(FatError::InvalidParameter("reserved sector count"));
+        }
+        let fat_count = sector[16];
+        if fat_count == 0 {
+            return Err(FatError::InvalidParameter("number of FATs"));
+        }
+        let root_entries = u16_at(17) as u32;
+        let total_sectors = match u16_at(19) {
+            0 => u32_at(32),
+            count => count as u32,
+        };
+        let fat_sectors = match u16_at(22) {
+            0 => u32_at(36),
+            count => count as u32,
+        };
+        if fat_sectors == 0 {
+            return Err(FatError::InvalidParameter("FAT size"));
+        }
+
+        let root_sectors = (root_entries * 32).div_ceil(sector_size);
+        let fat_end = reserved as u64 + fat_count as u64 * fat_sectors as u64;
+        let data_sector = fat_end + root_sectors as u64;
+        let clusters = (total_sectors as u64)
+            .checked_sub(data_sector)
+            .ok_or(FatError::InvalidParameter("total sector count"))?
+            / sectors_per_cluster as u64;
+
+        let fat_type = if clusters <= FAT12_CLUSTERS_MAX as u64 {
+            FatType::Fat12
+        } else if clusters <= FAT16_CLUSTERS_MAX as u64 {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        };
+        // clusters without an entry in the table cannot be used
+        let entries = fat_sectors as u64 * sector_size as u64 * 2 / fat_type.entry_nibbles() as u64;
+        let clusters = clusters.min(entries.saturating_sub(2)) as u32;
+
+        let sector_size_64 = sector_size as u64;
+        let (root, active_fat, fs_info) = match fat_type {
+            FatType::Fat32 => {
+                if root_entries != 0 {
+                    return Err(FatError::InvalidParameter("root entry count"));
+                }
+                let flags = u16_at(40);
+                let active_fat = (flags & 0x80 != 0).then_some((flags & 0xf) as u8);
+                let fs_info = match u16_at(48) {
+                    0 | 0xffff => None,
+                    sector => Some(sector as u64 * sector_size_64),
+                };
+                (Directory::Chain(u32_at(44)), active_fat, fs_info)
+            }
+            _ => {
+                if root_entries == 0 {
+                    return Err(FatError::InvalidParameter("root entry count"));
+                }
+                (
+                    Directory::Fixed {
+                        offset: fat_end * sector_size_64,
+                        entries: root_entries,
+                    },
+                    None,
+                    None,
+                )
+            }
+        };
+
+        if active_fat.is_some_and(|fat| fat >= fat_count) {
+            return Err(FatError::InvalidParameter("active FAT"));
+        }
+        if total_sectors as u64 * sector_size_64 > device.size() {
+            return Err(FatError::InvalidParameter("total sector count"));
+        }
+
+        let layout = Layout {
+            fat_type,
+            cluster_size: sector_size * sectors_per_cluster,
+            clusters,
+            fat_offset: reserved as u64 * sector_size_64,
+            fat_size: fat_sectors as u64 * sector_size_64,
+            fat_count,
+            active_fat,
+            root,
+            data_offset: data_sector * sector_size_64,
+            fs_info,
+        };
+
+        if let Directory::Chain(cluster) = root
+            && !layout.is_valid(cluster)
+        {
+            return Err(FatError::InvalidParameter("root cluster"));
+        }
+
+        Ok(layout)
+    }
+
+    /// Whether the cluster is a data cluster of the volume.
+    pub(crate) fn is_valid(&self, cluster: u32) -> bool {
+        (2..self.clusters + 2).contains(&cluster)
+    }
+
+    /// Returns the offset of the data cluster.
+    pub(crate) fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_offset + (cluster - 2) as u64 * self.cluster_size as u64
+    }
+}
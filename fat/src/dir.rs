@@ -0,0 +1,538 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use bitflags::bitflags;
+use vfs::error::VfsError;
+
+use crate::volume::Volume;
+
+/// Size of a directory entry.
+pub(crate) const ENTRY_SIZE: usize = 32;
+/// Number of UTF-16 code units stored in a long name entry.
+const LONG_NAME_UNITS: usize = 13;
+/// Maximum length of a long name in UTF-16 code units.
+const LONG_NAME_MAX: usize = 255;
+/// Flag of the sequence number of the last long name entry of a name.
+const LAST_LONG_ENTRY: u8 = 0x40;
+
+/// Attribute bits compared to detect a long name entry.
+const LONG_NAME_MASK: u8 = 0x3f;
+
+/// First byte of the name of a deleted entry.
+const DELETED: u8 = 0xe5;
+/// First byte of the name of the first unused entry, all following entries are unused as well.
+const END: u8 = 0x00;
+/// Stored instead of [`DELETED`] as the first byte of a name actually starting with 0xe5.
+const KANJI_E5: u8 = 0x05;
+
+/// Case flags of the short name, as used by Windows NT for names that are lowercase only.
+const LOWERCASE_BASE: u8 = 0x08;
+const LOWERCASE_EXTENSION: u8 = 0x10;
+
+/// Date stored for new entries, there is no clock available to the filesystem (1980-01-01).
+const DEFAULT_DATE: u16 = (1 << 5) | 1;
+
+/// Characters allowed in a short name besides uppercase letters and digits.
+const SHORT_NAME_SPECIAL: &[u8] = b"$%'-_@~`!(){}^#&";
+/// Characters not allowed in a long name besides control characters.
+const LONG_NAME_INVALID: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+
+bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub(crate) struct Attributes: u8 {
+        const READ_ONLY = 0x01;
+        const HIDDEN = 0x02;
+        const SYSTEM = 0x04;
+        const VOLUME_ID = 0x08;
+        const DIRECTORY = 0x10;
+        const ARCHIVE = 0x20;
+        /// Combination marking a long name entry.
+        const LONG_NAME = 0x0f;
+    }
+}
+
+/// Directory stored either in the fixed root directory region or in a cluster chain.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Directory {
+    /// Root directory of FAT12 and FAT16 volumes.
+    Fixed { offset: u64, entries: u32 },
+    /// Directory starting at the cluster.
+    Chain(u32),
+}
+
+/// Short directory entry, which holds everything about a file but its long name.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ShortEntry {
+    /// Base name and extension, padded with spaces.
+    pub(crate) name: [u8; 11],
+    pub(crate) attributes: Attributes,
+    /// Case flags of the name.
+    pub(crate) case: u8,
+    pub(crate) first_cluster: u32,
+    pub(crate) size: u32,
+}
+
+impl ShortEntry {
+    pub(crate) fn new(name: [u8; 11], attributes: Attributes, first_cluster: u32) -> ShortEntry {
+        ShortEntry {
+            name,
+            attributes,
+            case: 0,
+            first_cluster,
+            size: 0,
+        }
+    }
+
+    pub(crate) fn parse(slot: &[u8]) -> ShortEntry {
+        let u16_at = |offset: usize| u16::from_le_bytes([slot[offset], slot[offset + 1]]);
+        let mut name = [0; 11];
+        name.copy_from_slice(&slot[..11]);
+
+        ShortEntry {
+            name,
+            attributes: Attributes::from_bits_retain(slot[11]),
+            case: slot[12],
+            first_cluster: (u16_at(20) as u32) << 16 | u16_at(26) as u32,
+            size: u32::from_le_bytes([slot[28], slot[29], slot[30], slot[31]]),
+        }
+    }
+
+    /// Returns the entry as stored on the device, using [`DEFAULT_DATE`] for all dates.
+    pub(crate) fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut slot = [0; ENTRY_SIZE];
+        slot[..11].copy_from_slice(&self.name);
+        slot[11] = self.attributes.bits();
+        slot[12] = self.case;
+        for offset in [16, 18, 24] {
+            slot[offset..offset + 2].copy_from_slice(&DEFAULT_DATE.to_le_bytes());
+        }
+        self.patch(&mut slot);
+        slot
+    }
+
+    /// Updates the attributes, the first cluster and the size of the stored entry, keeping its
+    /// name and dates.
+    pub(crate) fn patch(self, slot: &mut [u8]) {
+        slot[11] = self.attributes.bits();
+        slot[20..22].copy_from_slice(&((self.first_cluster >> 16) as u16).to_le_bytes());
+        slot[26..28].copy_from_slice(&(self.first_cluster as u16).to_le_bytes());
+        slot[28..32].copy_from_slice(&self.size.to_le_bytes());
+    }
+
+    pub(crate) fn is_directory(&self) -> bool {
+        self.attributes.contains(Attributes::DIRECTORY)
+    }
+
+    /// Returns the name as displayed, e.g. `README.TXT`.
+    pub(crate) fn display_name(&self) -> String {
+        let mut name = self.name;
+        if name[0] == KANJI_E5 {
+            name[0] = DELETED;
+        }
+
+        let decode = |bytes: &[u8], lowercase: bool| {
+            bytes
+                .iter()
+                .map(|byte| {
+                    if lowercase {
+                        byte.to_ascii_lowercase() as char
+                    } else {
+                        *byte as char
+                    }
+                })
+                .collect::<String>()
+        };
+
+        let base = trim_padding(&name[..8]);
+        let extension = trim_padding(&name[8..]);
+        let mut display = decode(base, self.case & LOWERCASE_BASE != 0);
+        if !extension.is_empty() {
+            display.push('.');
+            display.push_str(&decode(extension, self.case & LOWERCASE_EXTENSION != 0));
+        }
+        display
+    }
+
+    /// Whether this is the `.` or `..` entry of a directory.
+    fn is_dot(&self) -> bool {
+        self.name == *b".          " || self.name == *b"..         "
+    }
+}
+
+/// File found in a directory.
+#[derive(Clone, Debug)]
+pub(crate) struct DirItem {
+    /// Long name, or the short name if there is none.
+    pub(crate) name: String,
+    pub(crate) entry: ShortEntry,
+    /// Offsets of all entries of the file, the short entry comes last.
+    pub(crate) slots: Vec<u64>,
+}
+
+impl DirItem {
+    /// Returns the offset of the short entry on the device.
+    pub(crate) fn offset(&self) -> u64 {
+        self.slots.last().copied().unwrap_or_default()
+    }
+}
+
+/// Long name being assembled from its entries, which are stored in reverse order before the
+/// short entry.
+struct LongName {
+    units: Vec<u16>,
+    checksum: u8,
+    /// Sequence number of the entry expected next.
+    next: u8,
+    slots: Vec<u64>,
+}
+
+/// Returns the files of the directory, excluding `.`, `..` and the volume label.
+pub(crate) fn read(volume: &Volume, directory: Directory) -> Result<Vec<DirItem>, VfsError> {
+    let mut items = Vec::new();
+    let mut long_name: Option<LongName> = None;
+
+    for (offset, length) in regions(volume, directory)? {
+        let mut buffer = vec![0; length];
+        volume.read(offset, &mut buffer)?;
+
+        for (index, slot) in buffer.chunks_exact(ENTRY_SIZE).enumerate() {
+            let slot_offset = offset + (index * ENTRY_SIZE) as u64;
+            match slot[0] {
+                END => return Ok(items),
+                DELETED => {
+                    long_name = None;
+                    continue;
+                }
+                _ => {}
+            }
+
+            if slot[11] & LONG_NAME_MASK == Attributes::LONG_NAME.bits() {
+                long_name = continue_long_name(long_name.take(), slot, slot_offset);
+                continue;
+            }
+
+            let entry = ShortEntry::parse(slot);
+            let long_name = long_name.take();
+            if entry.attributes.contains(Attributes::VOLUME_ID) || entry.is_dot() {
+                continue;
+            }
+
+            let checksum = checksum(&entry.name);
+            let (name, mut slots) = match long_name {
+                Some(long_name) if long_name.next == 0 && long_name.checksum == checksum => {
+                    let length = long_name
+                        .units
+                        .iter()
+                        .position(|unit| *unit == 0)
+                        .unwrap_or(long_name.units.len());
+                    let name = char::decode_utf16(long_name.units[..length].iter().copied())
+                        .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect();
+                    (name, long_name.slots)
+                }
+                _ => (entry.display_name(), Vec::new()),
+            };
+            slots.push(slot_offset);
+
+            items.push(DirItem { name, entry, slots });
+        }
+    }
+
+    Ok(items)
+}
+
+/// Returns the file of the directory with the name, which is compared case-insensitively.
+pub(crate) fn find(
+    volume: &Volume,
+    directory: Directory,
+    name: &str,
+) -> Result<Option<DirItem>, VfsError> {
+    Ok(read(volume, directory)?.into_iter().find(|item| {
+        eq_ignore_case(&item.name, name) || eq_ignore_case(&item.entry.display_name(), name)
+    }))
+}
+
+/// Adds an entry for the new file `name` to the directory, which is extended if necessary.
+/// Stores a long name, unless the name is a valid short name. Returns the offset of the short
+/// entry.
+pub(crate) fn insert(
+    volume: &Volume,
+    directory: Directory,
+    name: &str,
+    mut entry: ShortEntry,
+) -> Result<u64, VfsError> {
+    validate(name)?;
+    let items = read(volume, directory)?;
+    if items.iter().any(|item| {
+        eq_ignore_case(&item.name, name) || eq_ignore_case(&item.entry.display_name(), name)
+    }) {
+        return Err(VfsError::AlreadyExists);
+    }
+
+    let long_entries = match exact_short_name(name) {
+        Some(short) => {
+            entry.name = short;
+            Vec::new()
+        }
+        None => {
+            entry.name = generate_short_name(name, &items)?;
+            long_entries(name, checksum(&entry.name))
+        }
+    };
+
+    let slots = free_slots(volume, directory, long_entries.len() + 1)?;
+    for (slot, bytes) in slots
+        .iter()
+        .zip(long_entries.iter().chain([&entry.to_bytes()]))
+    {
+        volume.write(*slot, bytes)?;
+    }
+    Ok(slots[long_entries.len()])
+}
+
+/// Marks all entries of the file as deleted.
+pub(crate) fn remove(volume: &Volume, item: &DirItem) -> Result<(), VfsError> {
+    for slot in &item.slots {
+        volume.write(*slot, &[DELETED])?;
+    }
+    Ok(())
+}
+
+/// Writes the `.` and `..` entries to the first cluster of a new directory. The parent is 0 for
+/// the root directory.
+pub(crate) fn initialize(volume: &Volume, cluster: u32, parent: u32) -> Result<(), VfsError> {
+    let dot = ShortEntry::new(*b".          ", Attributes::DIRECTORY, cluster);
+    let dot_dot = ShortEntry::new(*b"..         ", Attributes::DIRECTORY, parent);
+
+    let offset = volume.layout().cluster_offset(cluster);
+    volume.write(offset, &dot.to_bytes())?;
+    volume.write(offset + ENTRY_SIZE as u64, &dot_dot.to_bytes())
+}
+
+/// Returns the size of the directory in bytes.
+pub(crate) fn size(volume: &Volume, directory: Directory) -> Result<u64, VfsError> {
+    Ok(regions(volume, directory)?
+        .iter()
+        .map(|(_, length)| *length as u64)
+        .sum())
+}
+
+/// Returns the offsets and lengths of the regions storing the entries of the directory.
+fn regions(volume: &Volume, directory: Directory) -> Result<Vec<(u64, usize)>, VfsError> {
+    Ok(match directory {
+        Directory::Fixed { offset, entries } => vec![(offset, entries as usize * ENTRY_SIZE)],
+        Directory::Chain(first) => {
+            let cluster_size = volume.layout().cluster_size as usize;
+            volume
+                .chain(first)?
+                .into_iter()
+                .map(|cluster| (volume.layout().cluster_offset(cluster), cluster_size))
+                .collect()
+        }
+    })
+}
+
+/// Returns the offsets of `count` consecutive unused entries of the directory. Directories in a
+/// cluster chain are extended by zeroed clusters if necessary.
+fn free_slots(volume: &Volume, directory: Directory, count: usize) -> Result<Vec<u64>, VfsError> {
+    let mut slots = Vec::with_capacity(count);
+    for (offset, length) in regions(volume, directory)? {
+        let mut buffer = vec![0; length];
+        volume.read(offset, &mut buffer)?;
+
+        for (index, slot) in buffer.chunks_exact(ENTRY_SIZE).enumerate() {
+            if slot[0] == END || slot[0] == DELETED {
+                slots.push(offset + (index * ENTRY_SIZE) as u64);
+                if slots.len() == count {
+                    return Ok(slots);
+                }
+            } else {
+                slots.clear();
+            }
+        }
+    }
+
+    let Directory::Chain(first) = directory else {
+        return Err(VfsError::NoSpace);
+    };
+
+    let layout = volume.layout();
+    let mut last = volume.chain(first)?.last().copied();
+    while slots.len() < count {
+        let cluster = volume.allocate(last)?;
+        let offset = layout.cluster_offset(cluster);
+        let entries = layout.cluster_size as usize / ENTRY_SIZE;
+        slots.extend(
+            (0..entries.min(count - slots.len())).map(|index| offset + (index * ENTRY_SIZE) as u64),
+        );
+        last = Some(cluster);
+    }
+    Ok(slots)
+}
+
+/// Adds the long name entry to the long name being assembled. Returns `None` if the entries are
+/// inconsistent, in which case the long name is ignored.
+fn continue_long_name(long_name: Option<LongName>, slot: &[u8], offset: u64) -> Option<LongName> {
+    let sequence = slot[0] & !LAST_LONG_ENTRY;
+    let checksum = slot[13];
+
+    if sequence == 0 || sequence as usize * LONG_NAME_UNITS > LONG_NAME_MAX + LONG_NAME_UNITS {
+        return None;
+    }
+
+    let mut long_name = match long_name {
+        _ if slot[0] & LAST_LONG_ENTRY != 0 => LongName {
+            units: vec![0; sequence as usize * LONG_NAME_UNITS],
+            checksum,
+            next: sequence,
+            slots: Vec::new(),
+        },
+        Some(long_name) if long_name.next == sequence && long_name.checksum == checksum => {
+            long_name
+        }
+        _ => return None,
+    };
+
+    let units = slot[1..11]
+        .chunks_exact(2)
+        .chain(slot[14..26].chunks_exact(2))
+        .chain(slot[28..32].chunks_exact(2))
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+    let start = (sequence as usize - 1) * LONG_NAME_UNITS;
+    for (target, unit) in long_name.units[start..start + LONG_NAME_UNITS]
+        .iter_mut()
+        .zip(units)
+    {
+        *target = unit;
+    }
+
+    long_name.next -= 1;
+    long_name.slots.push(offset);
+    Some(long_name)
+}
+
+/// Returns the long name entries of the name in the order they are stored.
+fn long_entries(name: &str, checksum: u8) -> Vec<[u8; ENTRY_SIZE]> {
+    let mut units: Vec<u16> = name.encode_utf16().collect();
+    if !units.len().is_multiple_of(LONG_NAME_UNITS) {
+        units.push(0);
+    }
+    units.resize(units.len().next_multiple_of(LONG_NAME_UNITS), 0xffff);
+
+    let count = units.len() / LONG_NAME_UNITS;
+    units
+        .chunks_exact(LONG_NAME_UNITS)
+        .enumerate()
+        .rev()
+        .map(|(index, units)| {
+            let mut slot = [0; ENTRY_SIZE];
+            slot[0] = index as u8 + 1;
+            if index + 1 == count {
+                slot[0] |= LAST_LONG_ENTRY;
+            }
+            slot[11] = Attributes::LONG_NAME.bits();
+            slot[13] = checksum;
+
+            let offsets = (1..11)
+                .step_by(2)
+                .chain((14..26).step_by(2))
+                .chain((28..32).step_by(2));
+            for (offset, unit) in offsets.zip(units) {
+                slot[offset..offset + 2].copy_from_slice(&unit.to_le_bytes());
+            }
+            slot
+        })
+        .collect()
+}
+
+/// Checks whether the name can be stored as a long name.
+fn validate(name: &str) -> Result<(), VfsError> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.ends_with(['.', ' '])
+        || name.encode_utf16().count() > LONG_NAME_MAX
+        || name
+            .chars()
+            .any(|c| c.is_ascii_control() || LONG_NAME_INVALID.contains(&c));
+
+    match invalid {
+        true => Err(VfsError::InvalidPath),
+        false => Ok(()),
+    }
+}
+
+/// Returns the short name of the name, if it is a valid uppercase 8.3 name.
+fn exact_short_name(name: &str) -> Option<[u8; 11]> {
+    let (base, extension) = name.split_once('.').unwrap_or((name, ""));
+    let valid = |part: &str, max: usize| part.len() <= max && part.bytes().all(is_short_name_byte);
+    if base.is_empty() || !valid(base, 8) || !valid(extension, 3) || name.ends_with('.') {
+        return None;
+    }
+
+    let mut short = [b' '; 11];
+    short[..base.len()].copy_from_slice(base.as_bytes());
+    short[8..8 + extension.len()].copy_from_slice(extension.as_bytes());
+    Some(short)
+}
+
+/// Generates a unique short name with a numeric tail for a name that requires a long name, e.g.
+/// `LONGFI~1.TXT` for `long file.txt`.
+fn generate_short_name(name: &str, items: &[DirItem]) -> Result<[u8; 11], VfsError> {
+    let convert = |part: &str, max: usize| {
+        part.chars()
+            .filter(|c| *c != ' ' && *c != '.')
+            .map(|c| match c.to_ascii_uppercase() as u32 {
+                byte @ 0..0x80 if is_short_name_byte(byte as u8) => byte as u8,
+                _ => b'_',
+            })
+            .take(max)
+            .collect::<Vec<u8>>()
+    };
+
+    let name = name.trim_start_matches('.');
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    let base = convert(base, 8);
+    let extension = convert(extension, 3);
+
+    let mut short = [b' '; 11];
+    short[8..8 + extension.len()].copy_from_slice(&extension);
+    for number in 1..1_000_000u32 {
+        let tail = format!("~{number}");
+        let length = base.len().min(8 - tail.len());
+        short[..8].fill(b' ');
+        short[..length].copy_from_slice(&base[..length]);
+        short[length..length + tail.len()].copy_from_slice(tail.as_bytes());
+
+        if items.iter().all(|item| item.entry.name != short) {
+            return Ok(short);
+        }
+    }
+
+    Err(VfsError::AlreadyExists)
+}
+
+/// Whether the byte may be part of a short name.
+fn is_short_name_byte(byte: u8) -> bool {
+    byte.is_ascii_uppercase() || byte.is_ascii_digit() || SHORT_NAME_SPECIAL.contains(&byte)
+}
+
+/// Returns the checksum of the short name stored in its long name entries.
+fn checksum(name: &[u8; 11]) -> u8 {
+    name.iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+/// Removes the space padding of a part of a short name.
+fn trim_padding(part: &[u8]) -> &[u8] {
+    let length = part
+        .iter()
+        .rposition(|byte| *byte != b' ')
+        .map_or(0, |index| index + 1);
+    &part[..length]
+}
+
+/// Compares the names case-insensitively, like FAT does.
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
@@ -0,0 +1,11 @@
+use vfs::error::VfsError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum FatError {
+    #[error("{0}")]
+    Vfs(#[from] VfsError),
+    #[error("Missing boot sector signature.")]
+    MissingSignature,
+    #[error("Invalid BIOS parameter block: {0}.")]
+    InvalidParameter(&'static str),
+}
@@ -0,0 +1,340 @@
+use alloc::{rc::Rc, vec, vec::Vec};
+use vfs::{
+    error::VfsError,
+    inode::{DirEntry, Inode, InodeType, Metadata},
+};
+
+use crate::{
+    dir::{self, Attributes, DirItem, Directory, ENTRY_SIZE, ShortEntry},
+    volume::Volume,
+};
+
+/// Inode number of the root directory, which has no directory entry.
+const ROOT_INO: u64 = 1;
+
+/// File of a FAT volume, identified by the location of its short directory entry. The entry is
+/// read on every access, so all inodes of a file agree on its size and clusters.
+#[derive(Debug)]
+pub(crate) struct FatInode {
+    volume: Rc<Volume>,
+    /// Offset of the short entry on the device, `None` for the root directory.
+    offset: Option<u64>,
+}
+
+impl FatInode {
+    pub(crate) fn root(volume: Rc<Volume>) -> FatInode {
+        FatInode {
+            volume,
+            offset: None,
+        }
+    }
+
+    /// Reads the short entry of the file, which fails if it has been removed.
+    fn entry(&self) -> Result<ShortEntry, VfsError> {
+        let offset = self.offset.ok_or(VfsError::IsDirectory)?;
+        let mut slot = [0; ENTRY_SIZE];
+        self.volume.read(offset, &mut slot)?;
+        match slot[0] {
+            0x00 | 0xe5 => Err(VfsError::NotFound),
+            _ => Ok(ShortEntry::parse(&slot)),
+        }
+    }
+
+    /// Writes the attributes, first cluster and size of the entry back to the device.
+    fn update(&self, entry: ShortEntry) -> Result<(), VfsError> {
+        let offset = self.offset.ok_or(VfsError::IsDirectory)?;
+        let mut slot = [0; ENTRY_SIZE];
+        self.volume.read(offset, &mut slot)?;
+        entry.patch(&mut slot);
+        self.volume.write(offset, &slot)
+    }
+
+    /// Reads the short entry of a regular file.
+    fn file_entry(&self) -> Result<ShortEntry, VfsError> {
+        let entry = self.entry()?;
+        match entry.is_directory() {
+            true => Err(VfsError::IsDirectory),
+            false => Ok(entry),
+        }
+    }
+
+    /// Returns the location of the entries of a directory.
+    fn directory(&self) -> Result<Directory, VfsError> {
+        if self.offset.is_none() {
+            return Ok(self.volume.layout().root);
+        }
+
+        let entry = self.entry()?;
+        match entry.is_directory() {
+            true => Ok(Directory::Chain(entry.first_cluster)),
+            false => Err(VfsError::NotDirectory),
+        }
+    }
+
+    /// Returns the first cluster of a directory, which is 0 for the root directory as stored in
+    /// the `..` entries of its subdirectories.
+    fn directory_cluster(&self) -> Result<u32, VfsError> {
+        match (self.offset, self.directory()?) {
+            (None, _) => Ok(0),
+            (Some(_), Directory::Chain(cluster)) => Ok(cluster),
+            (Some(_), Directory::Fixed { .. }) => Err(VfsError::Io),
+        }
+    }
+
+    fn item_inode(&self, item: &DirItem) -> FatInode {
+        FatInode {
+            volume: self.volume.clone(),
+            offset: Some(item.offset()),
+        }
+    }
+
+    /// Resizes the chain of the file to hold `size` bytes. New clusters are zeroed.
+    fn resize_chain(
+        &self,
+        entry: &mut ShortEntry,
+        chain: &mut Vec<u32>,
+        size: u64,
+    ) -> Result<(), VfsError> {
+        let needed = size.div_ceil(self.volume.layout().cluster_size as u64) as usize;
+
+        if needed < chain.len() {
+            self.volume.free(&chain[needed..])?;
+            chain.truncate(needed);
+            match chain.last() {
+                Some(last) => self.volume.terminate(*last)?,
+                None => entry.first_cluster = 0,
+            }
+        }
+
+        while chain.len() < needed {
+            let cluster = self.volume.allocate(chain.last().copied())?;
+            if chain.is_empty() {
+                entry.first_cluster = cluster;
+            }
+            chain.push(cluster);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the size of the file. Grown parts are zeroed, with newly allocated clusters being
+    /// zeroed by the volume already.
+    fn resize(
+        &self,
+        entry: &mut ShortEntry,
+        chain: &mut Vec<u32>,
+        size: u64,
+    ) -> Result<(), VfsError> {
+        let allocated = chain.len() as u64 * self.volume.layout().cluster_size as u64;
+        let old = entry.size as u64;
+
+        if let Err(err) = self.resize_chain(entry, chain, size) {
+            // keep the clusters allocated so far, they are freed once the file is truncated
+            self.update(*entry)?;
+            return Err(err);
+        }
+
+        if size > old {
+            let end = size.min(allocated);
+            if end > old {
+                self.write_chain(chain, old, &vec![0; (end - old) as usize])?;
+            }
+        }
+
+        entry.size = size as u32;
+        Ok(())
+    }
+
+    /// Reads the data of the chain starting at `offset`.
+    fn read_chain(&self, chain: &[u32], offset: u64, buffer: &mut [u8]) -> Result<(), VfsError> {
+        self.access_chain(chain, offset, buffer.len(), |position, range| {
+            self.volume.read(position, &mut buffer[range])
+        })
+    }
+
+    /// Writes the data of the chain starting at `offset`, which must lie within the chain.
+    fn write_chain(&self, chain: &[u32], offset: u64, buffer: &[u8]) -> Result<(), VfsError> {
+        self.access_chain(chain, offset, buffer.len(), |position, range| {
+            self.volume.write(position, &buffer[range])
+        })
+    }
+
+    /// Calls `access` with the device offset and buffer range of each contiguous part of the
+    /// `length` bytes starting at `offset`.
+    fn access_chain<F>(
+        &self,
+        chain: &[u32],
+        offset: u64,
+        length: usize,
+        mut access: F,
+    ) -> Result<(), VfsError>
+    where
+        F: FnMut(u64, core::ops::Range<usize>) -> Result<(), VfsError>,
+    {
+        let layout = self.volume.layout();
+        let cluster_size = layout.cluster_size as u64;
+        let mut done = 0;
+        while done < length {
+            let position = offset + done as u64;
+            let cluster = *chain
+                .get((position / cluster_size) as usize)
+                .ok_or(VfsError::Io)?;
+            let within = position % cluster_size;
+            let count = (length - done).min((cluster_size - within) as usize);
+
+            access(layout.cluster_offset(cluster) + within, done..done + count)?;
+            done += count;
+        }
+        Ok(())
+    }
+}
+
+impl Inode for FatInode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        let Some(offset) = self.offset else {
+            return Ok(Metadata {
+                ino: ROOT_INO,
+                kind: InodeType::Directory,
+                size: dir::size(&self.volume, self.volume.layout().root)?,
+                mode: 0o755,
+                links: 1,
+            });
+        };
+
+        let entry = self.entry()?;
+        let (kind, size, mode) = match entry.is_directory() {
+            true => (
+                InodeType::Directory,
+                dir::size(&self.volume, Directory::Chain(entry.first_cluster))?,
+                0o755,
+            ),
+            false => (InodeType::File, entry.size as u64, 0o644),
+        };
+        let mode = match entry.attributes.contains(Attributes::READ_ONLY) {
+            true => mode & !0o222,
+            false => mode,
+        };
+
+        Ok(Metadata {
+            ino: offset / ENTRY_SIZE as u64,
+            kind,
+            size,
+            mode,
+            links: 1,
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        let entry = self.file_entry()?;
+        let size = entry.size as u64;
+        if offset >= size {
+            return Ok(0);
+        }
+
+        let length = buffer.len().min((size - offset) as usize);
+        let chain = self.volume.chain(entry.first_cluster)?;
+        self.read_chain(&chain, offset, &mut buffer[..length])?;
+        Ok(length)
+    }
+
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<usize, VfsError> {
+        let mut entry = self.file_entry()?;
+        if entry.attributes.contains(Attributes::READ_ONLY) {
+            return Err(VfsError::ReadOnly);
+        }
+
+        // the size of a file is stored in 32 bits
+        let end = offset
+            .checked_add(buffer.len() as u64)
+            .filter(|end| *end <= u32::MAX as u64)
+            .ok_or(VfsError::NoSpace)?;
+
+        let mut chain = self.volume.chain(entry.first_cluster)?;
+        if end > entry.size as u64 {
+            self.resize(&mut entry, &mut chain, end)?;
+        }
+        self.write_chain(&chain, offset, buffer)?;
+
+        entry.attributes.insert(Attributes::ARCHIVE);
+        self.update(entry)?;
+        Ok(buffer.len())
+    }
+
+    fn truncate(&self, size: u64) -> Result<(), VfsError> {
+        let mut entry = self.file_entry()?;
+        if entry.attributes.contains(Attributes::READ_ONLY) {
+            return Err(VfsError::ReadOnly);
+        }
+        if size > u32::MAX as u64 {
+            return Err(VfsError::NoSpace);
+        }
+
+        let mut chain = self.volume.chain(entry.first_cluster)?;
+        self.resize(&mut entry, &mut chain, size)?;
+
+        entry.attributes.insert(Attributes::ARCHIVE);
+        self.update(entry)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        let item = dir::find(&self.volume, self.directory()?, name)?.ok_or(VfsError::NotFound)?;
+        Ok(Rc::new(self.item_inode(&item)))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        Ok(dir::read(&self.volume, self.directory()?)?
+            .into_iter()
+            .map(|item| DirEntry {
+                ino: item.offset() / ENTRY_SIZE as u64,
+                kind: match item.entry.is_directory() {
+                    true => InodeType::Directory,
+                    false => InodeType::File,
+                },
+                name: item.name,
+            })
+            .collect())
+    }
+
+    fn create(&self, name: &str, kind: InodeType) -> Result<Rc<dyn Inode>, VfsError> {
+        let directory = self.directory()?;
+
+        let offset = match kind {
+            InodeType::File => {
+                let entry = ShortEntry::new([b' '; 11], Attributes::ARCHIVE, 0);
+                dir::insert(&self.volume, directory, name, entry)?
+            }
+            InodeType::Directory => {
+                let parent = self.directory_cluster()?;
+                let cluster = self.volume.allocate(None)?;
+                let entry = ShortEntry::new([b' '; 11], Attributes::DIRECTORY, cluster);
+
+                let result = dir::initialize(&self.volume, cluster, parent)
+                    .and_then(|_| dir::insert(&self.volume, directory, name, entry));
+                if result.is_err() {
+                    self.volume.free(&[cluster])?;
+                }
+                result?
+            }
+            _ => return Err(VfsError::NotSupported),
+        };
+
+        Ok(Rc::new(FatInode {
+            volume: self.volume.clone(),
+            offset: Some(offset),
+        }))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), VfsError> {
+        let item = dir::find(&self.volume, self.directory()?, name)?.ok_or(VfsError::NotFound)?;
+
+        let chain = self.volume.chain(item.entry.first_cluster)?;
+        if item.entry.is_directory()
+            && !dir::read(&self.volume, Directory::Chain(item.entry.first_cluster))?.is_empty()
+        {
+            return Err(VfsError::NotEmpty);
+        }
+
+        dir::remove(&self.volume, &item)?;
+        self.volume.free(&chain)
+    }
+}
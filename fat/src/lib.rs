@@ -0,0 +1,67 @@
+#![no_std]
+
+//! FAT12, FAT16 and FAT32 filesystem with long file names on top of a
+//! [`vfs::block::BlockDevice`]. Files can be read, written, created and removed.
+//!
+//! The filesystem does not depend on the kernel, so it can be used on the host with a
+//! [`vfs::block::MemoryDevice`] holding a disk image.
+
+use alloc::rc::Rc;
+use error::FatError;
+use inode::FatInode;
+use vfs::{
+    block::BlockDevice,
+    error::VfsError,
+    inode::{FileSystem, Inode},
+};
+use volume::Volume;
+
+mod boot;
+mod dir;
+pub mod error;
+mod inode;
+mod volume;
+
+pub use boot::FatType;
+
+extern crate alloc;
+
+/// Mounted FAT volume.
+#[derive(Debug)]
+pub struct FatFileSystem {
+    volume: Rc<Volume>,
+}
+
+impl FatFileSystem {
+    /// Mounts the volume stored on the device, which starts with its boot sector.
+    pub fn new(device: Rc<dyn BlockDevice>) -> Result<FatFileSystem, FatError> {
+        Ok(FatFileSystem {
+            volume: Rc::new(Volume::new(device)?),
+        })
+    }
+}
+
+impl FatFileSystem {
+    pub fn fat_type(&self) -> FatType {
+        self.volume.layout().fat_type
+    }
+
+    /// Returns the number of free bytes.
+    pub fn free_space(&self) -> Result<u64, VfsError> {
+        Ok(self.volume.free_clusters()? as u64 * self.volume.layout().cluster_size as u64)
+    }
+}
+
+impl FileSystem for FatFileSystem {
+    fn name(&self) -> &str {
+        "fat"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(Rc::new(FatInode::root(self.volume.clone())))
+    }
+
+    fn sync(&self) -> Result<(), VfsError> {
+        self.volume.sync()
+    }
+}
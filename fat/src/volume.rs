@@ -0,0 +1,275 @@
+use core::{cell::RefCell, fmt::Debug};
+
+use alloc::{rc::Rc, vec, vec::Vec};
+use vfs::{block::BlockDevice, error::VfsError};
+
+use crate::{
+    boot::{FatType, Layout},
+    error::FatError,
+};
+
+/// Table entry of a free cluster.
+const FREE: u32 = 0;
+/// Table entry marking the last cluster of a chain, the values above are reserved as well.
+const END_OF_CHAIN: u32 = 0x0fff_fff8;
+
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+const FS_INFO_TRAIL_SIGNATURE: u32 = 0xaa55_0000;
+/// Value of the FSInfo fields, if they are unknown.
+const FS_INFO_UNKNOWN: u32 = 0xffff_ffff;
+
+/// Hints about free clusters, which are stored in the FSInfo sector of FAT32 volumes.
+#[derive(Copy, Clone, Debug)]
+struct Allocation {
+    /// Number of free clusters, if known.
+    free: Option<u32>,
+    /// Cluster the search for a free cluster starts at.
+    next: u32,
+}
+
+/// Mounted volume, which accesses the file allocation table and the data clusters.
+pub(crate) struct Volume {
+    device: Rc<dyn BlockDevice>,
+    layout: Layout,
+    allocation: RefCell<Allocation>,
+}
+
+impl Debug for Volume {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Volume")
+            .field("layout", &self.layout)
+            .field("allocation", &self.allocation)
+            .finish()
+    }
+}
+
+impl Volume {
+    pub(crate) fn new(device: Rc<dyn BlockDevice>) -> Result<Volume, FatError> {
+        let layout = Layout::read(device.as_ref())?;
+        let mut allocation = Allocation {
+            free: None,
+            next: 2,
+        };
+
+        if let Some(offset) = layout.fs_info {
+            let mut sector = [0; 512];
+            device.read_at(offset, &mut sector)?;
+            let u32_at = |offset: usize| {
+                u32::from_le_bytes(sector[offset..offset + 4].try_into().unwrap_or_default())
+            };
+
+            if u32_at(0) == FS_INFO_LEAD_SIGNATURE
+                && u32_at(484) == FS_INFO_STRUCT_SIGNATURE
+                && u32_at(508) == FS_INFO_TRAIL_SIGNATURE
+            {
+                allocation.free = Some(u32_at(488)).filter(|free| *free <= layout.clusters);
+                allocation.next = Some(u32_at(492))
+                    .filter(|next| layout.is_valid(*next))
+                    .unwrap_or(2);
+            }
+        }
+
+        Ok(Volume {
+            device,
+            layout,
+            allocation: RefCell::new(allocation),
+        })
+    }
+
+    pub(crate) fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Reads from the device starting at the byte `offset`.
+    pub(crate) fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<(), VfsError> {
+        self.device.read_at(offset, buffer)
+    }
+
+    /// Writes to the device starting at the byte `offset`.
+    pub(crate) fn write(&self, offset: u64, buffer: &[u8]) -> Result<(), VfsError> {
+        self.device.write_at(offset, buffer)
+    }
+
+    /// Returns the number of free clusters, counting them if the FSInfo sector does not know.
+    pub(crate) fn free_clusters(&self) -> Result<u32, VfsError> {
+        if let Some(free) = self.allocation.borrow().free {
+            return Ok(free);
+        }
+
+        let mut free = 0;
+        for cluster in 2..self.layout.clusters + 2 {
+            if self.entry(cluster)? == FREE {
+                free += 1;
+            }
+        }
+        self.allocation.borrow_mut().free = Some(free);
+        Ok(free)
+    }
+
+    /// Returns the clusters of the chain starting at `first`, which is empty if `first` is 0.
+    pub(crate) fn chain(&self, first: u32) -> Result<Vec<u32>, VfsError> {
+        let mut chain = Vec::new();
+        let mut cluster = first;
+        while cluster != FREE {
+            // a chain longer than the number of clusters contains a loop
+            if !self.layout.is_valid(cluster) || chain.len() as u32 >= self.layout.clusters {
+                return Err(VfsError::Io);
+            }
+            chain.push(cluster);
+            cluster = match self.entry(cluster)? {
+                next if next >= END_OF_CHAIN => FREE,
+                FREE => return Err(VfsError::Io),
+                next => next,
+            };
+        }
+        Ok(chain)
+    }
+
+    /// Allocates a zeroed cluster and appends it to the chain ending at `last`, if any.
+    pub(crate) fn allocate(&self, last: Option<u32>) -> Result<u32, VfsError> {
+        let mut allocation = self.allocation.borrow_mut();
+        let count = self.layout.clusters;
+        let cluster = (0..count)
+            .map(|index| 2 + (allocation.next - 2 + index) % count)
+            .find_map(|cluster| match self.entry(cluster) {
+                Ok(FREE) => Some(Ok(cluster)),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .ok_or(VfsError::NoSpace)??;
+
+        self.write(
+            self.layout.cluster_offset(cluster),
+            &vec![0; self.layout.cluster_size as usize],
+        )?;
+        self.set_entry(cluster, END_OF_CHAIN)?;
+        if let Some(last) = last {
+            self.set_entry(last, cluster)?;
+        }
+
+        allocation.next = if cluster + 1 < count + 2 {
+            cluster + 1
+        } else {
+            2
+        };
+        if let Some(free) = allocation.free.as_mut() {
+            *free = free.saturating_sub(1);
+        }
+        Ok(cluster)
+    }
+
+    /// Frees the clusters of the chain.
+    pub(crate) fn free(&self, chain: &[u32]) -> Result<(), VfsError> {
+        let mut allocation = self.allocation.borrow_mut();
+        for cluster in chain {
+            self.set_entry(*cluster, FREE)?;
+            if let Some(free) = allocation.free.as_mut() {
+                *free += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the cluster as the last one of its chain.
+    pub(crate) fn terminate(&self, cluster: u32) -> Result<(), VfsError> {
+        self.set_entry(cluster, END_OF_CHAIN)
+    }
+
+    /// Writes the allocation hints back to the FSInfo sector and flushes the device.
+    pub(crate) fn sync(&self) -> Result<(), VfsError> {
+        if let Some(offset) = self.layout.fs_info {
+            let allocation = self.allocation.borrow();
+            let mut hints = [0; 8];
+            hints[..4].copy_from_slice(&allocation.free.unwrap_or(FS_INFO_UNKNOWN).to_le_bytes());
+            hints[4..].copy_from_slice(&allocation.next.to_le_bytes());
+
+            let mut signature = [0; 4];
+            self.read(offset, &mut signature)?;
+            if u32::from_le_bytes(signature) == FS_INFO_LEAD_SIGNATURE {
+                self.write(offset + 488, &hints)?;
+            }
+        }
+        self.device.flush()
+    }
+
+    /// Reads the table entry of the cluster. Values marking the end of a chain are normalized to
+    /// [`END_OF_CHAIN`].
+    fn entry(&self, cluster: u32) -> Result<u32, VfsError> {
+        let fat = self.fat_offset(self.layout.active_fat.unwrap_or(0));
+        let (value, end) = match self.layout.fat_type {
+            FatType::Fat12 => {
+                let mut bytes = [0; 2];
+                self.read(fat + (cluster + cluster / 2) as u64, &mut bytes)?;
+                let value = u16::from_le_bytes(bytes);
+                let value = if cluster & 1 == 0 {
+                    value & 0xfff
+                } else {
+                    value >> 4
+                };
+                (value as u32, 0xff8)
+            }
+            FatType::Fat16 => {
+                let mut bytes = [0; 2];
+                self.read(fat + cluster as u64 * 2, &mut bytes)?;
+                (u16::from_le_bytes(bytes) as u32, 0xfff8)
+            }
+            FatType::Fat32 => {
+                let mut bytes = [0; 4];
+                self.read(fat + cluster as u64 * 4, &mut bytes)?;
+                (u32::from_le_bytes(bytes) & 0x0fff_ffff, END_OF_CHAIN)
+            }
+        };
+
+        // bad clusters are never part of a chain
+        Ok(if value >= end - 1 {
+            END_OF_CHAIN
+        } else {
+            value
+        })
+    }
+
+    /// Writes the table entry of the cluster to all mirrored tables.
+    fn set_entry(&self, cluster: u32, value: u32) -> Result<(), VfsError> {
+        let fats = match self.layout.active_fat {
+            Some(active) => active..active + 1,
+            None => 0..self.layout.fat_count,
+        };
+
+        for fat in fats {
+            let fat = self.fat_offset(fat);
+            match self.layout.fat_type {
+                FatType::Fat12 => {
+                    let offset = fat + (cluster + cluster / 2) as u64;
+                    let mut bytes = [0; 2];
+                    self.read(offset, &mut bytes)?;
+                    let old = u16::from_le_bytes(bytes);
+                    let value = (value & 0xfff) as u16;
+                    let new = if cluster & 1 == 0 {
+                        (old & 0xf000) | value
+                    } else {
+                        (old & 0x000f) | (value << 4)
+                    };
+                    self.write(offset, &new.to_le_bytes())?;
+                }
+                FatType::Fat16 => {
+                    self.write(fat + cluster as u64 * 2, &(value as u16).to_le_bytes())?;
+                }
+                FatType::Fat32 => {
+                    // the upper 4 bits are reserved and must be preserved
+                    let offset = fat + cluster as u64 * 4;
+                    let mut bytes = [0; 4];
+                    self.read(offset, &mut bytes)?;
+                    let new = (u32::from_le_bytes(bytes) & 0xf000_0000) | (value & 0x0fff_ffff);
+                    self.write(offset, &new.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of the file allocation table with the index.
+    fn fat_offset(&self, index: u8) -> u64 {
+        self.layout.fat_offset + index as u64 * self.layout.fat_size
+    }
+}
@@ -0,0 +1,293 @@
+//! Modifies images created by `mkfs.fat` and populated by `mcopy` from a directory tree, which
+//! must be installed on the host. The modified images are checked by `fsck.fat` and read back by
+//! `mtype` afterwards.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
+
+use fat::{FatFileSystem, FatType};
+use vfs::{
+    block::MemoryDevice,
+    error::VfsError,
+    file::{OpenFlags, SeekFrom},
+    inode::{FileSystem, InodeType},
+    mount::MountTable,
+};
+
+const HELLO: &[u8] = b"Hello, world!\n";
+const LONG_NAME: &str = "A name, which does not fit into 8.3.txt";
+const LARGE_SIZE: usize = 100 * 1024 + 123;
+/// Number of files created in a new directory, which is grown to several clusters.
+const GROWN_FILES: usize = 100;
+
+fn pattern(length: usize, seed: u8) -> Vec<u8> {
+    (0..length)
+        .map(|index| (index as u8).wrapping_mul(31).wrapping_add(seed))
+        .collect()
+}
+
+/// Creates the directory tree the images are populated with.
+fn populate(root: &Path) {
+    fs::write(root.join("hello.txt"), HELLO).unwrap();
+    fs::write(root.join("empty"), b"").unwrap();
+    fs::write(root.join(LONG_NAME), b"long\n").unwrap();
+    fs::write(root.join("MixedCase.Txt"), b"mixed\n").unwrap();
+    fs::write(root.join("large.bin"), pattern(LARGE_SIZE, 7)).unwrap();
+
+    fs::create_dir_all(root.join("nested/directory")).unwrap();
+    fs::write(root.join("nested/directory/deep.txt"), b"deep\n").unwrap();
+}
+
+/// Runs the host tool and returns its output. Returns `None` if it is not installed.
+fn tool<I, S>(program: &str, args: I) -> Option<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    // mtools refuses images whose geometry does not match a floppy disk otherwise
+    let output = Command::new(program)
+        .args(args)
+        .env("MTOOLS_SKIP_CHECK", "1")
+        .output();
+    match output {
+        Ok(output) => {
+            assert!(
+                output.status.success(),
+                "{program} failed: {}\n{}{}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            Some(output.stdout)
+        }
+        Err(err) => {
+            eprintln!("skipping fat test, {program} is not available: {err}");
+            None
+        }
+    }
+}
+
+/// Creates an image of `size` KiB with the FAT type and clusters of `cluster_sectors` sectors
+/// from the directory tree. Returns the directory of the test and the image, or `None` if
+/// `mkfs.fat` or `mcopy` is not installed.
+fn image(
+    name: &str,
+    fat_type: FatType,
+    size: usize,
+    cluster_sectors: usize,
+) -> Option<(PathBuf, PathBuf)> {
+    let directory = std::env::temp_dir().join(format!("fat-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    let tree = directory.join("tree");
+    fs::create_dir_all(&tree).unwrap();
+    populate(&tree);
+
+    let image = directory.join("image");
+    fs::write(&image, vec![0; size * 1024]).unwrap();
+    let bits = match fat_type {
+        FatType::Fat12 => "12",
+        FatType::Fat16 => "16",
+        FatType::Fat32 => "32",
+    };
+    let cluster_sectors = cluster_sectors.to_string();
+    tool(
+        "mkfs.fat",
+        [
+            OsStr::new("-F"),
+            OsStr::new(bits),
+            OsStr::new("-s"),
+            OsStr::new(&cluster_sectors),
+            image.as_os_str(),
+        ],
+    )?;
+
+    let mut args = vec![
+        OsStr::new("-s").to_owned(),
+        OsStr::new("-i").to_owned(),
+        image.clone().into_os_string(),
+    ];
+    for entry in fs::read_dir(&tree).unwrap() {
+        args.push(entry.unwrap().path().into_os_string());
+    }
+    args.push(OsStr::new("::/").to_owned());
+    tool("mcopy", args)?;
+
+    Some((directory, image))
+}
+
+/// Returns the sorted names of the entries of the directory.
+fn names(vfs: &MountTable, path: &str) -> Vec<String> {
+    let mut names: Vec<String> = vfs
+        .read_dir(path)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    names.sort();
+    names
+}
+
+fn grown_name(index: usize) -> String {
+    format!("/grown/file number {index} with a long name")
+}
+
+fn check(name: &str, fat_type: FatType, size: usize, cluster_sectors: usize) {
+    let Some((directory, image)) = image(name, fat_type, size, cluster_sectors) else {
+        return;
+    };
+
+    let device = Rc::new(MemoryDevice::new(fs::read(&image).unwrap(), 512).unwrap());
+    let fs = Rc::new(FatFileSystem::new(device.clone()).unwrap());
+    assert_eq!(fs.fat_type(), fat_type);
+    let mut vfs = MountTable::new();
+    vfs.mount("/", fs.clone()).unwrap();
+
+    // files copied by mcopy
+    assert_eq!(vfs.read_to_end("/hello.txt").unwrap(), HELLO);
+    assert_eq!(vfs.read_to_end("/HELLO.TXT").unwrap(), HELLO);
+    assert_eq!(vfs.read_to_end("/empty").unwrap(), b"");
+    assert_eq!(
+        vfs.read_to_end(&format!("/{LONG_NAME}")).unwrap(),
+        b"long\n"
+    );
+    assert_eq!(vfs.read_to_end("/MixedCase.Txt").unwrap(), b"mixed\n");
+    assert_eq!(
+        vfs.read_to_end("/large.bin").unwrap(),
+        pattern(LARGE_SIZE, 7)
+    );
+    assert_eq!(
+        vfs.read_to_end("/nested/directory/deep.txt").unwrap(),
+        b"deep\n"
+    );
+    assert_eq!(
+        names(&vfs, "/"),
+        [
+            LONG_NAME,
+            "MixedCase.Txt",
+            "empty",
+            "hello.txt",
+            "large.bin",
+            "nested"
+        ]
+    );
+
+    // writing spans several clusters
+    let created = "/created with a long name.bin";
+    let mut data = pattern(3 * 4096 + 5, 3);
+    let mut file = vfs
+        .open(
+            created,
+            OpenFlags::READ | OpenFlags::WRITE | OpenFlags::CREATE,
+        )
+        .unwrap();
+    assert_eq!(file.write(&data).unwrap(), data.len());
+    file.seek(SeekFrom::Start(100)).unwrap();
+    file.write(b"overwritten").unwrap();
+    data[100..111].copy_from_slice(b"overwritten");
+    drop(file);
+    assert_eq!(vfs.read_to_end(created).unwrap(), data);
+
+    // truncating frees and allocates clusters
+    let large = vfs.resolve("/large.bin").unwrap();
+    large.truncate(1000).unwrap();
+    assert_eq!(vfs.read_to_end("/large.bin").unwrap(), pattern(1000, 7));
+    large.truncate(5000).unwrap();
+    let mut truncated = pattern(1000, 7);
+    truncated.resize(5000, 0);
+    assert_eq!(vfs.read_to_end("/large.bin").unwrap(), truncated);
+    drop(large);
+
+    let mut file = vfs
+        .open("/hello.txt", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+        .unwrap();
+    file.write(b"Bye\n").unwrap();
+    drop(file);
+    assert_eq!(vfs.read_to_end("/hello.txt").unwrap(), b"Bye\n");
+
+    // unlinking
+    vfs.unlink("/empty").unwrap();
+    vfs.unlink(&format!("/{LONG_NAME}")).unwrap();
+    assert_eq!(vfs.resolve("/empty").err(), Some(VfsError::NotFound));
+    assert_eq!(
+        vfs.resolve(&format!("/{LONG_NAME}")).err(),
+        Some(VfsError::NotFound)
+    );
+    assert_eq!(vfs.unlink("/nested"), Err(VfsError::NotEmpty));
+    vfs.unlink("/nested/directory/deep.txt").unwrap();
+    vfs.unlink("/nested/directory").unwrap();
+    assert!(vfs.read_dir("/nested").unwrap().is_empty());
+
+    // the entries of a new directory do not fit into a single cluster
+    vfs.create("/grown", InodeType::Directory).unwrap();
+    for index in 0..GROWN_FILES {
+        let mut file = vfs
+            .open(&grown_name(index), OpenFlags::WRITE | OpenFlags::CREATE)
+            .unwrap();
+        file.write(&pattern(index * 37, index as u8)).unwrap();
+    }
+    let metadata = vfs.resolve("/grown").unwrap().metadata().unwrap();
+    assert!(metadata.size > 2 * (cluster_sectors * 512) as u64);
+    assert_eq!(vfs.read_dir("/grown").unwrap().len(), GROWN_FILES);
+    for index in (0..GROWN_FILES).step_by(2) {
+        vfs.unlink(&grown_name(index)).unwrap();
+    }
+    for index in 0..GROWN_FILES {
+        let content = vfs.read_to_end(&grown_name(index));
+        match index % 2 {
+            0 => assert_eq!(content.err(), Some(VfsError::NotFound)),
+            _ => assert_eq!(content.unwrap(), pattern(index * 37, index as u8)),
+        }
+    }
+
+    assert_eq!(
+        names(&vfs, "/"),
+        [
+            "MixedCase.Txt",
+            "created with a long name.bin",
+            "grown",
+            "hello.txt",
+            "large.bin",
+            "nested"
+        ]
+    );
+
+    fs.sync().unwrap();
+    drop(vfs);
+    drop(fs);
+    let device = Rc::try_unwrap(device).expect("the filesystem has been dropped");
+    fs::write(&image, device.into_inner()).unwrap();
+
+    // the host tools agree with the modified image
+    if tool("fsck.fat", [OsStr::new("-n"), image.as_os_str()]).is_none() {
+        return;
+    }
+    let mut target = OsStr::new("::").to_owned();
+    target.push(created);
+    let typed = tool(
+        "mtype",
+        [OsStr::new("-i"), image.as_os_str(), target.as_os_str()],
+    );
+    assert_eq!(typed.unwrap(), data);
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn fat12() {
+    check("12", FatType::Fat12, 4 * 1024, 4);
+}
+
+#[test]
+fn fat16() {
+    check("16", FatType::Fat16, 16 * 1024, 4);
+}
+
+#[test]
+fn fat32() {
+    check("32", FatType::Fat32, 64 * 1024, 1);
+}
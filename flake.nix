@@ -76,6 +76,9 @@
               ./mem
               ./sync
               ./scheduler
+              ./vfs
+              ./fat
+              ./ext2
             ];
           };
 
@@ -86,10 +89,21 @@
           additionalCargoLock = "${rustToolchain.passthru.availableComponents.rust-src}/lib/rustlib/src/rust/library/Cargo.lock"; # for building std
         };
 
-        kernel = naersk'.buildPackage (
-          (commonArgs "kernel")
+        kernelArgs = (commonArgs "kernel") // {
+          CARGO_BUILD_TARGET = "${./kernel/x86_64-unknown-nereus.json}";
+        };
+        kernel = naersk'.buildPackage kernelArgs;
+        # runs the self tests during boot
+        kernelSelftest = naersk'.buildPackage (
+          kernelArgs
           // {
-            CARGO_BUILD_TARGET = "${./kernel/x86_64-unknown-nereus.json}";
+            cargoBuildOptions =
+              x:
+              kernelArgs.cargoBuildOptions x
+              ++ [
+                "--features"
+                "selftest"
+              ];
           }
         );
         loader = naersk'.buildPackage (
@@ -100,6 +114,13 @@
         );
         bootimage = pkgs.callPackage ./nix/img.nix { inherit kernel loader; };
         qemu = pkgs.callPackage ./nix/qemu.nix { inherit bootimage; };
+        selftest = pkgs.callPackage ./nix/qemu.nix {
+          bootimage = pkgs.callPackage ./nix/img.nix {
+            kernel = kernelSelftest;
+            inherit loader;
+            selftest = true;
+          };
+        };
         flash = pkgs.callPackage ./nix/flash.nix { inherit bootimage; };
 
       in
@@ -111,6 +132,7 @@
             loader
             bootimage
             qemu
+            selftest
             flash
             ;
         };
@@ -121,6 +143,9 @@
         devShell = pkgs.mkShell {
           nativeBuildInputs = [
             rustToolchain
+            pkgs.e2fsprogs # mke2fs for the ext2 tests
+            pkgs.dosfstools # mkfs.fat and fsck.fat for the fat tests
+            pkgs.mtools # mcopy and mtype for the fat tests
           ];
         };
       }
@@ -0,0 +1,161 @@
+use core::arch::asm;
+
+use crate::{
+    instructions::cpuid::Cpuid,
+    registers::control::{Cr0, Cr4, Xcr0},
+};
+
+/// Required alignment of a save area. `fxsave` requires 16 bytes, `xsave` 64 bytes.
+pub const SAVE_AREA_ALIGNMENT: usize = 64;
+
+/// Size of the legacy save area used by `fxsave`.
+const FXSAVE_AREA_SIZE: usize = 512;
+
+/// Offset of the x87 FPU control word within a save area.
+const FCW_OFFSET: usize = 0;
+/// Offset of the MXCSR register within a save area.
+const MXCSR_OFFSET: usize = 24;
+
+/// x87 FPU control word after `fninit`, all exceptions are masked.
+const DEFAULT_FCW: u16 = 0x037F;
+/// MXCSR after reset, all exceptions are masked.
+const DEFAULT_MXCSR: u32 = 0x1F80;
+
+/// Instructions used to save and restore the floating point and SIMD state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SaveMechanism {
+    /// `fxsave`/`fxrstor`, saving the x87 FPU and SSE state.
+    Fxsave,
+    /// `xsave`/`xrstor`, saving the enabled state components.
+    Xsave {
+        /// State components enabled in XCR0.
+        components: Xcr0,
+        /// Size of the save area for the enabled components.
+        size: usize,
+    },
+}
+
+impl SaveMechanism {
+    /// Detects the best mechanism supported by the CPU. Returns `None` if the CPU supports neither
+    /// `fxsave` nor SSE (CPUID.01h:EDX[bit 24, 25]).
+    pub fn detect(cpuid: Cpuid) -> Option<SaveMechanism> {
+        let features = unsafe { cpuid.get(0x1) };
+        let fxsr = features.edx & (1 << 24) != 0;
+        let sse = features.edx & (1 << 25) != 0;
+        if !fxsr || !sse {
+            return None;
+        }
+
+        // CPUID.01h:ECX[bit 26]
+        let xsave = features.ecx & (1 << 26) != 0;
+        let max_leaf = unsafe { cpuid.get(0x0) }.eax;
+        if !xsave || max_leaf < 0xD {
+            return Some(SaveMechanism::Fxsave);
+        }
+
+        // CPUID.0Dh.00h:EAX contains the supported state components, ECX the size of the save
+        // area for all of them
+        let state = unsafe { cpuid.get(0xD) };
+        let components = Xcr0::from_bits_truncate(state.eax as u64);
+
+        Some(SaveMechanism::Xsave {
+            components: components & (Xcr0::X87 | Xcr0::SSE | Xcr0::AVX),
+            size: state.ecx as usize,
+        })
+    }
+
+    /// Size of a save area for this mechanism.
+    pub fn size(self) -> usize {
+        match self {
+            SaveMechanism::Fxsave => FXSAVE_AREA_SIZE,
+            SaveMechanism::Xsave { size, .. } => size,
+        }
+    }
+
+    /// Enables the x87 FPU, SSE and the save mechanism. Clears [`Cr0::TASK_SWITCHED`].
+    ///
+    /// # Safety
+    /// Caller must be in privilege level 0 and the mechanism must have been detected on this CPU.
+    pub unsafe fn enable(self) {
+        let cr0 = (Cr0::read() | Cr0::MONITOR_COPROCESSOR | Cr0::NUMERIC_ERROR)
+            - (Cr0::EMULATION | Cr0::TASK_SWITCHED);
+
+        let mut cr4 = Cr4::read() | Cr4::OSFXSR | Cr4::OSXMMEXCPT;
+        if let SaveMechanism::Xsave { .. } = self {
+            cr4 |= Cr4::OSXSAVE;
+        }
+
+        unsafe {
+            cr0.write();
+            cr4.write();
+
+            if let SaveMechanism::Xsave { components, .. } = self {
+                components.write();
+            }
+
+            asm!("fninit", options(nomem, nostack));
+        }
+    }
+
+    /// Initializes a save area, so that restoring it results in a clean state with all
+    /// floating point exceptions masked.
+    ///
+    /// # Safety
+    /// `area` must be valid for writes of [`SaveMechanism::size`] bytes.
+    pub unsafe fn initialize(self, area: *mut u8) {
+        unsafe {
+            area.write_bytes(0, self.size());
+            area.add(FCW_OFFSET)
+                .cast::<u16>()
+                .write_unaligned(DEFAULT_FCW);
+            area.add(MXCSR_OFFSET)
+                .cast::<u32>()
+                .write_unaligned(DEFAULT_MXCSR);
+        }
+    }
+
+    /// Saves the current state into `area`.
+    ///
+    /// # Safety
+    /// `area` must be valid for writes of [`SaveMechanism::size`] bytes and aligned to
+    /// [`SAVE_AREA_ALIGNMENT`]. [`Cr0::TASK_SWITCHED`] must be cleared.
+    pub unsafe fn save(self, area: *mut u8) {
+        match self {
+            SaveMechanism::Fxsave => unsafe {
+                asm!("fxsave64 [{}]", in(reg) area, options(nostack, preserves_flags));
+            },
+            SaveMechanism::Xsave { components, .. } => unsafe {
+                asm!(
+                    "xsave64 [{}]",
+                    in(reg) area,
+                    in("eax") components.bits() as u32,
+                    in("edx") (components.bits() >> 32) as u32,
+                    options(nostack, preserves_flags),
+                );
+            },
+        }
+    }
+
+    /// Restores the state saved in `area`.
+    ///
+    /// # Safety
+    /// `area` must have been initialized by [`SaveMechanism::initialize`] or
+    /// [`SaveMechanism::save`] and be aligned to [`SAVE_AREA_ALIGNMENT`].
+    /// [`Cr0::TASK_SWITCHED`] must be cleared.
+    pub unsafe fn restore(self, area: *const u8) {
+        match self {
+            SaveMechanism::Fxsave => unsafe {
+                asm!("fxrstor64 [{}]", in(reg) area, options(nostack, preserves_flags));
+            },
+            SaveMechanism::Xsave { components, .. } => unsafe {
+                asm!(
+                    "xrstor64 [{}]",
+                    in(reg) area,
+                    in("eax") components.bits() as u32,
+                    in("edx") (components.bits() >> 32) as u32,
+                    options(nostack, preserves_flags),
+                );
+            },
+        }
+    }
+}
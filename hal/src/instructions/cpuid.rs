@@ -34,4 +34,12 @@ impl Cpuid {
         // The caller guarantees that `leaf` is valid.
         unsafe { __cpuid(leaf) }
     }
+
+    /// Check whether 1 GiB pages are available to the CPU
+    pub fn huge_pages_available(self) -> bool {
+        // Safety: leaf 0x8000_0000 reports the highest supported extended leaf.
+        let max_extended = unsafe { self.get(0x8000_0000) }.eax;
+        // Safety: leaf 0x8000_0001 was checked to be supported above.
+        max_extended >= 0x8000_0001 && unsafe { self.get(0x8000_0001) }.edx & (1 << 26) != 0
+    }
 }
@@ -3,15 +3,22 @@
 use core::arch::asm;
 
 pub mod cpu_state;
+pub mod fpu;
 pub mod instructions;
 pub mod interrupts;
 pub mod registers;
 
+/// Halts the CPU until the next interrupt arrives.
+#[inline]
+pub fn hlt() {
+    unsafe {
+        asm!("hlt", options(nomem, nostack, preserves_flags));
+    }
+}
+
 #[inline]
 pub fn hlt_loop() -> ! {
     loop {
-        unsafe {
-            asm!("hlt", options(nomem, nostack, preserves_flags));
-        }
+        hlt();
     }
 }
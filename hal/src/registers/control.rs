@@ -0,0 +1,200 @@
+use bitflags::bitflags;
+use core::arch::asm;
+
+bitflags! {
+    /// Control register 0, which controls the operating mode and state of the CPU.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Cr0: u64 {
+        /// Protected mode enable
+        const PROTECTED_MODE = 1 << 0;
+        /// Monitor co-processor, `wait`/`fwait` raise #NM if TASK_SWITCHED is set.
+        const MONITOR_COPROCESSOR = 1 << 1;
+        /// x87 FPU emulation, FPU and SSE instructions raise #NM if set.
+        const EMULATION = 1 << 2;
+        /// Task switched, the first FPU or SSE instruction raises #NM if set.
+        const TASK_SWITCHED = 1 << 3;
+        /// Extension type, hardwired to 1 on x86-64.
+        const EXTENSION_TYPE = 1 << 4;
+        /// Numeric error, enables native x87 FPU error reporting (#MF).
+        const NUMERIC_ERROR = 1 << 5;
+        // bits 6-15 reserved
+        /// Write protect, supervisor code cannot write to read-only pages if set.
+        const WRITE_PROTECT = 1 << 16;
+        // bit 17 reserved
+        /// Alignment mask
+        const ALIGNMENT_MASK = 1 << 18;
+        // bits 19-28 reserved
+        /// Not-write through
+        const NOT_WRITE_THROUGH = 1 << 29;
+        /// Cache disable
+        const CACHE_DISABLE = 1 << 30;
+        /// Paging
+        const PAGING = 1 << 31;
+        // bits 32-63 reserved
+    }
+}
+
+impl Cr0 {
+    /// Read the CR0 register
+    #[inline]
+    pub fn read() -> Self {
+        let cr0: u64;
+        unsafe {
+            asm!("mov {}, cr0", out(reg) cr0, options(nomem, nostack, preserves_flags));
+        }
+        Cr0::from_bits_retain(cr0)
+    }
+
+    /// Write the CR0 register, preserving reserved bits.
+    ///
+    /// # Safety
+    /// Changing the operating mode of the CPU may break memory safety.
+    #[inline]
+    pub unsafe fn write(self) {
+        unsafe {
+            asm!("mov cr0, {}", in(reg) self.bits(), options(nostack, preserves_flags));
+        }
+    }
+
+    /// Clears the TASK_SWITCHED flag using `clts`.
+    ///
+    /// # Safety
+    /// Caller must be in privilege level 0.
+    #[inline]
+    pub unsafe fn clear_task_switched() {
+        unsafe {
+            asm!("clts", options(nomem, nostack, preserves_flags));
+        }
+    }
+}
+
+bitflags! {
+    /// Control register 4, which enables architectural extensions.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Cr4: u64 {
+        /// Virtual-8086 mode extensions
+        const VIRTUAL_8086_MODE_EXTENSIONS = 1 << 0;
+        /// Protected-mode virtual interrupts
+        const PROTECTED_MODE_VIRTUAL_INTERRUPTS = 1 << 1;
+        /// Time stamp disable, `rdtsc` is restricted to privilege level 0 if set.
+        const TIME_STAMP_DISABLE = 1 << 2;
+        /// Debugging extensions
+        const DEBUGGING_EXTENSIONS = 1 << 3;
+        /// Page size extension
+        const PAGE_SIZE_EXTENSION = 1 << 4;
+        /// Physical address extension
+        const PHYSICAL_ADDRESS_EXTENSION = 1 << 5;
+        /// Machine check exception
+        const MACHINE_CHECK_EXCEPTION = 1 << 6;
+        /// Page global enable
+        const PAGE_GLOBAL = 1 << 7;
+        /// Performance monitoring counter enable
+        const PERFORMANCE_MONITORING_COUNTER = 1 << 8;
+        /// OS support for `fxsave` and `fxrstor`, enables SSE instructions.
+        const OSFXSR = 1 << 9;
+        /// OS support for unmasked SIMD floating-point exceptions (#XM).
+        const OSXMMEXCPT = 1 << 10;
+        /// User-mode instruction prevention
+        const USER_MODE_INSTRUCTION_PREVENTION = 1 << 11;
+        /// 57-bit linear addresses (5-level paging)
+        const LA57 = 1 << 12;
+        /// Virtual machine extensions enable
+        const VMX = 1 << 13;
+        /// Safer mode extensions enable
+        const SMX = 1 << 14;
+        // bit 15 reserved
+        /// Enables `rdfsbase`, `rdgsbase`, `wrfsbase` and `wrgsbase`.
+        const FSGSBASE = 1 << 16;
+        /// PCID enable
+        const PCID = 1 << 17;
+        /// OS support for `xsave` and processor extended states, enables XCR0.
+        const OSXSAVE = 1 << 18;
+        // bit 19 reserved
+        /// Supervisor mode execution protection enable
+        const SMEP = 1 << 20;
+        /// Supervisor mode access prevention enable
+        const SMAP = 1 << 21;
+        /// Protection key enable
+        const PROTECTION_KEY = 1 << 22;
+        // bits 23-63 reserved
+    }
+}
+
+impl Cr4 {
+    /// Read the CR4 register
+    #[inline]
+    pub fn read() -> Self {
+        let cr4: u64;
+        unsafe {
+            asm!("mov {}, cr4", out(reg) cr4, options(nomem, nostack, preserves_flags));
+        }
+        Cr4::from_bits_retain(cr4)
+    }
+
+    /// Write the CR4 register, preserving reserved bits.
+    ///
+    /// # Safety
+    /// Enabling unsupported extensions causes a general protection fault.
+    #[inline]
+    pub unsafe fn write(self) {
+        unsafe {
+            asm!("mov cr4, {}", in(reg) self.bits(), options(nostack, preserves_flags));
+        }
+    }
+}
+
+bitflags! {
+    /// Extended control register 0, which selects the state components managed by `xsave`.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Xcr0: u64 {
+        /// x87 FPU state, must always be set.
+        const X87 = 1 << 0;
+        /// SSE state (XMM registers and MXCSR)
+        const SSE = 1 << 1;
+        /// AVX state (upper halves of the YMM registers), requires SSE.
+        const AVX = 1 << 2;
+    }
+}
+
+impl Xcr0 {
+    const INDEX: u32 = 0;
+
+    /// Read the XCR0 register
+    ///
+    /// # Safety
+    /// [`Cr4::OSXSAVE`] must be set.
+    #[inline]
+    pub unsafe fn read() -> Self {
+        let (high, low): (u32, u32);
+        unsafe {
+            asm!(
+                "xgetbv",
+                in("ecx") Self::INDEX,
+                out("eax") low, out("edx") high,
+                options(nomem, nostack, preserves_flags),
+            );
+        }
+        Xcr0::from_bits_retain(((high as u64) << 32) | (low as u64))
+    }
+
+    /// Write the XCR0 register
+    ///
+    /// # Safety
+    /// [`Cr4::OSXSAVE`] must be set and all state components must be supported by the CPU.
+    #[inline]
+    pub unsafe fn write(self) {
+        let low = self.bits() as u32;
+        let high = (self.bits() >> 32) as u32;
+        unsafe {
+            asm!(
+                "xsetbv",
+                in("ecx") Self::INDEX,
+                in("eax") low, in("edx") high,
+                options(nostack, preserves_flags),
+            );
+        }
+    }
+}
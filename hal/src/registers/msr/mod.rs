@@ -1,6 +1,7 @@
 pub mod apic;
 pub mod efer;
 pub mod msr_guard;
+pub mod syscall;
 
 use bitflags::Flags;
 use msr_guard::Msr;
@@ -0,0 +1,140 @@
+use bitflags::bitflags;
+
+use crate::{instructions::cpuid::Cpuid, registers::rflags::RFlags};
+
+use super::{ModelSpecificRegister, Msr};
+
+const IA32_STAR: u32 = 0xC000_0081;
+const IA32_LSTAR: u32 = 0xC000_0082;
+const IA32_FMASK: u32 = 0xC000_0084;
+
+bitflags! {
+    /// System Call Target Address Register, contains the segment selectors loaded by SYSCALL and
+    /// SYSRET.
+    #[repr(transparent)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct Star: u64 {
+        // bits 0 - 31 are only used in legacy mode
+        /// Code segment selector loaded by SYSCALL. The stack segment selector is the next entry
+        /// (+ 8).
+        const SYSCALL_SELECTOR = 0xFFFF << 32;
+        /// Base selector used by SYSRET to 64-bit mode. The stack segment selector is the next
+        /// entry (+ 8), the code segment selector the one after it (+ 16).
+        const SYSRET_SELECTOR = 0xFFFF << 48;
+    }
+}
+
+bitflags! {
+    /// Long Mode System Call Target Address Register, contains the address SYSCALL jumps to.
+    #[repr(transparent)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct LStar: u64 {
+        const TARGET = !0;
+    }
+}
+
+bitflags! {
+    /// System Call Flag Mask Register, contains the RFLAGS bits cleared by SYSCALL.
+    #[repr(transparent)]
+    #[derive(Copy, Clone, Debug)]
+    pub struct SfMask: u64 {
+        // bits 32 - 63 are reserved
+        const MASK = 0xFFFF_FFFF;
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyscallError {
+    #[error("SYSCALL/SYSRET are not available on this CPU")]
+    SyscallUnavailable,
+}
+
+// Safety: IA32_STAR is a valid MSR index.
+unsafe impl ModelSpecificRegister for Star {
+    const MSR_INDEX: u32 = IA32_STAR;
+    type ReadError = ();
+    type WriteError = SyscallError;
+
+    unsafe fn write(self, msr: Msr) -> Result<(), SyscallError> {
+        if available(msr.get_cpuid()) {
+            // Safety: Caller guarantees that we are in privilege level 0, Self::MSR_INDEX is a valid index.
+            unsafe { msr.write(Self::MSR_INDEX, self.bits()) }
+            Ok(())
+        } else {
+            Err(SyscallError::SyscallUnavailable)
+        }
+    }
+}
+
+// Safety: IA32_LSTAR is a valid MSR index.
+unsafe impl ModelSpecificRegister for LStar {
+    const MSR_INDEX: u32 = IA32_LSTAR;
+    type ReadError = ();
+    type WriteError = SyscallError;
+
+    unsafe fn write(self, msr: Msr) -> Result<(), SyscallError> {
+        if available(msr.get_cpuid()) {
+            // Safety: Caller guarantees that we are in privilege level 0, Self::MSR_INDEX is a valid index.
+            unsafe { msr.write(Self::MSR_INDEX, self.bits()) }
+            Ok(())
+        } else {
+            Err(SyscallError::SyscallUnavailable)
+        }
+    }
+}
+
+// Safety: IA32_FMASK is a valid MSR index.
+unsafe impl ModelSpecificRegister for SfMask {
+    const MSR_INDEX: u32 = IA32_FMASK;
+    type ReadError = ();
+    type WriteError = SyscallError;
+
+    unsafe fn write(self, msr: Msr) -> Result<(), SyscallError> {
+        if available(msr.get_cpuid()) {
+            // Safety: Caller guarantees that we are in privilege level 0, Self::MSR_INDEX is a valid index.
+            unsafe { msr.write(Self::MSR_INDEX, self.bits()) }
+            Ok(())
+        } else {
+            Err(SyscallError::SyscallUnavailable)
+        }
+    }
+}
+
+/// Check whether the SYSCALL and SYSRET instructions are available to the CPU
+pub fn available(cpuid: Cpuid) -> bool {
+    unsafe { cpuid.get(0x80000001) }.edx & (1 << 11) != 0
+}
+
+impl Star {
+    /// Creates the register value from the code segment selector loaded by SYSCALL and the base
+    /// selector used by SYSRET, see [`Star::SYSRET_SELECTOR`].
+    pub fn new(syscall_cs: u16, sysret_base: u16) -> Star {
+        Star::from_bits_retain(((sysret_base as u64) << 48) | ((syscall_cs as u64) << 32))
+    }
+
+    pub fn syscall_cs(&self) -> u16 {
+        (self.bits() >> 32) as u16
+    }
+
+    pub fn sysret_base(&self) -> u16 {
+        (self.bits() >> 48) as u16
+    }
+}
+
+impl LStar {
+    /// Creates the register value from the address of the system call entry.
+    pub fn new(target: u64) -> LStar {
+        LStar::from_bits_retain(target)
+    }
+
+    pub fn target(&self) -> u64 {
+        self.bits()
+    }
+}
+
+impl SfMask {
+    /// Creates the register value from the flags to clear on SYSCALL.
+    pub fn new(mask: RFlags) -> SfMask {
+        SfMask::from_bits_truncate(mask.bits())
+    }
+}
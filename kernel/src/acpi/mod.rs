@@ -1,13 +1,15 @@
 use alloc::vec::Vec;
 use error::AcpiError;
+use hal::interrupts::without_interrupts;
 use madt::{
     entry::{InterruptSourceOverride, IoApic},
     Madt,
 };
 use mem::PhysicalAddress;
 use rsd::Rsd;
-use sdt::Rsdt;
+use sdt::{Header, Rsdt};
 use signature::Signature;
+use sync::locked::Locked;
 
 pub(crate) mod error;
 pub(crate) mod madt;
@@ -15,14 +17,24 @@ pub(crate) mod rsd;
 pub(crate) mod sdt;
 pub(crate) mod signature;
 
+/// Headers of the tables found while parsing. They are copied, since the memory of the tables is
+/// reclaimed later on.
+static TABLES: Locked<Vec<Header>> = Locked::new();
+
 /// Parses the ACPI Tables.
 pub(crate) fn parse(rsdp: *const u8) -> Result<Rsdt, AcpiError> {
     let rsd = Rsd::parse(rsdp)?;
     let sdt = Rsdt::new(rsd)?;
+    TABLES.initialize(sdt.headers());
     // todo: parse remaining tables and retrieve system information
     Ok(sdt)
 }
 
+/// Returns the headers of the tables found while parsing.
+pub(crate) fn tables() -> Vec<Header> {
+    without_interrupts(|| TABLES.locked().get().cloned().unwrap_or_default())
+}
+
 /// Parses the MADT and returns the physical address of the local apic registers as well as
 /// interrupt source overrides and IOApics.
 pub(crate) fn madt(
@@ -1,3 +1,4 @@
+use alloc::vec::Vec;
 use core::ptr::{self, NonNull};
 
 use super::{error::AcpiError, signature::Signature, Rsd};
@@ -23,9 +24,25 @@ pub(crate) struct Header {
 }
 
 impl Header {
-    pub(super) fn length(&self) -> u32 {
+    pub(crate) fn signature(&self) -> Signature<4> {
+        self.signature
+    }
+
+    pub(crate) fn length(&self) -> u32 {
         self.length
     }
+
+    pub(crate) fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    pub(crate) fn oem_id(&self) -> [u8; 6] {
+        self.oem_id
+    }
+
+    pub(crate) fn oem_table_id(&self) -> [u8; 8] {
+        self.oem_table_id
+    }
 }
 
 /// Root System Descriptor Table. This table contains pointers to all the other System Description Tables.
@@ -57,6 +74,26 @@ impl Rsdt {
 }
 
 impl Rsdt {
+    /// Returns the headers of all tables the root table points to.
+    pub(super) fn headers(&self) -> Vec<Header> {
+        let header = unsafe { self.ptr.read_unaligned() };
+        let ptr_size = if self.version2 { 8 } else { 4 };
+        let entries = (header.length as usize - size_of::<Header>()) / ptr_size;
+        let base_ptr = unsafe { self.ptr.add(1).cast::<u8>() };
+
+        (0..entries)
+            .map(|i| {
+                let entry_ptr = unsafe { base_ptr.add(i * ptr_size) };
+                let address = if self.version2 {
+                    unsafe { entry_ptr.cast::<u64>().read_unaligned() }
+                } else {
+                    unsafe { entry_ptr.cast::<u32>().read_unaligned() as u64 }
+                };
+                unsafe { (address as *const Header).read_unaligned() }
+            })
+            .collect()
+    }
+
     /// Parses the given system descriptor table based on it's signature, yielding a pointer to the
     /// table.
     pub(super) fn parse_table<T>(&self, signature: Signature<4>) -> Result<NonNull<T>, AcpiError> {
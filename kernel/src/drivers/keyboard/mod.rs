@@ -1,14 +1,17 @@
 use framebuffer::color::{Color, INFO};
 use qwertz::Qwertz;
+use scheduler::wait::WaitQueue;
 use sync::spin::SpinLock;
 
-use crate::{handle_scancode, print, println};
+use crate::{handle_scancode, print, scheduling};
 use core::marker::PhantomData;
 
 pub(crate) static KEYBOARD: SpinLock<Keyboard<Qwertz>> = SpinLock::new(Keyboard::new());
 
 pub(crate) mod qwertz;
 const KEYBOARD_COLOR: Color = INFO;
+/// Number of characters that are buffered until they are read.
+const BUFFER_SIZE: usize = 0x80;
 
 #[derive(Debug)]
 pub(crate) struct Keyboard<T>
@@ -17,9 +20,19 @@ where
 {
     is_left_shift: bool,
     is_right_shift: bool,
+    /// Ring buffer of characters that have not yet been read.
+    buffer: [char; BUFFER_SIZE],
+    head: usize,
+    len: usize,
+    /// Tasks waiting for input.
+    readers: WaitQueue,
     _marker: PhantomData<T>,
 }
 
+/// Safety: The tasks of the wait queue are only accessed on the core they are scheduled on, with
+/// interrupts disabled.
+unsafe impl<T> Send for Keyboard<T> where T: KeyboardType {}
+
 impl<T> Keyboard<T>
 where
     T: KeyboardType,
@@ -28,24 +41,109 @@ where
         Self {
             is_left_shift: false,
             is_right_shift: false,
+            buffer: ['\0'; BUFFER_SIZE],
+            head: 0,
+            len: 0,
+            readers: WaitQueue::new(),
             _marker: PhantomData,
         }
     }
 
+    /// Handles a scancode received by the keyboard interrupt.
     pub(crate) fn handle(&mut self, scancode: u8) {
         handle_scancode!(self, scancode, T,
             |ascii| {
                 if ascii != '\0' {
-                    print!(KEYBOARD_COLOR, "{}", ascii);
+                    self.push(ascii);
                 }
             },
             T::LEFT_SHIFT => { self.is_left_shift = true; },
             T::LEFT_SHIFT + 0x80 => { self.is_left_shift = false; },
             T::RIGHT_SHIFT => { self.is_right_shift = true; },
             T::RIGHT_SHIFT + 0x80 => { self.is_right_shift = false; },
-            T::ENTER => println!()
+            T::ENTER => { self.push('\n'); }
         );
     }
+
+    /// Buffers a character and wakes up all waiting readers. If the buffer is full, the oldest
+    /// character is dropped.
+    fn push(&mut self, character: char) {
+        let tail = (self.head + self.len) % BUFFER_SIZE;
+        self.buffer[tail] = character;
+
+        if self.len == BUFFER_SIZE {
+            self.head = (self.head + 1) % BUFFER_SIZE;
+        } else {
+            self.len += 1;
+        }
+
+        self.readers.wake_all();
+    }
+
+    /// Takes the oldest buffered character.
+    fn pop(&mut self) -> Option<char> {
+        (self.len > 0).then(|| {
+            let character = self.buffer[self.head];
+            self.head = (self.head + 1) % BUFFER_SIZE;
+            self.len -= 1;
+            character
+        })
+    }
+}
+
+/// Reads the next character typed on the keyboard. The current task is blocked until input is
+/// available.
+pub(crate) fn read() -> Result<char, scheduling::error::SchedulerError> {
+    scheduling::wait_for(|current| {
+        let mut keyboard = KEYBOARD.lock();
+        let character = keyboard.pop();
+
+        if character.is_none() {
+            keyboard
+                .readers
+                .park(current.clone())
+                .expect("the current task cannot have finished");
+        }
+
+        character
+    })
+}
+
+/// Reads characters typed on the keyboard into the buffer, encoded as UTF-8. The current task is
+/// blocked until input is available. Returns the number of bytes read, characters which do not
+/// fit into the buffer remain buffered.
+pub(crate) fn read_utf8(buffer: &mut [u8]) -> Result<usize, scheduling::error::SchedulerError> {
+    scheduling::wait_for(|current| {
+        let mut keyboard = KEYBOARD.lock();
+
+        if keyboard.len == 0 {
+            keyboard
+                .readers
+                .park(current.clone())
+                .expect("the current task cannot have finished");
+            return None;
+        }
+
+        let mut length = 0;
+        while keyboard.len > 0 {
+            let character = keyboard.buffer[keyboard.head];
+            if length + character.len_utf8() > buffer.len() {
+                break;
+            }
+            character.encode_utf8(&mut buffer[length..]);
+            length += character.len_utf8();
+            keyboard.pop();
+        }
+
+        Some(length)
+    })
+}
+
+/// Kernel task echoing the keyboard input to the screen.
+pub(crate) fn console() {
+    while let Ok(character) = read() {
+        print!(KEYBOARD_COLOR, "{}", character);
+    }
 }
 
 pub(crate) trait KeyboardType {
@@ -0,0 +1,19 @@
+use scheduler::memory::AddressSpaceError;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ElfError {
+    #[error("Elf parsing error: {0}")]
+    Goblin(#[from] goblin::error::Error),
+    #[error("{0}")]
+    AddressSpace(#[from] AddressSpaceError),
+    #[error("Invalid ELF-format, 64-bit little endian is required")]
+    InvalidFormat,
+    #[error("Unsupported machine type: {0:#x}")]
+    UnsupportedMachine(u16),
+    #[error("Unsupported ELF type: {0}, only static executables can be loaded")]
+    UnsupportedType(u16),
+    #[error("Invalid loadable segment at {0:#x}")]
+    InvalidSegment(u64),
+    #[error("Arguments and environment exceed {0} bytes")]
+    ArgumentsTooLong(usize),
+}
@@ -0,0 +1,264 @@
+use alloc::vec::Vec;
+use error::ElfError;
+use goblin::elf::{
+    header::{EM_X86_64, ET_EXEC},
+    program_header::{ProgramHeader, PT_LOAD},
+    Elf,
+};
+use mem::{
+    align_down, align_up, paging::PageEntryFlags, FrameAllocator, VirtualAddress, PAGE_SIZE,
+    USER_STACK_SIZE, USER_STACK_TOP, USER_VIRTUAL_MAX,
+};
+use scheduler::memory::AddressSpace;
+
+use crate::vmm::object::VmFlags;
+
+pub(crate) mod error;
+
+/// Maximum size of the arguments, environment and auxiliary vector on the initial user stack. The
+/// rest of the stack is left to the program.
+const ARGUMENTS_MAX: usize = USER_STACK_SIZE / 4;
+
+// auxiliary vector entry types (System V ABI)
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+
+/// Statically linked ELF64 executable of a user program.
+pub(crate) struct Executable<'a> {
+    data: &'a [u8],
+    elf: Elf<'a>,
+}
+
+impl<'a> Executable<'a> {
+    /// Parses the executable and validates its loadable segments against the file data.
+    pub(crate) fn parse(data: &'a [u8]) -> Result<Executable<'a>, ElfError> {
+        let elf = Elf::parse(data)?;
+
+        if !elf.is_64 || !elf.little_endian {
+            return Err(ElfError::InvalidFormat);
+        }
+        if elf.header.e_machine != EM_X86_64 {
+            return Err(ElfError::UnsupportedMachine(elf.header.e_machine));
+        }
+        // position independent executables would have to be relocated
+        if elf.header.e_type != ET_EXEC {
+            return Err(ElfError::UnsupportedType(elf.header.e_type));
+        }
+
+        for segment in elf.program_headers.iter() {
+            if segment.p_type != PT_LOAD {
+                continue;
+            }
+
+            let in_file = segment
+                .p_offset
+                .checked_add(segment.p_filesz)
+                .is_some_and(|end| end <= data.len() as u64);
+            if !in_file || segment.p_filesz > segment.p_memsz {
+                return Err(ElfError::InvalidSegment(segment.p_vaddr));
+            }
+        }
+
+        Ok(Executable { data, elf })
+    }
+}
+
+impl Executable<'_> {
+    /// Retrieve entry point address
+    pub(crate) fn entry(&self) -> VirtualAddress {
+        self.elf.entry
+    }
+
+    /// Maps the loadable segments into the address space with their permissions, followed by
+    /// the user stack below [`USER_STACK_TOP`]. Pages shared by several segments get the
+    /// combined permissions of all of them. The stack is set up with the arguments, environment
+    /// and auxiliary vector as expected by the System V ABI. Returns the initial stack pointer,
+    /// which points to the argument count.
+    ///
+    /// Note: The address space does not need to be active.
+    pub(crate) fn load(
+        &self,
+        address_space: &mut AddressSpace,
+        pmm: &mut FrameAllocator,
+        argv: &[&str],
+        envp: &[&str],
+    ) -> Result<VirtualAddress, ElfError> {
+        // frames are zeroed when mapped, which takes care of `.bss`
+        for (start, end, flags) in self.regions()? {
+            let page_count = (end - start) as usize / PAGE_SIZE;
+            address_space.map_user(start, page_count, flags.into(), pmm)?;
+        }
+
+        for segment in self.segments() {
+            if segment.p_memsz == 0 {
+                continue;
+            }
+
+            let offset = segment.p_offset as usize;
+            address_space.write(
+                segment.p_vaddr,
+                &self.data[offset..offset + segment.p_filesz as usize],
+            )?;
+        }
+
+        address_space.map_user(
+            USER_STACK_TOP - USER_STACK_SIZE as u64,
+            USER_STACK_SIZE / PAGE_SIZE,
+            PageEntryFlags::READ_WRITE | PageEntryFlags::EXECUTE_DISABLE,
+            pmm,
+        )?;
+
+        let (stack_pointer, stack) = self.initial_stack(argv, envp)?;
+        address_space.write(stack_pointer, &stack)?;
+
+        Ok(stack_pointer)
+    }
+
+    /// Builds the top of the initial user stack. Returns the stack pointer and the stack content
+    /// up to [`USER_STACK_TOP`]:
+    ///
+    /// ```text
+    /// argc, argv[0..argc], NULL, envp[..], NULL, auxv[..], AT_NULL, padding, strings
+    /// ```
+    fn initial_stack(
+        &self,
+        argv: &[&str],
+        envp: &[&str],
+    ) -> Result<(VirtualAddress, Vec<u8>), ElfError> {
+        let auxv = self.auxiliary_vector();
+        let strings: usize = argv.iter().chain(envp).map(|arg| arg.len() + 1).sum();
+        let words = 1 + (argv.len() + 1) + (envp.len() + 1) + 2 * (auxv.len() + 1);
+
+        if strings + words * size_of::<u64>() > ARGUMENTS_MAX {
+            return Err(ElfError::ArgumentsTooLong(ARGUMENTS_MAX));
+        }
+
+        // the stack pointer must be 16-byte aligned at the entry point
+        let strings_start = USER_STACK_TOP - strings as u64;
+        let stack_pointer = align_down(strings_start - (words * size_of::<u64>()) as u64, 16);
+
+        let mut stack = Vec::with_capacity((USER_STACK_TOP - stack_pointer) as usize);
+        let mut push = |word: u64| stack.extend_from_slice(&word.to_ne_bytes());
+
+        push(argv.len() as u64);
+        let mut string = strings_start;
+        for list in [argv, envp] {
+            for arg in list {
+                push(string);
+                string += arg.len() as u64 + 1;
+            }
+            push(0);
+        }
+        for (kind, value) in auxv {
+            push(kind);
+            push(value);
+        }
+        push(AT_NULL);
+        push(0);
+
+        stack.resize((strings_start - stack_pointer) as usize, 0);
+        for arg in argv.iter().chain(envp) {
+            stack.extend_from_slice(arg.as_bytes());
+            stack.push(0);
+        }
+
+        Ok((stack_pointer, stack))
+    }
+
+    /// Returns the entries of the auxiliary vector, excluding the terminating [`AT_NULL`].
+    fn auxiliary_vector(&self) -> Vec<(u64, u64)> {
+        let header = &self.elf.header;
+        let mut auxv = Vec::with_capacity(5);
+
+        // the program headers are only accessible if they are part of a loadable segment
+        if let Some(segment) = self.segments().find(|segment| {
+            (segment.p_offset..segment.p_offset + segment.p_filesz).contains(&header.e_phoff)
+        }) {
+            auxv.push((
+                AT_PHDR,
+                segment.p_vaddr + (header.e_phoff - segment.p_offset),
+            ));
+            auxv.push((AT_PHENT, header.e_phentsize as u64));
+            auxv.push((AT_PHNUM, header.e_phnum as u64));
+        }
+        auxv.push((AT_PAGESZ, PAGE_SIZE as u64));
+        auxv.push((AT_ENTRY, self.entry()));
+
+        auxv
+    }
+
+    /// Returns the page-aligned regions covered by the loadable segments as start, end and
+    /// permissions, sorted by their start. Segments sharing a page, e.g. if the linker does not
+    /// align them to pages, are split at their page boundaries, such that each region has the
+    /// combined permissions of all segments covering it.
+    fn regions(&self) -> Result<Vec<(VirtualAddress, VirtualAddress, VmFlags)>, ElfError> {
+        let mut spans = Vec::new();
+        for segment in self.segments() {
+            if segment.p_memsz == 0 {
+                continue;
+            }
+
+            let end = segment
+                .p_vaddr
+                .checked_add(segment.p_memsz)
+                .filter(|end| *end <= USER_VIRTUAL_MAX)
+                .ok_or(ElfError::InvalidSegment(segment.p_vaddr))?;
+            spans.push((
+                align_down(segment.p_vaddr, PAGE_SIZE),
+                align_up(end, PAGE_SIZE),
+                Self::flags(segment),
+            ));
+        }
+
+        let mut bounds: Vec<VirtualAddress> = spans
+            .iter()
+            .flat_map(|(start, end, _)| [*start, *end])
+            .collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut regions: Vec<(VirtualAddress, VirtualAddress, VmFlags)> = Vec::new();
+        for bound in bounds.windows(2) {
+            let (start, end) = (bound[0], bound[1]);
+            let Some(flags) = spans
+                .iter()
+                .filter(|(from, to, _)| *from <= start && end <= *to)
+                .map(|(_, _, flags)| *flags)
+                .reduce(|combined, flags| combined | flags)
+            else {
+                continue;
+            };
+
+            match regions.last_mut() {
+                Some(last) if last.1 == start && last.2 == flags => last.1 = end,
+                _ => regions.push((start, end, flags)),
+            }
+        }
+
+        Ok(regions)
+    }
+
+    /// Returns the loadable segments.
+    fn segments(&self) -> impl Iterator<Item = &ProgramHeader> {
+        self.elf
+            .program_headers
+            .iter()
+            .filter(|segment| segment.p_type == PT_LOAD)
+    }
+
+    /// Returns the permissions of the segment, which is always accessible by user code.
+    fn flags(segment: &ProgramHeader) -> VmFlags {
+        let mut flags = VmFlags::USER;
+        if segment.is_write() {
+            flags |= VmFlags::WRITE;
+        }
+        if segment.is_executable() {
+            flags |= VmFlags::EXECUTABLE;
+        }
+        flags
+    }
+}
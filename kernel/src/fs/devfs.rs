@@ -0,0 +1,246 @@
+use core::fmt::Debug;
+
+use alloc::{collections::btree_map::BTreeMap, rc::Rc, string::String, vec::Vec};
+use framebuffer::color;
+use hal::interrupts::without_interrupts;
+use sync::locked::Locked;
+use vfs::{
+    block::BlockDevice,
+    error::VfsError,
+    inode::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+};
+
+use super::error::FsError;
+use crate::{drivers::keyboard, print, serial};
+
+/// Inode number of the root directory.
+const ROOT_INO: u64 = 1;
+
+/// Size of the largest character read from the keyboard, encoded as UTF-8.
+const CHARACTER_SIZE_MAX: usize = 4;
+
+/// Registered devices by their name, with their inode number.
+static DEVICES: Locked<BTreeMap<String, (u64, Device)>> = Locked::new();
+
+/// Registers the devices of the kernel and returns the filesystem exposing them.
+pub(crate) fn initialize() -> Result<DevFs, FsError> {
+    DEVICES.initialize(BTreeMap::new());
+
+    register("null", Device::Null)?;
+    register("zero", Device::Zero)?;
+    register("ttyS0", Device::Serial)?;
+    register("console", Device::Console)?;
+    register("keyboard", Device::Keyboard)?;
+
+    Ok(DevFs)
+}
+
+/// Adds the device as the file `name` of the device filesystem.
+pub(crate) fn register(name: &str, device: Device) -> Result<(), FsError> {
+    without_interrupts(|| {
+        let mut locked = DEVICES.locked();
+        let devices = locked.get_mut().ok_or(FsError::Uninitialized)?;
+        if devices.contains_key(name) {
+            return Err(FsError::Vfs(VfsError::AlreadyExists));
+        }
+
+        // devices are never removed
+        let ino = ROOT_INO + 1 + devices.len() as u64;
+        devices.insert(String::from(name), (ino, device));
+        Ok(())
+    })
+}
+
+/// Device exposed as a file.
+#[derive(Clone)]
+pub(crate) enum Device {
+    /// Discards all writes and reads nothing.
+    Null,
+    /// Discards all writes and reads zeros.
+    Zero,
+    /// First serial port, which can only be written.
+    Serial,
+    /// Framebuffer console, which can only be written.
+    Console,
+    /// Characters typed on the keyboard encoded as UTF-8, reading blocks until input is
+    /// available.
+    Keyboard,
+    #[allow(dead_code)] // there are no storage drivers yet
+    Block(Rc<dyn BlockDevice>),
+}
+
+impl Device {
+    fn kind(&self) -> InodeType {
+        match self {
+            Device::Block(_) => InodeType::BlockDevice,
+            _ => InodeType::CharDevice,
+        }
+    }
+}
+
+impl Debug for Device {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Device::Null => f.write_str("Null"),
+            Device::Zero => f.write_str("Zero"),
+            Device::Serial => f.write_str("Serial"),
+            Device::Console => f.write_str("Console"),
+            Device::Keyboard => f.write_str("Keyboard"),
+            Device::Block(device) => f
+                .debug_struct("Block")
+                .field("block_size", &device.block_size())
+                .field("block_count", &device.block_count())
+                .finish(),
+        }
+    }
+}
+
+/// Filesystem with a flat directory of the registered devices.
+#[derive(Debug)]
+pub(crate) struct DevFs;
+
+impl FileSystem for DevFs {
+    fn name(&self) -> &str {
+        "devfs"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(Rc::new(DevRoot))
+    }
+
+    fn sync(&self) -> Result<(), VfsError> {
+        for (_, device) in with_devices(|devices| devices.values().cloned().collect::<Vec<_>>())? {
+            if let Device::Block(device) = device {
+                device.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f` with the registered devices locked.
+fn with_devices<T, F>(f: F) -> Result<T, VfsError>
+where
+    F: FnOnce(&BTreeMap<String, (u64, Device)>) -> T,
+{
+    without_interrupts(|| {
+        let locked = DEVICES.locked();
+        // the filesystem is only created after the registry has been initialized
+        let devices = locked.get().ok_or(VfsError::Io)?;
+        Ok(f(devices))
+    })
+}
+
+#[derive(Debug)]
+struct DevRoot;
+
+impl Inode for DevRoot {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        Ok(Metadata {
+            ino: ROOT_INO,
+            kind: InodeType::Directory,
+            size: 0,
+            mode: 0o755,
+            links: 2,
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        let (ino, device) =
+            with_devices(|devices| devices.get(name).cloned())?.ok_or(VfsError::NotFound)?;
+        Ok(Rc::new(DevInode { ino, device }))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        with_devices(|devices| {
+            devices
+                .iter()
+                .map(|(name, (ino, device))| DirEntry {
+                    name: name.clone(),
+                    ino: *ino,
+                    kind: device.kind(),
+                })
+                .collect()
+        })
+    }
+}
+
+/// File of a device, which accesses the device directly.
+#[derive(Debug)]
+struct DevInode {
+    ino: u64,
+    device: Device,
+}
+
+impl Inode for DevInode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        let (size, mode) = match &self.device {
+            Device::Null | Device::Zero | Device::Serial | Device::Console => (0, 0o666),
+            Device::Keyboard => (0, 0o444),
+            Device::Block(device) => (device.size(), 0o660),
+        };
+        Ok(Metadata {
+            ino: self.ino,
+            kind: self.device.kind(),
+            size,
+            mode,
+            links: 1,
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        match &self.device {
+            Device::Null => Ok(0),
+            Device::Zero => {
+                buffer.fill(0);
+                Ok(buffer.len())
+            }
+            Device::Serial | Device::Console => Err(VfsError::NotSupported),
+            Device::Keyboard => {
+                if buffer.is_empty() {
+                    return Ok(0);
+                }
+                if buffer.len() < CHARACTER_SIZE_MAX {
+                    return Err(VfsError::InvalidArgument);
+                }
+                keyboard::read_utf8(buffer).map_err(|_| VfsError::Io)
+            }
+            Device::Block(device) => {
+                let size = device.size();
+                if offset >= size {
+                    return Ok(0);
+                }
+
+                let length = buffer.len().min((size - offset) as usize);
+                device.read_at(offset, &mut buffer[..length])?;
+                Ok(length)
+            }
+        }
+    }
+
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<usize, VfsError> {
+        match &self.device {
+            Device::Null | Device::Zero => Ok(buffer.len()),
+            Device::Serial => {
+                serial::write(buffer);
+                Ok(buffer.len())
+            }
+            Device::Console => {
+                // the write system call passes characters split by its buffer as a whole
+                print!(color::LOG, "{}", String::from_utf8_lossy(buffer));
+                Ok(buffer.len())
+            }
+            Device::Keyboard => Err(VfsError::NotSupported),
+            Device::Block(device) => {
+                let size = device.size();
+                if offset >= size {
+                    return Err(VfsError::NoSpace);
+                }
+
+                let length = buffer.len().min((size - offset) as usize);
+                device.write_at(offset, &buffer[..length])?;
+                Ok(length)
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use core::fmt;
+
+use vfs::error::VfsError;
+
+use crate::{memory::vmm::error::VmmError, scheduling::error::SchedulerError};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum FsError {
+    #[error("{0}")]
+    Vfs(#[from] VfsError),
+    #[error("{0}")]
+    Scheduler(#[from] SchedulerError),
+    #[error("{0}")]
+    Vmm(#[from] VmmError),
+    #[error("Formatting generated file failed")]
+    Format(#[from] fmt::Error),
+    #[error("Filesystem namespace has not been initialized")]
+    Uninitialized,
+}
@@ -0,0 +1,134 @@
+use alloc::{rc::Rc, vec::Vec};
+use error::FsError;
+use hal::interrupts::without_interrupts;
+use scheduler::task::Priority;
+use sync::locked::Locked;
+use vfs::{
+    error::VfsError,
+    fd::{FileTable, SharedFile},
+    file::{OpenFlags, SeekFrom},
+    inode::FileSystem,
+    mount::MountTable,
+};
+
+use crate::scheduling;
+
+pub(crate) mod devfs;
+pub(crate) mod error;
+pub(crate) mod procfs;
+pub(crate) mod tmpfs;
+
+/// Namespace of all mounted filesystems.
+static VFS: Locked<MountTable> = Locked::new();
+
+/// Initializes the global namespace with the root filesystem mounted at `/`.
+pub(crate) fn initialize(root: Rc<dyn FileSystem>) -> Result<(), FsError> {
+    VFS.initialize(MountTable::new());
+    with_vfs(|vfs| vfs.mount("/", root))
+}
+
+/// Mounts the filesystem at the path of the global namespace.
+pub(crate) fn mount(path: &str, fs: Rc<dyn FileSystem>) -> Result<(), FsError> {
+    with_vfs(|vfs| vfs.mount(path, fs))
+}
+
+/// Opens the file at the path for the current process. Returns the new file descriptor.
+pub(crate) fn open(path: &str, flags: OpenFlags) -> Result<usize, FsError> {
+    let file = with_vfs(|vfs| vfs.open(path, flags))?;
+    with_files(|files| files.insert(file))
+}
+
+/// Closes the file descriptor of the current process.
+pub(crate) fn close(fd: usize) -> Result<(), FsError> {
+    with_files(|files| files.remove(fd))?;
+    Ok(())
+}
+
+/// Reads from the file descriptor of the current process into the buffer. Returns the number of
+/// bytes read, which is 0 at the end of the file.
+pub(crate) fn read(fd: usize, buffer: &mut [u8]) -> Result<usize, FsError> {
+    let file = file(fd)?;
+    // Reading from a device may block, during which other tasks sharing the file must still be
+    // able to use it, so read from a copy and only borrow the file to store the new offset.
+    let mut copy = file.borrow().clone();
+    let read = copy.read(buffer)?;
+    file.borrow_mut().seek(SeekFrom::Start(copy.offset()))?;
+    Ok(read)
+}
+
+/// Writes the buffer to the file descriptor of the current process. Returns the number of bytes
+/// written.
+pub(crate) fn write(fd: usize, buffer: &[u8]) -> Result<usize, FsError> {
+    Ok(file(fd)?.borrow_mut().write(buffer)?)
+}
+
+/// Sets the offset of the file descriptor of the current process. Returns the new offset.
+pub(crate) fn seek(fd: usize, position: SeekFrom) -> Result<u64, FsError> {
+    Ok(file(fd)?.borrow_mut().seek(position)?)
+}
+
+/// Returns whether the file descriptor is open in the current process.
+pub(crate) fn is_open(fd: usize) -> Result<bool, FsError> {
+    match file(fd) {
+        Ok(_) => Ok(true),
+        Err(FsError::Vfs(VfsError::BadDescriptor(_))) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads the whole regular file at the path.
+pub(crate) fn read_to_end(path: &str) -> Result<Vec<u8>, FsError> {
+    with_vfs(|vfs| vfs.read_to_end(path))
+}
+
+/// Reads the whole regular file at the path, or returns `None` if it does not exist.
+pub(crate) fn read_if_exists(path: &str) -> Result<Option<Vec<u8>>, FsError> {
+    match read_to_end(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(FsError::Vfs(VfsError::NotFound)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Creates a new user process from the ELF64 executable at the path, see
+/// [`scheduling::spawn_elf`]. Returns the PID of the new process.
+#[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+pub(crate) fn spawn(
+    path: &str,
+    argv: &[&str],
+    envp: &[&str],
+    priority: Priority,
+) -> Result<u64, FsError> {
+    let data = read_to_end(path)?;
+    Ok(scheduling::spawn_elf(&data, argv, envp, priority)?)
+}
+
+/// Returns the open file of the file descriptor of the current process.
+fn file(fd: usize) -> Result<SharedFile, FsError> {
+    with_files(|files| files.get(fd))
+}
+
+/// Runs `f` with the file table of the current process.
+fn with_files<T, F>(f: F) -> Result<T, FsError>
+where
+    F: FnOnce(&mut FileTable) -> Result<T, VfsError>,
+{
+    without_interrupts(|| {
+        let task = scheduling::current()?;
+        let task = task.borrow();
+        let mut process = task.process().borrow_mut();
+        Ok(f(process.files_mut())?)
+    })
+}
+
+/// Runs `f` with the global namespace locked.
+fn with_vfs<T, F>(f: F) -> Result<T, FsError>
+where
+    F: FnOnce(&mut MountTable) -> Result<T, VfsError>,
+{
+    without_interrupts(|| {
+        let mut locked = VFS.locked();
+        let vfs = locked.get_mut().ok_or(FsError::Uninitialized)?;
+        Ok(f(vfs)?)
+    })
+}
@@ -0,0 +1,208 @@
+use core::fmt::Write;
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use hal::interrupts::without_interrupts;
+use vfs::{
+    error::VfsError,
+    inode::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+};
+
+use super::error::FsError;
+use crate::{
+    acpi, idt,
+    memory::vmm::{error::VmmError, VirtualMemoryManager, VMM},
+    scheduling::stats,
+};
+
+/// Inode number of the root directory.
+const ROOT_INO: u64 = 1;
+
+/// Function appending the content of a file to the string.
+type Generator = fn(&mut String) -> Result<(), FsError>;
+
+/// Files of the filesystem with the function generating their content.
+const FILES: [(&str, Generator); 6] = [
+    ("acpi", acpi),
+    ("interrupts", interrupts),
+    ("meminfo", meminfo),
+    ("tasks", tasks),
+    ("uptime", uptime),
+    ("vmm", vmm),
+];
+
+/// Read-only filesystem exposing the state of the kernel. The content of the files is generated on
+/// every read, their size is reported as 0.
+#[derive(Debug)]
+pub(crate) struct ProcFs;
+
+impl FileSystem for ProcFs {
+    fn name(&self) -> &str {
+        "procfs"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(Rc::new(ProcInode { file: None }))
+    }
+}
+
+#[derive(Debug)]
+struct ProcInode {
+    /// Index into [`FILES`], `None` for the root directory.
+    file: Option<usize>,
+}
+
+impl Inode for ProcInode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        Ok(match self.file {
+            Some(index) => Metadata {
+                ino: ROOT_INO + 1 + index as u64,
+                kind: InodeType::File,
+                size: 0,
+                mode: 0o444,
+                links: 1,
+            },
+            None => Metadata {
+                ino: ROOT_INO,
+                kind: InodeType::Directory,
+                size: 0,
+                mode: 0o555,
+                links: 2,
+            },
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        let (_, generate) = FILES[self.file.ok_or(VfsError::NotSupported)?];
+
+        let mut content = String::new();
+        generate(&mut content).map_err(|err| match err {
+            FsError::Vfs(err) => err,
+            _ => VfsError::Io,
+        })?;
+
+        let data = content
+            .as_bytes()
+            .get(offset as usize..)
+            .unwrap_or_default();
+        let length = data.len().min(buffer.len());
+        buffer[..length].copy_from_slice(&data[..length]);
+        Ok(length)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        if self.file.is_some() {
+            return Err(VfsError::NotDirectory);
+        }
+
+        let index = FILES
+            .iter()
+            .position(|(file, _)| *file == name)
+            .ok_or(VfsError::NotFound)?;
+        Ok(Rc::new(ProcInode { file: Some(index) }))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        if self.file.is_some() {
+            return Err(VfsError::NotDirectory);
+        }
+
+        Ok(FILES
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| DirEntry {
+                name: String::from(*name),
+                ino: ROOT_INO + 1 + index as u64,
+                kind: InodeType::File,
+            })
+            .collect())
+    }
+}
+
+/// Runs `f` with the global virtual memory manager locked.
+fn with_vmm<T, F>(f: F) -> Result<T, FsError>
+where
+    F: FnOnce(&mut VirtualMemoryManager) -> Result<T, FsError>,
+{
+    without_interrupts(|| {
+        let mut locked = VMM.locked();
+        let vmm = locked.get_mut().ok_or(VmmError::VmmUnitialized)?;
+        f(vmm)
+    })
+}
+
+/// Lists the ACPI tables found.
+fn acpi(out: &mut String) -> Result<(), FsError> {
+    writeln!(
+        out,
+        "{:<9} {:>8} {:>8} {:<6} {:<8}",
+        "SIGNATURE", "LENGTH", "REVISION", "OEM", "OEMTABLE"
+    )?;
+
+    for header in acpi::tables() {
+        writeln!(
+            out,
+            "{:<9} {:>8} {:>8} {:<6} {:<8}",
+            String::from_utf8_lossy(&header.signature().0),
+            header.length(),
+            header.revision(),
+            String::from_utf8_lossy(&header.oem_id()).trim_end(),
+            String::from_utf8_lossy(&header.oem_table_id()).trim_end()
+        )?;
+    }
+    Ok(())
+}
+
+/// Lists the number of interrupts dispatched per vector.
+fn interrupts(out: &mut String) -> Result<(), FsError> {
+    writeln!(out, "{:>6} {:>12}", "VECTOR", "COUNT")?;
+    for (vector, count) in idt::interrupt_counts() {
+        writeln!(out, "{:>#6x} {:>12}", vector, count)?;
+    }
+    Ok(())
+}
+
+/// Shows the usage of physical memory.
+fn meminfo(out: &mut String) -> Result<(), FsError> {
+    with_vmm(|vmm| {
+        let pmm = vmm.ptm().pmm();
+        writeln!(out, "free:     {:>12} KiB", pmm.free_memory() / 1024)?;
+        writeln!(out, "used:     {:>12} KiB", pmm.used_memory() / 1024)?;
+        writeln!(out, "reserved: {:>12} KiB", pmm.reserved_memory() / 1024)?;
+        Ok(())
+    })
+}
+
+/// Lists all tasks of the scheduler.
+fn tasks(out: &mut String) -> Result<(), FsError> {
+    write!(out, "{}", stats::snapshot()?)?;
+    Ok(())
+}
+
+/// Shows the time since the scheduler has been initialized in seconds.
+fn uptime(out: &mut String) -> Result<(), FsError> {
+    let uptime = stats::uptime()?;
+    writeln!(out, "{}.{:03}", uptime.as_secs(), uptime.subsec_millis())?;
+    Ok(())
+}
+
+/// Lists the objects allocated by the virtual memory manager.
+fn vmm(out: &mut String) -> Result<(), FsError> {
+    with_vmm(|vmm| {
+        let (allocated, total) = vmm.page_counts();
+        writeln!(out, "pages: {} of {} allocated", allocated, total)?;
+        writeln!(
+            out,
+            "{:<18} {:>10} {:>8} FLAGS",
+            "ADDRESS", "LENGTH", "GUARD"
+        )?;
+
+        for object in vmm.objects() {
+            writeln!(
+                out,
+                "{:#018x} {:>10} {:>8} {:?}",
+                object.address, object.length, object.guard, object.flags
+            )?;
+        }
+        Ok(())
+    })
+}
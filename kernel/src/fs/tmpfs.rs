@@ -0,0 +1,201 @@
+use core::cell::{Cell, RefCell};
+
+use alloc::{collections::btree_map::BTreeMap, rc::Rc, string::String, vec::Vec};
+use vfs::{
+    error::VfsError,
+    inode::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+};
+
+/// Inode number of the root directory.
+const ROOT_INO: u64 = 1;
+
+/// Writable in-memory filesystem, whose files are stored on the kernel heap. The files are lost
+/// once the filesystem is dropped.
+#[derive(Debug)]
+pub(crate) struct TmpFs {
+    root: Rc<TmpInode>,
+}
+
+impl TmpFs {
+    pub(crate) fn new() -> TmpFs {
+        let root = TmpInode {
+            ino: ROOT_INO,
+            // everyone may create files
+            mode: 0o1777,
+            next_ino: Rc::new(Cell::new(ROOT_INO + 1)),
+            content: RefCell::new(Content::Directory(BTreeMap::new())),
+        };
+        TmpFs {
+            root: Rc::new(root),
+        }
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn name(&self) -> &str {
+        "tmpfs"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(self.root.clone())
+    }
+}
+
+#[derive(Debug)]
+enum Content {
+    File(Vec<u8>),
+    Directory(BTreeMap<String, Rc<TmpInode>>),
+}
+
+/// File of a tmpfs. Removed files are kept until the last reference to them is dropped.
+#[derive(Debug)]
+struct TmpInode {
+    ino: u64,
+    /// Permission bits
+    mode: u16,
+    /// Inode number of the next file created, shared by all files of the filesystem.
+    next_ino: Rc<Cell<u64>>,
+    content: RefCell<Content>,
+}
+
+impl TmpInode {
+    /// Sets the size of the data, growing it with zeros. Fails with [`VfsError::NoSpace`] if the
+    /// kernel heap is exhausted.
+    fn resize(data: &mut Vec<u8>, size: u64) -> Result<(), VfsError> {
+        let size = usize::try_from(size).map_err(|_| VfsError::NoSpace)?;
+
+        if size > data.len() {
+            data.try_reserve(size - data.len())
+                .map_err(|_| VfsError::NoSpace)?;
+            data.resize(size, 0);
+        } else {
+            data.truncate(size);
+            data.shrink_to_fit();
+        }
+        Ok(())
+    }
+}
+
+impl Inode for TmpInode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        let (kind, size, links) = match &*self.content.borrow() {
+            Content::File(data) => (InodeType::File, data.len() as u64, 1),
+            Content::Directory(_) => (InodeType::Directory, 0, 2),
+        };
+        Ok(Metadata {
+            ino: self.ino,
+            kind,
+            size,
+            mode: self.mode,
+            links,
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        let Content::File(data) = &*self.content.borrow() else {
+            return Err(VfsError::NotSupported);
+        };
+
+        let data = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| data.get(offset..))
+            .unwrap_or_default();
+        let length = data.len().min(buffer.len());
+        buffer[..length].copy_from_slice(&data[..length]);
+        Ok(length)
+    }
+
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<usize, VfsError> {
+        let Content::File(data) = &mut *self.content.borrow_mut() else {
+            return Err(VfsError::IsDirectory);
+        };
+
+        let end = offset
+            .checked_add(buffer.len() as u64)
+            .ok_or(VfsError::NoSpace)?;
+        if end > data.len() as u64 {
+            Self::resize(data, end)?;
+        }
+
+        data[offset as usize..end as usize].copy_from_slice(buffer);
+        Ok(buffer.len())
+    }
+
+    fn truncate(&self, size: u64) -> Result<(), VfsError> {
+        match &mut *self.content.borrow_mut() {
+            Content::File(data) => Self::resize(data, size),
+            Content::Directory(_) => Err(VfsError::IsDirectory),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        let Content::Directory(entries) = &*self.content.borrow() else {
+            return Err(VfsError::NotDirectory);
+        };
+
+        let inode = entries.get(name).ok_or(VfsError::NotFound)?;
+        Ok(inode.clone())
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        let Content::Directory(entries) = &*self.content.borrow() else {
+            return Err(VfsError::NotDirectory);
+        };
+
+        entries
+            .iter()
+            .map(|(name, inode)| {
+                Ok(DirEntry {
+                    name: name.clone(),
+                    ino: inode.ino,
+                    kind: inode.metadata()?.kind,
+                })
+            })
+            .collect()
+    }
+
+    fn create(&self, name: &str, kind: InodeType) -> Result<Rc<dyn Inode>, VfsError> {
+        let Content::Directory(entries) = &mut *self.content.borrow_mut() else {
+            return Err(VfsError::NotDirectory);
+        };
+        if entries.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        let (content, mode) = match kind {
+            InodeType::File => (Content::File(Vec::new()), 0o644),
+            InodeType::Directory => (Content::Directory(BTreeMap::new()), 0o755),
+            _ => return Err(VfsError::NotSupported),
+        };
+
+        let ino = self.next_ino.get();
+        self.next_ino.set(ino + 1);
+
+        let inode = Rc::new(TmpInode {
+            ino,
+            mode,
+            next_ino: self.next_ino.clone(),
+            content: RefCell::new(content),
+        });
+        entries.insert(String::from(name), inode.clone());
+        Ok(inode)
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), VfsError> {
+        let Content::Directory(entries) = &mut *self.content.borrow_mut() else {
+            return Err(VfsError::NotDirectory);
+        };
+
+        let inode = entries.get(name).ok_or(VfsError::NotFound)?;
+        let is_empty = match &*inode.content.borrow() {
+            Content::Directory(children) => children.is_empty(),
+            Content::File(_) => true,
+        };
+        if !is_empty {
+            return Err(VfsError::NotEmpty);
+        }
+
+        entries.remove(name);
+        Ok(())
+    }
+}
@@ -3,13 +3,22 @@ use core::{arch::asm, cell::LazyCell};
 use descriptor::SegmentDescriptor;
 use mem::VirtualAddress;
 use sync::spin::SpinLock;
-use tss::TSS;
+use tss::TaskStateSegment;
 
 mod descriptor;
 mod tss;
 
+pub(crate) use tss::{RSP0_OFFSET, TSS};
+
 pub(crate) const KERNEL_CS: u16 = 0x08;
 pub(crate) const KERNEL_DS: u16 = 0x10;
+/// User data segment selector with a requested privilege level of 3.
+pub(crate) const USER_DS: u16 = 0x18 | 3;
+/// User code segment selector with a requested privilege level of 3.
+pub(crate) const USER_CS: u16 = 0x20 | 3;
+/// Base selector used by SYSRET, which loads the user data segment from the next entry and the
+/// user code segment from the one after it.
+pub(crate) const SYSRET_BASE: u16 = KERNEL_DS;
 const KERNEL_TSS: u16 = 0x28;
 
 static GDTR: SpinLock<LazyCell<GdtDescriptor>> = SpinLock::new(LazyCell::new(GdtDescriptor::new));
@@ -38,15 +47,18 @@ impl GdtDescriptor {
 
 /// The Global Descriptor Table contains entries telling the CPU about memory segments and their
 /// permissions.
+///
+/// Note: SYSCALL and SYSRET require the kernel data segment to follow the kernel code segment and
+/// the user code segment to follow the user data segment.
 #[allow(dead_code)]
-#[repr(align(0x1000))]
+#[repr(C, align(0x1000))]
 #[derive(Debug)]
 pub(super) struct GlobalDescriptorTable {
     null: SegmentDescriptor,
     kernel_code: SegmentDescriptor,
     kernel_data: SegmentDescriptor,
-    user_code: SegmentDescriptor,
     user_data: SegmentDescriptor,
+    user_code: SegmentDescriptor,
     tss_low: SegmentDescriptor,
     tss_high: SegmentDescriptor,
 }
@@ -60,14 +72,25 @@ impl GlobalDescriptorTable {
             null: SegmentDescriptor::null(),
             kernel_code: SegmentDescriptor::kernel_code(),
             kernel_data: SegmentDescriptor::kernel_data(),
-            user_code: SegmentDescriptor::user_code(),
             user_data: SegmentDescriptor::user_data(),
+            user_code: SegmentDescriptor::user_code(),
             tss_low,
             tss_high,
         }
     }
 }
 
+/// Sets the stack the CPU switches to when an interrupt occurs while executing user code.
+///
+/// # Safety
+/// The caller must guarantee that the stack is mapped and not in use by any other task. This must
+/// only be called with interrupts disabled.
+pub(crate) unsafe fn set_kernel_stack(stack_top: *const u8) {
+    unsafe {
+        TaskStateSegment::set_rsp0(stack_top);
+    }
+}
+
 /// Load the GlobalDescriptorTable
 ///
 /// # Safety
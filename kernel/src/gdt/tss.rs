@@ -1,6 +1,7 @@
 #![allow(static_mut_refs)]
-// note: mutation occurs once at at the initialization of the TSS. After that, only the CPU mutates the
-// memory of these stacks during interrupt-handling.
+// note: mutation of the stacks occurs once at at the initialization of the TSS. After that, only the
+// CPU mutates the memory of these stacks during interrupt-handling. The TSS itself is only mutated
+// by the scheduler to switch the stack used for mode switches.
 
 use core::ptr::null;
 
@@ -12,17 +13,22 @@ pub(super) const TSS_AVAILABLE_FLAGS: u8 = 0x9;
 #[repr(align(16))] // stack pointer must be aligned to 16-bytes
 struct Stack([u8; KERNEL_INTERRUPT_STACK_SIZE]);
 
-/// Stack used for double faults.
+/// Stack used for double faults, so that a task overflowing its stack can be handled. A page fault
+/// in the guard page below a kernel stack cannot be delivered on that stack and escalates to a
+/// double fault.
 static mut IST_STACK: Stack = Stack([0; KERNEL_INTERRUPT_STACK_SIZE]);
 
-/// Stack used for mode switches.
+/// Stack used for mode switches, until the first task has been scheduled.
 static mut RSP_STACK: Stack = Stack([0; KERNEL_INTERRUPT_STACK_SIZE]);
 
-pub(super) static TSS: TaskStateSegment = TaskStateSegment::new();
+pub(crate) static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+/// Offset of [`TaskStateSegment::rsp0`], which is also loaded by the system call entry.
+pub(crate) const RSP0_OFFSET: usize = core::mem::offset_of!(TaskStateSegment, rsp0);
 
 #[repr(C, packed(4))]
 #[derive(Debug, Copy, Clone)]
-pub(super) struct TaskStateSegment {
+pub(crate) struct TaskStateSegment {
     _reserved0: u32,
     /// The first stack pointer used to load the stack when a privilege level change occurs from a lower privilege level to a higher one.
     rsp0: *const u8,
@@ -40,14 +46,14 @@ pub(super) struct TaskStateSegment {
     iopb: u16,
 }
 
-/// Safety: The TSS is never mutated.
+/// Safety: The TSS is only mutated with interrupts disabled.
 unsafe impl Send for TaskStateSegment {}
-/// Safety: The TSS is never mutated.
+/// Safety: The TSS is only mutated with interrupts disabled.
 unsafe impl Sync for TaskStateSegment {}
 
 impl TaskStateSegment {
-    /// Creates a new task state segment and with one static stack for double faults and one for
-    /// mode switched.
+    /// Creates a new task state segment with one static stack for double faults and one for mode
+    /// switches.
     const fn new() -> Self {
         let rsp0 = unsafe { RSP_STACK.0.as_ptr().add(KERNEL_INTERRUPT_STACK_SIZE) };
 
@@ -70,4 +76,14 @@ impl TaskStateSegment {
             _reserved_3: 0,
         }
     }
+
+    /// Sets the stack pointer loaded when a privilege level change to ring 0 occurs.
+    ///
+    /// # Safety
+    /// The caller must guarantee that the stack is valid and that interrupts are disabled.
+    pub(super) unsafe fn set_rsp0(rsp0: *const u8) {
+        unsafe {
+            (&raw mut TSS.rsp0).write_unaligned(rsp0);
+        }
+    }
 }
@@ -8,14 +8,14 @@ assign_isr!(
     4, GateType::TrapGate, 0
     5, GateType::TrapGate, 0
     6, GateType::TrapGate, 0
-    7, GateType::TrapGate, 0
-    8, GateType::TrapGate, 1, error
+    7, GateType::InterruptGate, 0 // device not available, the FPU state is switched
+    8, GateType::InterruptGate, 1, error // double fault, own stack to handle stack overflows
     9, GateType::TrapGate, 0
     10, GateType::TrapGate, 0, error
     11, GateType::TrapGate, 0, error
     12, GateType::TrapGate, 0, error
     13, GateType::TrapGate, 0, error
-    14, GateType::TrapGate, 0, error
+    14, GateType::InterruptGate, 0, error // page fault
     15, GateType::TrapGate, 0
     16, GateType::TrapGate, 0
     17, GateType::TrapGate, 0, error
@@ -29,4 +29,5 @@ assign_isr!(
     0x20 , GateType::InterruptGate, 0 // lapic timer interrupt
     0x21, GateType::InterruptGate, 0 // keyboard interrupts
     0x22, GateType::InterruptGate, 0 // pit interrupts
+    0x30, GateType::InterruptGate, 0 // scheduler yield
 );
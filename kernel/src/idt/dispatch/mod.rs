@@ -1,24 +1,80 @@
-use core::arch::{asm, naked_asm};
+use core::{
+    arch::{asm, naked_asm},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use error::{ErrorCode, PageFaultErrorCode};
 use framebuffer::color;
 use hal::{cpu_state::CpuState, hlt_loop};
-use scheduler::Scheduler;
+use scheduler::{task::Task, Scheduler};
 
 use crate::{
     drivers::keyboard::KEYBOARD,
     io::{apic::lapic, inb},
-    loginfo, pit, println, scheduling, serial_println,
+    loginfo,
+    memory::{user, vmm},
+    pit, println, scheduling, serial_println,
 };
 
 mod error;
 pub(super) mod handler;
 pub(super) mod macros;
 
-fn dispatch(state: &CpuState) -> &CpuState {
+/// Number of interrupts dispatched for each vector.
+pub(super) static COUNTS: [AtomicU64; super::IDT_MAX_DESCRIPTORS] =
+    [const { AtomicU64::new(0) }; super::IDT_MAX_DESCRIPTORS];
+
+fn dispatch(state: &mut CpuState) -> &CpuState {
     let vector_number = state.vector_number;
     let error_code = state.error_code;
 
+    if let Some(count) = COUNTS.get(vector_number as usize) {
+        count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // pages of lazy objects are backed on first access, also if the access of user code raises
+    // the fault while entering the kernel
+    if vector_number == 14 {
+        let error_code = PageFaultErrorCode::from_bits_truncate(error_code as u32);
+        if !error_code.contains(PageFaultErrorCode::PRESENT)
+            && vmm::handle_page_fault(cr2(), error_code.contains(PageFaultErrorCode::USER))
+        {
+            return state;
+        }
+
+        // pages shared with a forked process are copied on the first write, also if the kernel
+        // writes to user memory
+        if error_code.contains(PageFaultErrorCode::PRESENT | PageFaultErrorCode::WRITE)
+            && vmm::copy_on_write(cr2())
+        {
+            return state;
+        }
+    }
+
+    // exceptions raised by user code only terminate the faulting task. Breakpoints and the lazy
+    // FPU switch are handled regularly.
+    if vector_number < 32 && !matches!(vector_number, 3 | 7) && state.iretq_cs & 0b11 == 3 {
+        if let Some(task) = scheduling::terminate_active() {
+            let (tid, pid) = (task.borrow().tid(), task.borrow().pid());
+            println!(
+                color::ERROR,
+                " [ERROR]: EXCEPTION {:#x} in user task {} (PID{}), error code: {:#x}",
+                vector_number,
+                tid,
+                pid,
+                error_code
+            );
+            serial_println!(
+                " [ERROR]: EXCEPTION {:#x} in user task {} (PID{}), error code: {:#x}",
+                vector_number,
+                tid,
+                pid,
+                error_code
+            );
+            return <scheduling::PerCoreScheduler as Scheduler>::run(state);
+        }
+    }
+
     match vector_number {
         0 => {
             println!(color::ERROR, " [ERROR]: division by 0 EXCEPTION");
@@ -27,7 +83,43 @@ fn dispatch(state: &CpuState) -> &CpuState {
         3 => {
             loginfo!("breakpoint EXCEPTION");
         }
+        7 => {
+            // first use of the FPU by the active task since it has been scheduled
+            scheduling::restore_fpu();
+        }
+        8 => {
+            // a kernel task has overflowed its stack, so the page fault raised by its guard page
+            // could not be delivered on the same stack. Only that task is terminated.
+            let cr2 = cr2();
+            if vmm::is_guard_page(cr2) {
+                if let Some(task) = scheduling::terminate_active() {
+                    report_stack_overflow(&task.borrow(), cr2);
+                    return <scheduling::PerCoreScheduler as Scheduler>::run(state);
+                }
+            }
+
+            println!(color::ERROR, " [ERROR]: double FAULT");
+            serial_println!(" [ERROR]: double FAULT");
+            hlt_loop();
+        }
         14 => {
+            // faults while copying user memory abort the copy instead of the kernel
+            if let Some(rip) = user::fixup(state.iretq_rip) {
+                state.iretq_rip = rip;
+                return state;
+            }
+
+            let cr2 = cr2();
+
+            // a task has accessed the guard page below its stack without pushing to it, only
+            // that task is terminated
+            if vmm::is_guard_page(cr2) {
+                if let Some(task) = scheduling::terminate_active() {
+                    report_stack_overflow(&task.borrow(), cr2);
+                    return <scheduling::PerCoreScheduler as Scheduler>::run(state);
+                }
+            }
+
             println!(
                 color::ERROR,
                 " [ERROR]: page FAULT, error code: {:?}",
@@ -37,11 +129,6 @@ fn dispatch(state: &CpuState) -> &CpuState {
                 " [ERROR]: page FAULT, error code: {:?}",
                 PageFaultErrorCode::from_bits_truncate(error_code as u32)
             );
-            let cr2: u64;
-
-            unsafe {
-                asm!("mov {}, cr2", out(reg) cr2, options(nostack, nomem, preserves_flags));
-            }
 
             println!(color::ERROR, " [INFO ]: faulting address: {:#x}", cr2);
             serial_println!(" [INFO]: faulting address: {:#x}", cr2);
@@ -51,6 +138,7 @@ fn dispatch(state: &CpuState) -> &CpuState {
             // SAFETY: hardware interrupts are disabled until after the handler is called.
             lapic::eoi()
                 .expect("LAPIC must have been initialized before enabling hardware interrupts!");
+            scheduling::tick();
             return <scheduling::PerCoreScheduler as Scheduler>::run(state);
         }
 
@@ -66,6 +154,10 @@ fn dispatch(state: &CpuState) -> &CpuState {
             lapic::eoi()
                 .expect("LAPIC must have been initialized before enabling hardware interrupts!");
         }
+        48 => {
+            // software interrupt raised by `scheduling::yield_now`, no EOI required.
+            return <scheduling::PerCoreScheduler as Scheduler>::run(state);
+        }
 
         unknown => {
             println!(
@@ -81,6 +173,32 @@ fn dispatch(state: &CpuState) -> &CpuState {
     state
 }
 
+/// Reports that the task has been terminated after accessing the guard page at the address.
+fn report_stack_overflow(task: &Task, address: u64) {
+    println!(
+        color::ERROR,
+        " [ERROR]: stack overflow in task {} (PID{}), faulting address: {:#x}",
+        task.tid(),
+        task.pid(),
+        address
+    );
+    serial_println!(
+        " [ERROR]: stack overflow in task {} (PID{}), faulting address: {:#x}",
+        task.tid(),
+        task.pid(),
+        address
+    );
+}
+
+/// Returns the address which caused the last page fault.
+fn cr2() -> u64 {
+    let cr2: u64;
+    unsafe {
+        asm!("mov {}, cr2", out(reg) cr2, options(nostack, nomem, preserves_flags));
+    }
+    cr2
+}
+
 #[unsafe(naked)]
 extern "C" fn interrupt_stub() {
     naked_asm!(
@@ -1,4 +1,4 @@
-use core::{arch::asm, cell::LazyCell};
+use core::{arch::asm, cell::LazyCell, sync::atomic::Ordering};
 
 use descriptor::{GateDescriptor, GateFlags, GateType};
 use mem::VirtualAddress;
@@ -66,6 +66,16 @@ impl InterruptDescriptorTable {
     }
 }
 
+/// Returns the vectors which have been raised at least once, with the number of interrupts
+/// dispatched for them.
+pub(crate) fn interrupt_counts() -> impl Iterator<Item = (usize, u64)> {
+    dispatch::COUNTS
+        .iter()
+        .map(|count| count.load(Ordering::Relaxed))
+        .enumerate()
+        .filter(|(_, count)| *count > 0)
+}
+
 pub(super) unsafe fn load() {
     let idtr = IDTR.lock();
     // load idt
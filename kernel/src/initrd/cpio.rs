@@ -0,0 +1,104 @@
+use super::error::InitrdError;
+
+/// Magic number of the "new ASCII" (newc) cpio format.
+const MAGIC: &[u8] = b"070701";
+/// Size of the header, consisting of the magic number and 13 fields of 8 hex digits.
+const HEADER_SIZE: usize = MAGIC.len() + 13 * 8;
+/// Name of the entry terminating the archive.
+const TRAILER: &str = "TRAILER!!!";
+
+// indices of the header fields used
+const FIELD_MODE: usize = 1;
+const FIELD_FILESIZE: usize = 6;
+const FIELD_NAMESIZE: usize = 11;
+
+/// Entry of a cpio archive.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct Entry<'a> {
+    /// Path without leading `./` or `/`.
+    pub(super) name: &'a str,
+    /// File type and permission bits.
+    pub(super) mode: u32,
+    pub(super) data: &'a [u8],
+}
+
+/// Iterates over the entries of a cpio archive in the newc format, stopping at the trailer.
+#[derive(Debug)]
+pub(super) struct Archive<'a> {
+    data: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> Archive<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Archive<'a> {
+        Archive {
+            data,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Parses the entry at the current offset and advances to the next one. Returns `None` for
+    /// the trailer.
+    fn parse_entry(&mut self) -> Result<Option<Entry<'a>>, InitrdError> {
+        let start = self.offset;
+        let header = self
+            .data
+            .get(start..start + HEADER_SIZE)
+            .ok_or(InitrdError::MissingTrailer)?;
+        if !header.starts_with(MAGIC) {
+            return Err(InitrdError::InvalidHeader(start));
+        }
+
+        let field = |index: usize| {
+            let offset = MAGIC.len() + index * 8;
+            core::str::from_utf8(&header[offset..offset + 8])
+                .ok()
+                .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                .ok_or(InitrdError::InvalidHeader(start))
+        };
+        let mode = field(FIELD_MODE)?;
+        let file_size = field(FIELD_FILESIZE)? as usize;
+        let name_size = field(FIELD_NAMESIZE)? as usize;
+
+        // the name includes a terminating null byte, header + name as well as the data are padded
+        // to a multiple of 4 bytes
+        let name_start = start + HEADER_SIZE;
+        let data_start = (name_start + name_size).next_multiple_of(4);
+        let data_end = data_start + file_size;
+
+        let name = self
+            .data
+            .get(name_start..(name_start + name_size).saturating_sub(1))
+            .and_then(|name| core::str::from_utf8(name).ok())
+            .ok_or(InitrdError::InvalidHeader(start))?;
+        let data = self
+            .data
+            .get(data_start..data_end)
+            .ok_or(InitrdError::Truncated(start))?;
+
+        self.offset = data_end.next_multiple_of(4);
+
+        if name == TRAILER {
+            return Ok(None);
+        }
+
+        let name = name.trim_start_matches("./").trim_start_matches('/');
+        Ok(Some(Entry { name, mode, data }))
+    }
+}
+
+impl<'a> Iterator for Archive<'a> {
+    type Item = Result<Entry<'a>, InitrdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = self.parse_entry();
+        self.done = !matches!(entry, Ok(Some(_)));
+        entry.transpose()
+    }
+}
@@ -0,0 +1,9 @@
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum InitrdError {
+    #[error("Invalid cpio header at offset {0:#x}")]
+    InvalidHeader(usize),
+    #[error("Truncated cpio entry at offset {0:#x}")]
+    Truncated(usize),
+    #[error("Missing cpio trailer")]
+    MissingTrailer,
+}
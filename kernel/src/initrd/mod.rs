@@ -0,0 +1,223 @@
+use alloc::{collections::btree_map::BTreeMap, rc::Rc, string::String, vec::Vec};
+use bootinfo::Initrd;
+use cpio::Archive;
+use error::InitrdError;
+use mem::PAS_VIRTUAL;
+use vfs::{
+    error::VfsError,
+    inode::{DirEntry, FileSystem, Inode, InodeType, Metadata},
+};
+
+mod cpio;
+pub(crate) mod error;
+
+// file type bits of the mode
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Inode number of the root directory.
+const ROOT_INO: u64 = 1;
+
+/// Parses the initial ramdisk handed over by the loader.
+///
+/// Note: The archive is accessed via the mapped physical address space and must be kept for the
+/// lifetime of the kernel.
+pub(crate) fn initialize(initrd: Initrd) -> Result<Ramdisk, InitrdError> {
+    let data = unsafe {
+        core::slice::from_raw_parts((PAS_VIRTUAL + initrd.address) as *const u8, initrd.length)
+    };
+    Ramdisk::parse(data)
+}
+
+/// Read-only in-memory filesystem of the initial ramdisk. The files refer to their data in the
+/// archive.
+#[derive(Debug)]
+pub(crate) struct Ramdisk {
+    /// Files by their path relative to the root directory, which is not included.
+    files: Rc<BTreeMap<&'static str, File>>,
+}
+
+impl Ramdisk {
+    /// Parses a cpio archive in the newc format. Entries other than regular files, directories
+    /// and symbolic links are skipped. Parent directories missing in the archive are added.
+    pub(crate) fn parse(data: &'static [u8]) -> Result<Ramdisk, InitrdError> {
+        let mut files = BTreeMap::new();
+
+        for entry in Archive::new(data) {
+            let entry = entry?;
+            let path = entry.name.trim_end_matches('/');
+            if path.is_empty() || path == "." {
+                continue;
+            }
+
+            let kind = match entry.mode & S_IFMT {
+                S_IFREG => InodeType::File,
+                S_IFDIR => InodeType::Directory,
+                S_IFLNK => InodeType::Symlink,
+                _ => continue,
+            };
+            files.insert(
+                path,
+                File::new(kind, entry.mode as u16 & 0o7777, entry.data),
+            );
+
+            let mut parent = path;
+            while let Some((ancestor, _)) = parent.rsplit_once('/') {
+                files
+                    .entry(ancestor)
+                    .or_insert(File::new(InodeType::Directory, 0o755, &[]));
+                parent = ancestor;
+            }
+        }
+
+        for (index, file) in files.values_mut().enumerate() {
+            file.ino = ROOT_INO + 1 + index as u64;
+        }
+
+        Ok(Ramdisk {
+            files: Rc::new(files),
+        })
+    }
+
+    /// Returns the number of files, including directories and symbolic links.
+    pub(crate) fn len(&self) -> usize {
+        self.files.len()
+    }
+}
+
+impl FileSystem for Ramdisk {
+    fn name(&self) -> &str {
+        "initrd"
+    }
+
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError> {
+        Ok(Rc::new(RamdiskInode {
+            files: self.files.clone(),
+            path: "",
+        }))
+    }
+}
+
+/// File of the initial ramdisk.
+#[derive(Copy, Clone, Debug)]
+struct File {
+    ino: u64,
+    kind: InodeType,
+    /// Permission bits
+    mode: u16,
+    /// Content of a regular file or the target of a symbolic link.
+    data: &'static [u8],
+}
+
+impl File {
+    fn new(kind: InodeType, mode: u16, data: &'static [u8]) -> File {
+        File {
+            ino: 0,
+            kind,
+            mode,
+            data,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RamdiskInode {
+    files: Rc<BTreeMap<&'static str, File>>,
+    /// Path of the file, which is empty for the root directory.
+    path: &'static str,
+}
+
+impl RamdiskInode {
+    fn file(&self) -> Result<File, VfsError> {
+        if self.path.is_empty() {
+            return Ok(File {
+                ino: ROOT_INO,
+                ..File::new(InodeType::Directory, 0o755, &[])
+            });
+        }
+        self.files.get(self.path).copied().ok_or(VfsError::NotFound)
+    }
+
+    /// Returns the path of the entry `name` of this directory.
+    fn child(&self, name: &str) -> String {
+        let mut path = String::from(self.path);
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(name);
+        path
+    }
+}
+
+impl Inode for RamdiskInode {
+    fn metadata(&self) -> Result<Metadata, VfsError> {
+        let file = self.file()?;
+        Ok(Metadata {
+            ino: file.ino,
+            kind: file.kind,
+            size: file.data.len() as u64,
+            mode: file.mode,
+            links: if file.kind == InodeType::Directory {
+                2
+            } else {
+                1
+            },
+        })
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        let file = self.file()?;
+        if file.kind != InodeType::File {
+            return Err(VfsError::NotSupported);
+        }
+
+        let data = file.data.get(offset as usize..).unwrap_or_default();
+        let length = data.len().min(buffer.len());
+        buffer[..length].copy_from_slice(&data[..length]);
+        Ok(length)
+    }
+
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        if self.file()?.kind != InodeType::Directory {
+            return Err(VfsError::NotDirectory);
+        }
+
+        let (path, _) = self
+            .files
+            .get_key_value(self.child(name).as_str())
+            .ok_or(VfsError::NotFound)?;
+        Ok(Rc::new(RamdiskInode {
+            files: self.files.clone(),
+            path,
+        }))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        if self.file()?.kind != InodeType::Directory {
+            return Err(VfsError::NotDirectory);
+        }
+
+        Ok(self
+            .files
+            .iter()
+            .filter_map(|(path, file)| {
+                let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+                (parent == self.path).then(|| DirEntry {
+                    name: String::from(name),
+                    ino: file.ino,
+                    kind: file.kind,
+                })
+            })
+            .collect())
+    }
+
+    fn read_link(&self) -> Result<String, VfsError> {
+        let file = self.file()?;
+        if file.kind != InodeType::Symlink {
+            return Err(VfsError::NotSymlink);
+        }
+        Ok(String::from_utf8_lossy(file.data).into_owned())
+    }
+}
@@ -3,7 +3,7 @@ use core::{
     sync::atomic::{AtomicPtr, Ordering},
 };
 
-use mem::{PhysicalAddress, PAGE_SIZE};
+use mem::{PhysicalAddress, LAPIC_VIRTUAL, PAGE_SIZE};
 
 use crate::vmm::{error::VmmError, object::VmFlags, AllocationType, VMM};
 
@@ -34,8 +34,10 @@ pub(crate) fn get() -> Result<*mut u8, ApicError> {
 pub(crate) fn initialize(base: PhysicalAddress) -> Result<(), VmmError> {
     let mut locked = VMM.locked();
     let vmm = locked.get_mut().ok_or(VmmError::VmmUnitialized)?;
-    // lapic registers are at a boundary of 4KB
-    let lapic_registers = vmm.alloc(
+    // lapic registers are at a boundary of 4KB, they are mapped to the end of the VMM segment,
+    // since objects are allocated from the start of a gap
+    let lapic_registers = vmm.alloc_at(
+        LAPIC_VIRTUAL,
         PAGE_SIZE,
         VmFlags::WRITE | VmFlags::MMIO | VmFlags::NO_CACHE,
         AllocationType::Address(base),
@@ -10,6 +10,9 @@ use crate::{
 
 use super::pit;
 
+/// Interval between two timer interrupts in milliseconds.
+pub(crate) const INTERVAL_MILLIS: u64 = 10;
+
 /// Initializes the Local APIC Timer and callibrates it using the `crate::io::timer::pit::PIT`.
 /// Thus, the LAPIC Timer has the same frequency as the one configured for the PIT.
 pub(crate) fn initialize() -> Result<(), ApicError> {
@@ -29,15 +32,15 @@ pub(crate) fn initialize() -> Result<(), ApicError> {
         initial_counter_register.write_volatile(0xFFFFFFFF);
     }
 
-    // sleep for 10ms
-    pit::sleep(10);
+    // sleep for one interval
+    pit::sleep(INTERVAL_MILLIS);
     // stop APIC timer
     unsafe {
         let timer_register = lapic_address.add(LVT_TIMER_OFFSET).cast::<u32>();
         timer_register.write_volatile(TimerLocalVectorTableEntry::INTERRUPT_MASK.bits());
     }
 
-    let ticks_in_10ms = 0xFFFFFFFF
+    let ticks_per_interval = 0xFFFFFFFF
         - unsafe {
             lapic_address
                 .add(CURRENT_COUNT_OFFSET)
@@ -56,7 +59,7 @@ pub(crate) fn initialize() -> Result<(), ApicError> {
     }
     unsafe {
         let initial_counter_register = lapic_address.add(INITIAL_COUNT_OFFSET).cast::<u32>();
-        initial_counter_register.write_volatile(ticks_in_10ms);
+        initial_counter_register.write_volatile(ticks_per_interval);
     }
 
     Ok(())
@@ -1,8 +1,5 @@
 #![allow(dead_code)] // enum variants kept for completness and readability
-use core::{
-    hint::spin_loop,
-    sync::atomic::{AtomicU64, Ordering},
-};
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use crate::io::{io_wait, outb};
 
@@ -45,6 +42,11 @@ pub(crate) unsafe fn initialize() {
 pub(crate) fn tick() {
     TICK_COUNTER.fetch_add(1, Ordering::Relaxed);
 }
+
+/// Halts the CPU until the given time has passed. Hardware interrupts must be enabled.
+///
+/// Note: This is only intended to be used before multitasking is available, tasks should use
+/// [`crate::scheduling::sleep`] instead.
 pub(crate) fn sleep(millis: u64) {
     let frequency = BASE_CLOCK / DIVISOR as u64;
     let ticks_to_sleep = (millis * frequency) / 1000;
@@ -52,7 +54,7 @@ pub(crate) fn sleep(millis: u64) {
     let target_ticks = start_ticks + ticks_to_sleep;
 
     while TICK_COUNTER.load(Ordering::Relaxed) < target_ticks {
-        spin_loop();
+        hal::hlt();
     }
 }
 
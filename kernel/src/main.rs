@@ -4,28 +4,35 @@
 #![feature(fn_align)]
 #![feature(once_cell_get_mut)]
 
+use alloc::rc::Rc;
 use bootinfo::BootInfo;
 use core::panic::PanicInfo;
 use framebuffer::color::{self};
 use graphics::LOGGER;
+use hal::registers::control::Cr0;
 use io::{
     apic::lapic,
     timer::{lapict, pit},
 };
 use mem::{KHEAP_PAGE_COUNT, KHEAP_VIRTUAL};
 use memory::vmm::{self, paging::PTM};
+use scheduler::task::Priority;
 
 extern crate alloc;
 
 mod acpi;
 mod drivers;
+mod elf;
+mod fs;
 mod gdt;
 mod graphics;
 mod idt;
+mod initrd;
 mod io;
 mod memory;
 mod scheduling;
 mod serial;
+mod syscall;
 
 #[no_mangle]
 pub extern "sysv64" fn _start(bootinfo: &mut BootInfo) -> ! {
@@ -81,6 +88,21 @@ pub extern "sysv64" fn _start(bootinfo: &mut BootInfo) -> ! {
         unsafe { vmm::initialize() },
         "Initializing virtual memory manager"
     );
+    // writes of the kernel to copy-on-write user pages must fault as well
+    validate!(
+        unsafe {
+            (Cr0::read() | Cr0::WRITE_PROTECT).write();
+        },
+        "Enabling write protection"
+    );
+
+    let ramdisk = validate!(result initrd::initialize(bootinfo.initrd), "Parsing initial ramdisk");
+    loginfo!("Initial ramdisk files: {}", ramdisk.len());
+    validate!(result fs::initialize(Rc::new(ramdisk)), "Mounting initial ramdisk as root");
+    validate!(result fs::mount("/tmp", Rc::new(fs::tmpfs::TmpFs::new())), "Mounting tmpfs at /tmp");
+    let devfs = validate!(result fs::devfs::initialize(), "Registering devices");
+    validate!(result fs::mount("/dev", Rc::new(devfs)), "Mounting devfs at /dev");
+    validate!(result fs::mount("/proc", Rc::new(fs::procfs::ProcFs)), "Mounting procfs at /proc");
 
     validate!(result
          memory::vmm::paging::remap_framebuffer(),
@@ -119,7 +141,16 @@ pub extern "sysv64" fn _start(bootinfo: &mut BootInfo) -> ! {
     validate!(result lapict::initialize(), "Initializing LAPIC timer");
     loginfo!("LAPIC timer is callibrated to PIT frequency");
 
-    // validate!(result scheduling::initialize(), "Initializing multitasking");
+    validate!(result syscall::initialize(), "Enabling system calls");
+    let init = validate!(result fs::read_if_exists(scheduling::INIT_PATH), "Loading init");
+    validate!(result scheduling::initialize(init.as_deref()), "Initializing multitasking");
+    validate!(result scheduling::spawn(drivers::keyboard::console, Priority::RealTime(0)), "Starting keyboard console");
+    #[cfg(feature = "selftest")]
+    validate!(result fs::spawn("/bin/test", &["/bin/test"], &[], Priority::Normal), "Starting user test program");
+    #[cfg(feature = "selftest")]
+    validate!(result scheduling::spawn(scheduling::selftest::thread_test, Priority::Normal), "Starting kernel thread test");
+    #[cfg(feature = "task-monitor")]
+    validate!(result scheduling::spawn(scheduling::stats::monitor, Priority::Normal), "Starting task monitor");
 
     hal::hlt_loop();
 }
@@ -2,6 +2,7 @@ use core::{
     alloc::{GlobalAlloc, Layout},
     ptr::{self, NonNull},
 };
+use hal::interrupts::without_interrupts;
 use mem::{
     align_up,
     error::HeapError,
@@ -69,16 +70,19 @@ unsafe impl GlobalAlloc for HeapWrapper {
                         }
                     }
                 }
-                // check for VMM instead
+                // check for VMM instead, which must not be held by a preempted task, as the
+                // page fault handler relies on it
                 else {
                     drop(ptm);
-                    let mut vmm = VMM.locked();
-                    if let Some(vmm) = vmm.get_mut() {
-                        if heap.expand(size, vmm.ptm()).is_ok() {
-                            if let Ok(fit_node) = heap.find_fit(size) {
-                                if heap.split_block(fit_node, size).is_ok() {
-                                    return fit_node.as_ptr().add(1) as *mut u8;
-                                }
+                    let expanded = without_interrupts(|| {
+                        let mut vmm = VMM.locked();
+                        vmm.get_mut()
+                            .is_some_and(|vmm| heap.expand(size, vmm.ptm()).is_ok())
+                    });
+                    if expanded {
+                        if let Ok(fit_node) = heap.find_fit(size) {
+                            if heap.split_block(fit_node, size).is_ok() {
+                                return fit_node.as_ptr().add(1) as *mut u8;
                             }
                         }
                     }
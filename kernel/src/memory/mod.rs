@@ -1,2 +1,3 @@
 pub(super) mod kheap;
+pub(crate) mod user;
 pub(crate) mod vmm;
@@ -0,0 +1,17 @@
+use mem::VirtualAddress;
+
+use crate::scheduling::error::SchedulerError;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum UserAccessError {
+    #[error("{0}")]
+    Scheduler(#[from] SchedulerError),
+    #[error("User range starting at {0:#x} exceeds the lower half")]
+    OutOfRange(VirtualAddress),
+    #[error("User address {0:#x} is not mapped")]
+    NotMapped(VirtualAddress),
+    #[error("Address space of the current task is not active")]
+    Inactive,
+    #[error("Page fault while accessing user address {0:#x}")]
+    Fault(VirtualAddress),
+}
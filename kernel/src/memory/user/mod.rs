@@ -0,0 +1,116 @@
+use core::arch::naked_asm;
+
+use error::UserAccessError;
+use mem::{VirtualAddress, USER_VIRTUAL_MAX};
+use scheduler::memory::{AddressSpace, State, UserRegion};
+
+use crate::scheduling;
+
+pub(crate) mod error;
+
+unsafe extern "C" {
+    /// Instruction of [`copy_user`] that accesses user memory.
+    #[link_name = "__user_copy_access"]
+    static USER_COPY_ACCESS: u8;
+    /// Instruction [`copy_user`] continues with after a page fault.
+    #[link_name = "__user_copy_fixup"]
+    static USER_COPY_FIXUP: u8;
+}
+
+/// Checks that the range of `length` bytes starting at `start` lies within the lower half and is
+/// covered by the user regions of the address space. Whether its pages are present and writable
+/// is not checked: lazy pages are backed and copy-on-write pages are copied by the page fault
+/// handler, while any other fault aborts the copy, see [`fixup`].
+pub(crate) fn validate(
+    address_space: &AddressSpace,
+    start: VirtualAddress,
+    length: usize,
+) -> Result<(), UserAccessError> {
+    if length == 0 {
+        return Ok(());
+    }
+
+    let end = start
+        .checked_add(length as u64)
+        .filter(|end| *end <= USER_VIRTUAL_MAX)
+        .ok_or(UserAccessError::OutOfRange(start))?;
+
+    // adjacent regions may cover the range together
+    let mut address = start;
+    while address < end {
+        address = address_space
+            .regions()
+            .iter()
+            .find(|region| region.contains(address))
+            .and_then(UserRegion::end)
+            .ok_or(UserAccessError::NotMapped(address))?;
+    }
+
+    Ok(())
+}
+
+/// Copies `dst.len()` bytes from the user memory of the current task starting at `src`.
+///
+/// Note: The range is validated up front, see [`validate`]. If accessing a page faults, e.g. since
+/// it is read-only, the copy is aborted by the page fault handler and fails with
+/// [`UserAccessError::Fault`].
+pub(crate) fn copy_from_user(dst: &mut [u8], src: VirtualAddress) -> Result<(), UserAccessError> {
+    validate_current(src, dst.len())?;
+
+    let remaining = unsafe { copy_user(dst.as_mut_ptr(), src as *const u8, dst.len()) };
+    match remaining {
+        0 => Ok(()),
+        remaining => Err(UserAccessError::Fault(src + (dst.len() - remaining) as u64)),
+    }
+}
+
+/// Copies `src` to the user memory of the current task starting at `dst`, see
+/// [`copy_from_user`].
+pub(crate) fn copy_to_user(dst: VirtualAddress, src: &[u8]) -> Result<(), UserAccessError> {
+    validate_current(dst, src.len())?;
+
+    let remaining = unsafe { copy_user(dst as *mut u8, src.as_ptr(), src.len()) };
+    match remaining {
+        0 => Ok(()),
+        remaining => Err(UserAccessError::Fault(dst + (src.len() - remaining) as u64)),
+    }
+}
+
+/// Returns the address to continue with, if the page fault at `rip` has been raised while
+/// copying user memory. The copy then returns the number of bytes it has not copied.
+pub(crate) fn fixup(rip: u64) -> Option<u64> {
+    (rip == &raw const USER_COPY_ACCESS as u64).then_some(&raw const USER_COPY_FIXUP as u64)
+}
+
+/// Validates the range against the address space of the current task, which must be active.
+fn validate_current(start: VirtualAddress, length: usize) -> Result<(), UserAccessError> {
+    let task = scheduling::current()?;
+    let task = task.borrow();
+    let process = task.process().borrow();
+    let address_space = process.address_space();
+
+    if address_space.state() != State::Active {
+        return Err(UserAccessError::Inactive);
+    }
+    validate(address_space, start, length)
+}
+
+/// Copies `length` bytes from `src` to `dst` and returns the number of bytes that have not been
+/// copied, which is only non-zero if a page fault occurred.
+///
+/// # Safety
+/// `dst` and `src` must be valid for `length` bytes, except for unmapped user pages.
+#[unsafe(naked)]
+unsafe extern "sysv64" fn copy_user(dst: *mut u8, src: *const u8, length: usize) -> usize {
+    naked_asm!(
+        "mov rcx, rdx",
+        // a page fault at this instruction continues at the fixup, rcx holds the remaining bytes
+        ".global __user_copy_access",
+        "__user_copy_access:",
+        "rep movsb",
+        ".global __user_copy_fixup",
+        "__user_copy_fixup:",
+        "mov rax, rcx",
+        "ret",
+    );
+}
@@ -1,15 +1,26 @@
-use mem::{error::FrameAllocatorError, VirtualAddress};
+use mem::{
+    error::{FrameAllocatorError, RegionError},
+    VirtualAddress,
+};
+
+use super::object::VmFlags;
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum VmmError {
     #[error("Paging error: {0}")]
     Paging(#[from] PagingError),
+    #[error("Region error: {0}")]
+    Region(#[from] RegionError),
     #[error("Requested object has not been allocated")]
     InvalidRequest(VirtualAddress),
+    #[error("Invalid flags {0:?} for the type of allocation")]
+    InvalidFlags(VmFlags),
     #[error("Out of memory")]
     Oom,
     #[error("Virtual Memory Manager has not been intialized")]
     VmmUnitialized,
+    #[error("Virtual Memory Manager is currently in use")]
+    VmmLocked,
 }
 
 #[derive(Debug, thiserror::Error)]
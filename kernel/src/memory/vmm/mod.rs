@@ -1,16 +1,21 @@
-use core::{alloc::Layout, ptr::NonNull};
+use core::{
+    arch::asm,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use alloc::alloc::dealloc;
 use error::{PagingError, VmmError};
 use mem::{
-    align_up,
+    align_down, align_up,
+    error::RegionError,
     paging::{
         ptm::{PageTableManager, PageTableMappings},
-        PageEntryFlags,
+        PageEntryFlags, PageTable,
     },
-    VirtualAddress, PAGE_SIZE, VMM_PAGE_COUNT, VMM_VIRTUAL,
+    region::RegionTree,
+    PhysicalAddress, VirtualAddress, PAGE_SIZE, PAS_VIRTUAL, VMM_PAGE_COUNT, VMM_VIRTUAL,
 };
-use object::{VmFlags, VmObject};
+use object::{ObjectInfo, VmFlags, VmObject};
 use paging::PTM;
 use sync::locked::Locked;
 
@@ -20,6 +25,16 @@ pub(crate) mod paging;
 
 pub(crate) static VMM: Locked<VirtualMemoryManager> = Locked::new();
 
+/// Number of frames kept in reserve to back pages of lazy objects.
+const RESERVE_FRAMES: usize = 8;
+/// Marks an empty slot of the reserve.
+const RESERVE_EMPTY: PhysicalAddress = PhysicalAddress::MAX;
+
+/// Frames used to back pages of lazy objects, if the page fault is raised while the VMM is in use
+/// by the interrupted task, e.g. if it grows its stack while allocating memory.
+static RESERVE: [AtomicU64; RESERVE_FRAMES] =
+    [const { AtomicU64::new(RESERVE_EMPTY) }; RESERVE_FRAMES];
+
 /// Initializes the global virtual memory manager.
 ///
 /// # Safety
@@ -38,22 +53,105 @@ pub(crate) unsafe fn initialize() -> Result<(), VmmError> {
 
 /// Updates the page table mappings of the global virtual memory manager.
 ///
+/// Note: This is called by the scheduler from within the timer interrupt. Thus, it does not spin
+/// if the VMM is in use by the interrupted task, but fails with [`VmmError::VmmLocked`] instead.
+///
 /// # Safety
 /// The caller must guarantee that the new mappings are valid.
 pub(crate) unsafe fn update(mappings: PageTableMappings) -> Result<(), VmmError> {
-    let mut locked = VMM.locked();
+    let mut locked = VMM.try_locked().ok_or(VmmError::VmmLocked)?;
     let vmm = locked.get_mut().ok_or(VmmError::VmmUnitialized)?;
     unsafe { vmm.ptm.update_mappings(mappings) };
     Ok(())
 }
 
+/// Checks whether the address lies within the guard region of an allocated object.
+///
+/// Note: This is called from within the page fault handler. Thus, it does not spin if the VMM is
+/// in use by the interrupted task, but returns `false` instead.
+pub(crate) fn is_guard_page(address: VirtualAddress) -> bool {
+    VMM.try_locked()
+        .and_then(|locked| locked.get().map(|vmm| vmm.is_guard(address)))
+        .unwrap_or(false)
+}
+
+/// Backs the page containing the address with a zeroed frame, if it belongs to a lazy object and
+/// has not been backed yet. Returns whether the page has been backed. Pages which are not user
+/// accessible are not backed on behalf of user code.
+///
+/// Note: This is called from within the page fault handler. If the VMM is in use by the
+/// interrupted task, the frame is taken from a reserve and mapped into the active page tables
+/// directly. This does not allocate, as the page tables of lazy objects are created up front.
+pub(crate) fn handle_page_fault(address: VirtualAddress, user: bool) -> bool {
+    let page = align_down(address, PAGE_SIZE);
+
+    if let Some(mut locked) = VMM.try_locked() {
+        return locked.get_mut().is_some_and(|vmm| vmm.back(page, user));
+    }
+
+    // Safety: The VMM only modifies entries of other pages, while the page tables of this page
+    // already exist.
+    let mut mappings = unsafe { active_mappings() };
+    match mappings.reserved(page) {
+        Some(flags) if !user || flags.contains(PageEntryFlags::USER_SUPER) => take_reserved_frame()
+            .is_some_and(|frame| {
+                unsafe { ((frame + PAS_VIRTUAL) as *mut u8).write_bytes(0, PAGE_SIZE) };
+                mappings.commit_memory(page, frame)
+            }),
+        _ => false,
+    }
+}
+
+/// Resolves a write to the copy-on-write page containing the address, which shares its frame with
+/// another address space. The page is mapped writable to a copy of the frame, or to the frame
+/// itself if no other address space uses it anymore. Returns whether the write can be retried.
+///
+/// Note: This is called from within the page fault handler. Thus, it does not spin if the VMM is
+/// in use by the interrupted task, but returns `false` instead. The VMM is only ever locked with
+/// interrupts disabled, so this only happens if the kernel itself writes to a copy-on-write page
+/// while using the VMM, which it never does.
+pub(crate) fn copy_on_write(address: VirtualAddress) -> bool {
+    VMM.try_locked()
+        .and_then(|mut locked| locked.get_mut().map(|vmm| vmm.copy_page(address)))
+        .unwrap_or(false)
+}
+
+/// Returns the page table mappings of the active address space, which are accessed via the
+/// direct mapping.
+///
+/// # Safety
+/// The caller must ensure that the page tables are not modified concurrently.
+unsafe fn active_mappings() -> PageTableMappings {
+    let cr3: u64;
+    unsafe {
+        asm!("mov {}, cr3", out(reg) cr3, options(nostack, nomem, preserves_flags));
+    }
+
+    let pml4 = align_down(cr3, PAGE_SIZE);
+    unsafe {
+        let mut mappings =
+            PageTableMappings::new(NonNull::new_unchecked(pml4 as *mut PageTable), false);
+        mappings.update_offset(PAS_VIRTUAL);
+        mappings.update_pml4_virtual(NonNull::new_unchecked(
+            (pml4 + PAS_VIRTUAL) as *mut PageTable,
+        ));
+        mappings
+    }
+}
+
+/// Takes a frame out of the reserve.
+fn take_reserved_frame() -> Option<PhysicalAddress> {
+    RESERVE
+        .iter()
+        .map(|slot| slot.swap(RESERVE_EMPTY, Ordering::Relaxed))
+        .find(|frame| *frame != RESERVE_EMPTY)
+}
+
 /// Uses page table manager and kernel heap to keep track of allocated virtual memory objects with specific permissions.
 #[derive(Debug)]
 pub(crate) struct VirtualMemoryManager {
-    head: Option<NonNull<VmObject>>,
-    vmm_start: VirtualAddress,
-    vmm_page_count: usize,
-    pages_allocated: usize,
+    /// Objects allocated in the virtual memory segment of the VMM.
+    objects: RegionTree<VmObject>,
     ptm: PageTableManager,
 }
 
@@ -69,10 +167,7 @@ impl VirtualMemoryManager {
         ptm: PageTableManager,
     ) -> Self {
         Self {
-            vmm_start,
-            vmm_page_count,
-            head: None,
-            pages_allocated: 0,
+            objects: RegionTree::new(vmm_start, (vmm_page_count * PAGE_SIZE) as u64),
             ptm,
         }
     }
@@ -92,169 +187,322 @@ impl VirtualMemoryManager {
         flags: VmFlags,
         allocation_type: AllocationType,
     ) -> Result<NonNull<u8>, VmmError> {
-        // align length to next valid page size
-        let length = align_up(length as u64, PAGE_SIZE) as usize;
-        let mut base = 0;
-        let mut current = self.head;
-
-        // check if there is enough space for vmm object
-        _ = self
-            .pages_allocated
-            .checked_add(length / PAGE_SIZE)
-            .map(|res| res > self.vmm_page_count)
-            .ok_or(VmmError::Oom)?;
-
-        // allocate first object
-        if current.is_some() {
-            // allocate new vm object struct on heap
-            while let Some(mut object) = current {
-                let current_ref = unsafe { object.as_mut() };
-
-                if let Some(mut prev) = current_ref.prev {
-                    let prev_ref = unsafe { prev.as_mut() };
-                    let new_base = prev_ref.base + prev_ref.length as u64;
-
-                    // allocate between previous object and current one
-                    if new_base + (length as u64) < current_ref.base {
-                        base = new_base;
-                        let new_object = unsafe {
-                            VmObject::alloc_new(base, length, flags, current, current_ref.prev)
-                        };
-
-                        prev_ref.next = Some(new_object);
-                        current_ref.prev = Some(new_object);
-                        break;
-                    }
-                } else {
-                    // allocate new object before the first one, if possible
-                    if (length as u64) < current_ref.base {
-                        base = 0;
-                        let new_object =
-                            unsafe { VmObject::alloc_new(base, length, flags, current, None) };
-                        current_ref.prev = Some(new_object);
-                        break;
-                    }
-                }
+        self.alloc_guarded(length, 0, flags, allocation_type)
+    }
 
-                // allocate after last object
-                if current_ref.next.is_none() {
-                    base = current_ref.base + current_ref.length as u64;
-                    let new_object =
-                        unsafe { VmObject::alloc_new(base, length, flags, None, current) };
-                    current_ref.next = Some(new_object);
-                    break;
+    /// Allocates a new virtual memory object, which is preceded by an unmapped guard region of
+    /// `guard` bytes. Accessing the guard region causes a page fault, which can be identified
+    /// using [`VirtualMemoryManager::is_guard`]. Returns the address following the guard region.
+    pub(crate) fn alloc_guarded(
+        &mut self,
+        length: usize,
+        guard: usize,
+        flags: VmFlags,
+        allocation_type: AllocationType,
+    ) -> Result<NonNull<u8>, VmmError> {
+        self.alloc_object(None, length, guard, flags, allocation_type)
+    }
+
+    /// Allocates a new virtual memory object starting at the page-aligned `address`, which must
+    /// not overlap with any other object. See [`VirtualMemoryManager::alloc`].
+    pub(crate) fn alloc_at(
+        &mut self,
+        address: VirtualAddress,
+        length: usize,
+        flags: VmFlags,
+        allocation_type: AllocationType,
+    ) -> Result<NonNull<u8>, VmmError> {
+        if !address.is_multiple_of(PAGE_SIZE as u64) {
+            return Err(VmmError::InvalidRequest(address));
+        }
+
+        self.alloc_object(Some(address), length, 0, flags, allocation_type)
+    }
+
+    /// Reserves the virtual memory of an object, either at the fixed address or in the smallest
+    /// gap it fits into, and backs it. Returns the address following the guard region. If the
+    /// object cannot be backed completely, its region and the pages backed so far are released.
+    fn alloc_object(
+        &mut self,
+        address: Option<VirtualAddress>,
+        length: usize,
+        guard: usize,
+        flags: VmFlags,
+        allocation_type: AllocationType,
+    ) -> Result<NonNull<u8>, VmmError> {
+        if flags.contains(VmFlags::LAZY)
+            && (flags.contains(VmFlags::MMIO)
+                || !matches!(allocation_type, AllocationType::AnyPages))
+        {
+            return Err(VmmError::InvalidFlags(flags));
+        }
+
+        // align lengths to next valid page size
+        let guard = align_up(guard as u64, PAGE_SIZE);
+        let mapped_length = align_up(length as u64, PAGE_SIZE);
+        let length = guard + mapped_length;
+
+        let object = |start: VirtualAddress| VmObject {
+            mapped: start + guard,
+            flags,
+        };
+        let start = match address {
+            Some(address) => {
+                self.objects.allocate_at(address, length, object(address))?;
+                address
+            }
+            None => {
+                // the start address is only known once the gap has been found
+                let start = self
+                    .objects
+                    .allocate(length, object(0))
+                    .map_err(|err| match err {
+                        RegionError::NoSpace(_) => VmmError::Oom,
+                        err => err.into(),
+                    })?;
+                if let Some(region) = self.objects.get_mut(start) {
+                    region.data = object(start);
                 }
-                // continue with new object
-                current = current_ref.next;
+                start
             }
-        } else {
-            let new_object = unsafe { VmObject::alloc_new(base, length, flags, None, None) };
-            self.head = Some(new_object);
-        }
+        };
 
-        // map pages for newly allocated vm object
-        let page_count = length / PAGE_SIZE;
-        self.pages_allocated += page_count;
+        // map pages for newly allocated vm object, leaving the guard region unmapped
+        let base = start + guard;
+        let page_count = mapped_length as usize / PAGE_SIZE;
 
-        let vmm_start = self.vmm_start;
         let ptm = self.ptm();
-        // immediate backing
-        for page in 0..page_count {
-            let physical_address = match allocation_type {
-                AllocationType::AnyPages => ptm
-                    .pmm()
-                    .request_page()
-                    .map_err(|err| VmmError::Paging(err.into()))?,
-                AllocationType::Address(address) => {
-                    if flags.contains(VmFlags::MMIO) {
-                        address + (page * PAGE_SIZE) as u64
-                    } else {
-                        unimplemented!("allocating a specific address frame is not yet supported.")
+
+        // number of pages backed or reserved, which are released again if the object cannot be
+        // backed completely
+        let mut committed = 0;
+        let backed = (|| -> Result<(), VmmError> {
+            for page in 0..page_count {
+                let virtual_address = base + (page * PAGE_SIZE) as u64;
+
+                // lazy backing, the page tables are created up front
+                if flags.contains(VmFlags::LAZY) {
+                    let (mappings, pmm) = ptm.inner();
+                    mappings
+                        .reserve_memory(virtual_address, PageEntryFlags::from(flags), pmm)
+                        .map_err(|err| VmmError::Paging(err.into()))?;
+                    committed += 1;
+                    continue;
+                }
+
+                // immediate backing
+                let physical_address = match allocation_type {
+                    AllocationType::AnyPages => ptm
+                        .pmm()
+                        .request_page()
+                        .map_err(|err| VmmError::Paging(err.into()))?,
+                    AllocationType::Address(address) => {
+                        let physical_address = address + (page * PAGE_SIZE) as u64;
+                        if !flags.contains(VmFlags::MMIO) {
+                            ptm.pmm()
+                                .allocate_frame(physical_address)
+                                .map_err(|err| VmmError::Paging(err.into()))?;
+                        }
+                        physical_address
+                    }
+                };
+
+                if let Err(err) = ptm.map_memory(
+                    virtual_address,
+                    physical_address,
+                    PageEntryFlags::from(flags),
+                ) {
+                    if !flags.contains(VmFlags::MMIO) {
+                        _ = ptm.pmm().free_frame(physical_address);
                     }
+                    return Err(VmmError::Paging(err.into()));
                 }
-            };
+                committed += 1;
 
-            let virtual_address = vmm_start + base + (page * PAGE_SIZE) as u64;
-            ptm.map_memory(
-                virtual_address,
-                physical_address,
-                PageEntryFlags::from(flags),
-            )
-            .map_err(|err| VmmError::Paging(err.into()))?;
-            // clear newly allocated region
-            if !flags.contains(VmFlags::MMIO) && flags.contains(VmFlags::WRITE) {
-                unsafe {
-                    (virtual_address as *mut u8).write_bytes(0, PAGE_SIZE);
+                // clear newly allocated region
+                if !flags.contains(VmFlags::MMIO) && flags.contains(VmFlags::WRITE) {
+                    unsafe {
+                        (virtual_address as *mut u8).write_bytes(0, PAGE_SIZE);
+                    }
                 }
             }
+            Ok(())
+        })();
+
+        if let Err(err) = backed {
+            let object = self.objects.free(start)?.data;
+            self.release(start, guard + (committed * PAGE_SIZE) as u64, object)?;
+            return Err(err);
+        }
+
+        if flags.contains(VmFlags::LAZY) {
+            self.refill_reserve();
         }
 
-        Ok(unsafe { NonNull::new_unchecked((self.vmm_start + base) as *mut u8) })
+        Ok(unsafe { NonNull::new_unchecked(base as *mut u8) })
     }
 
-    /// Frees an allocated VMM-object.
+    /// Frees an allocated VMM-object, given the address following its guard region. Parts split
+    /// off an object by [`VirtualMemoryManager::unmap`] are freed by their start address.
     pub(crate) fn free(&mut self, address: VirtualAddress) -> Result<(), VmmError> {
-        if address >= self.vmm_start {
+        let region = self
+            .objects
+            .get(address)
+            .filter(|region| region.start == address || region.data.mapped == address)
+            .ok_or(VmmError::InvalidRequest(address))?;
+
+        let (start, length) = (region.start, region.length);
+        self.unmap(start, length as usize)
+    }
+
+    /// Unmaps `length` bytes starting at the page-aligned `address` from all objects they
+    /// overlap, which are split if only a part of them is unmapped. Their frames are freed,
+    /// unless they are MMIO.
+    pub(crate) fn unmap(&mut self, address: VirtualAddress, length: usize) -> Result<(), VmmError> {
+        if !address.is_multiple_of(PAGE_SIZE as u64) {
             return Err(VmmError::InvalidRequest(address));
         }
 
-        let mut current = self.head;
-        while let Some(current_ref) = current {
-            let current_ref = unsafe { current_ref.as_ref() };
-
-            // check for requested object
-            if current_ref.base == address - self.vmm_start {
-                let ptm = self.ptm();
-
-                let page_count = current_ref.length / PAGE_SIZE;
-                // free regions in vmm memory segment
-                for page in 0..page_count {
-                    // unmap virtual address
-                    let physical_address = ptm
-                        .mappings()
-                        .unmap_memory(address + (page * PAGE_SIZE) as u64)
-                        .ok_or(VmmError::InvalidRequest(address))?;
-
-                    // free physical page frames
-                    if !current_ref.flags.contains(VmFlags::MMIO) {
-                        ptm.pmm()
-                            .free_frame(physical_address)
-                            .map_err(|err| VmmError::Paging(err.into()))?;
-                    }
-                }
+        let length = align_up(length as u64, PAGE_SIZE);
+        for part in self.objects.unmap(address, length)? {
+            self.release(part.start, part.length, part.data)?;
+        }
 
-                self.pages_allocated -= page_count;
+        Ok(())
+    }
 
-                // remove object from linked list
-                let heap_ptr = if let Some(mut prev) = current_ref.prev {
-                    let prev_ref = unsafe { prev.as_mut() };
-                    let heap_ptr = prev_ref.next.unwrap().as_ptr();
-                    prev_ref.next = current_ref.next;
-                    heap_ptr
-                } else {
-                    let heap_ptr = self.head.unwrap().as_ptr();
-                    self.head = current_ref.next;
+    /// Unmaps the pages of the object within the range, freeing their frames unless it is MMIO.
+    /// The guard region is not mapped and pages of lazy objects may not have been backed.
+    fn release(
+        &mut self,
+        start: VirtualAddress,
+        length: u64,
+        object: VmObject,
+    ) -> Result<(), VmmError> {
+        let ptm = self.ptm();
 
-                    heap_ptr
-                };
+        for address in (start.max(object.mapped)..start + length).step_by(PAGE_SIZE) {
+            let physical_address = match ptm.mappings().unmap_memory(address) {
+                Some(physical_address) => physical_address,
+                None if object.flags.contains(VmFlags::LAZY) => continue,
+                None => return Err(VmmError::InvalidRequest(address)),
+            };
 
-                if let Some(mut next) = current_ref.next {
-                    let next_ref = unsafe { next.as_mut() };
-                    next_ref.prev = current_ref.prev;
-                }
+            // free physical page frames
+            if !object.flags.contains(VmFlags::MMIO) {
+                ptm.pmm()
+                    .free_frame(physical_address)
+                    .map_err(|err| VmmError::Paging(err.into()))?;
+            }
+        }
 
-                // deallocate vmm struct from heap
-                unsafe {
-                    dealloc(heap_ptr as *mut u8, Layout::new::<VmObject>());
-                }
+        Ok(())
+    }
+
+    /// Returns all allocated objects ordered by their address.
+    pub(crate) fn objects(&self) -> impl Iterator<Item = ObjectInfo> + '_ {
+        self.objects.regions().map(|region| ObjectInfo {
+            address: region.start,
+            length: region.length as usize,
+            guard: region.data.mapped.clamp(region.start, region.end()) as usize
+                - region.start as usize,
+            flags: region.data.flags,
+        })
+    }
+
+    /// Returns the number of pages allocated, including guard regions, and the total number of
+    /// pages managed.
+    pub(crate) fn page_counts(&self) -> (usize, usize) {
+        (
+            self.objects.allocated() as usize / PAGE_SIZE,
+            self.objects.size() as usize / PAGE_SIZE,
+        )
+    }
+
+    /// Backs the reserved page of a lazy object with a zeroed frame, see [`handle_page_fault`].
+    fn back(&mut self, page: VirtualAddress, user: bool) -> bool {
+        match self.ptm.mappings_ref().reserved(page) {
+            Some(flags) if !user || flags.contains(PageEntryFlags::USER_SUPER) => {}
+            _ => return false,
+        }
 
-                return Ok(());
+        let Some(frame) = self
+            .ptm
+            .pmm()
+            .request_page()
+            .ok()
+            .or_else(take_reserved_frame)
+        else {
+            return false;
+        };
+        unsafe {
+            ((frame + self.ptm.mappings_ref().offset()) as *mut u8).write_bytes(0, PAGE_SIZE)
+        };
+
+        let backed = self.ptm.mappings().commit_memory(page, frame);
+        if !backed {
+            _ = self.ptm.pmm().free_frame(frame);
+        }
+
+        self.refill_reserve();
+        backed
+    }
+
+    /// Maps the copy-on-write page containing the address writable to its own frame, see
+    /// [`copy_on_write`].
+    fn copy_page(&mut self, address: VirtualAddress) -> bool {
+        let page = align_down(address, PAGE_SIZE);
+        let Some(frame) = self.ptm.mappings_ref().copy_on_write(page) else {
+            return false;
+        };
+
+        // the last owner keeps the frame
+        if self
+            .ptm
+            .pmm()
+            .references(frame)
+            .is_ok_and(|owners| owners == 1)
+        {
+            return self.ptm.mappings().copy_written(page, frame);
+        }
+
+        let Ok(copy) = self.ptm.pmm().request_page() else {
+            return false;
+        };
+        let offset = self.ptm.mappings_ref().offset();
+        unsafe {
+            ptr::copy_nonoverlapping(
+                (frame + offset) as *const u8,
+                (copy + offset) as *mut u8,
+                PAGE_SIZE,
+            );
+        }
+
+        self.ptm.mappings().copy_written(page, copy);
+        // drops the reference of this address space
+        _ = self.ptm.pmm().free_frame(frame);
+        true
+    }
+
+    /// Fills the empty slots of the reserve with free frames.
+    fn refill_reserve(&mut self) {
+        for slot in &RESERVE {
+            if slot.load(Ordering::Relaxed) != RESERVE_EMPTY {
+                continue;
             }
 
-            current = current_ref.next;
+            match self.ptm.pmm().request_page() {
+                Ok(frame) => slot.store(frame, Ordering::Relaxed),
+                Err(_) => return,
+            }
         }
+    }
 
-        Err(VmmError::InvalidRequest(address))
+    /// Checks whether the address lies within the guard region of an allocated object.
+    pub(crate) fn is_guard(&self, address: VirtualAddress) -> bool {
+        self.objects
+            .get(address)
+            .is_some_and(|region| address < region.data.mapped)
     }
 }
 
@@ -1,43 +1,30 @@
-use core::ptr::NonNull;
-
-use alloc::boxed::Box;
 use bitflags::bitflags;
 use mem::{paging::PageEntryFlags, VirtualAddress};
 
-#[derive(Debug)]
+/// Attributes of an object allocated by the VMM, which spans a region of its
+/// [`mem::region::RegionTree`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(super) struct VmObject {
-    pub(super) base: VirtualAddress,
-    pub(super) length: usize,
+    /// Address following the unmapped guard region at the start of the object. Parts split off
+    /// the object keep it, such that only parts below it lie within the guard region.
+    pub(super) mapped: VirtualAddress,
     pub(super) flags: VmFlags,
-    pub(super) next: Option<NonNull<VmObject>>,
-    pub(super) prev: Option<NonNull<VmObject>>,
 }
 
-impl VmObject {
-    /// Allocates new `VmObject` struct on the heap. Returns a non-null pointer to the object.
-    ///
-    /// # Safety
-    /// The caller must ensure that the new allocated vm object is valid.
-    pub(super) unsafe fn alloc_new(
-        base: VirtualAddress,
-        length: usize,
-        flags: VmFlags,
-        next: Option<NonNull<VmObject>>,
-        prev: Option<NonNull<VmObject>>,
-    ) -> NonNull<VmObject> {
-        let new_object = Box::into_raw(Box::new(VmObject {
-            base,
-            length,
-            flags,
-            next,
-            prev,
-        }));
-        NonNull::new_unchecked(new_object)
-    }
+/// Allocated object as listed by [`super::VirtualMemoryManager::objects`].
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ObjectInfo {
+    /// Address of the start of the object including the guard region.
+    pub(crate) address: VirtualAddress,
+    /// Length including the guard region.
+    pub(crate) length: usize,
+    /// Length of the unmapped guard region at the start of the object.
+    pub(crate) guard: usize,
+    pub(crate) flags: VmFlags,
 }
 
 bitflags! {
-    #[derive(Copy, Clone, Debug)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
     pub(crate) struct VmFlags: u8 {
         /// If set, the object can be written to
         const WRITE = 1 << 0;
@@ -49,6 +36,8 @@ bitflags! {
         const MMIO = 1 << 3;
         /// If set, the CPU does not cache any data in that memory region.
         const NO_CACHE = 1 << 4;
+        /// If set, only virtual memory is reserved for the object. Its pages are backed by zeroed frames on first access.
+        const LAZY = 1 << 5;
     }
 }
 
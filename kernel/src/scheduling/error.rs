@@ -1,6 +1,6 @@
 use scheduler::memory::AddressSpaceError;
 
-use crate::vmm::error::VmmError;
+use crate::{elf::error::ElfError, vmm::error::VmmError};
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum SchedulerError {
@@ -8,10 +8,37 @@ pub(crate) enum SchedulerError {
     Vmm(#[from] VmmError),
     #[error("{0}")]
     AddressSpace(#[from] AddressSpaceError),
+    #[error("{0}")]
+    Elf(#[from] ElfError),
     #[error("Process not found: PID{0}")]
     ProcessNotFound(u64),
-    #[error("Process with the same PID{0} is already in the queue.")]
+    #[error("Thread not found: TID{0}")]
+    ThreadNotFound(u64),
+    #[error("Thread with the same TID{0} is already in the queue.")]
+    DuplicateTid(u64),
+    #[error("Process with the same PID{0} is already in the process table.")]
     DuplicatePid(u64),
+    #[error("Process PID{0} has not exited yet.")]
+    NotZombie(u64),
+    #[error("Process PID{0} has no children to wait for.")]
+    NoChildren(u64),
+    #[error("Process PID{0} is not a child of the current process.")]
+    ChildNotFound(u64),
     #[error("Must not remove active task.")]
     RemoveNoDone,
+    #[error("Must not kill the process PID{0} of the active task.")]
+    KillActive(u64),
+    #[error("Must not kill the idle or init process PID{0}.")]
+    KillProtected(u64),
+    #[error("Kernel task TID{0} cannot be forked.")]
+    ForkKernel(u64),
+    #[error("The CPUID feature is unavailable to the CPU")]
+    CpuidUnavailable,
+    #[error("The CPU supports neither FXSAVE nor SSE")]
+    FpuUnavailable,
+    #[error("Scheduler has not been initialized")]
+    SchedulerUninitialized,
+    #[error("Thread TID{0} has finished without a result.")]
+    #[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+    ResultMissing(u64),
 }
@@ -0,0 +1,53 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use scheduler::task::{Task, TaskState};
+
+use super::{error::SchedulerError, reap, wait_for};
+
+/// Owned permission to wait for a spawned task to finish and to retrieve its result.
+///
+/// Note: Dropping the handle detaches the task. Its resources are released once its process is
+/// reaped, see [`super::wait`].
+#[derive(Debug)]
+pub(crate) struct JoinHandle<T> {
+    task: Rc<RefCell<Task>>,
+    result: Rc<RefCell<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(super) fn new(task: Rc<RefCell<Task>>, result: Rc<RefCell<Option<T>>>) -> JoinHandle<T> {
+        JoinHandle { task, result }
+    }
+
+    pub(crate) fn tid(&self) -> u64 {
+        self.task.borrow().tid()
+    }
+
+    pub(crate) fn pid(&self) -> u64 {
+        self.task.borrow().pid()
+    }
+
+    /// Blocks the current task until the task has finished and returns its result. The stack of
+    /// the task is released afterwards, as is the address space of its process if it was the
+    /// last thread, unless its process has been reaped already.
+    pub(crate) fn join(self) -> Result<T, SchedulerError> {
+        wait_for(|current| {
+            let mut task = self.task.borrow_mut();
+            if task.state() == TaskState::Done {
+                return Some(());
+            }
+
+            task.park_joiner(current.clone())
+                .expect("the current task cannot have finished");
+            None
+        })?;
+
+        let tid = self.tid();
+        reap(tid)?;
+
+        self.result
+            .borrow_mut()
+            .take()
+            .ok_or(SchedulerError::ResultMissing(tid))
+    }
+}
@@ -1,32 +1,554 @@
-use alloc::{collections::linked_list::LinkedList, rc::Rc};
-use core::{cell::RefCell, ptr::NonNull};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, rc::Rc};
+use core::{
+    arch::asm,
+    cell::RefCell,
+    ptr::NonNull,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
 use error::SchedulerError;
-use hal::{cpu_state::CpuState, hlt_loop};
-use mem::{paging::PageTable, VirtualAddress, PAGE_SIZE};
-use scheduler::{memory::AddressSpace, task::Task, Scheduler};
+use hal::{
+    cpu_state::CpuState, fpu::SaveMechanism, hlt_loop, instructions::cpuid::Cpuid,
+    interrupts::without_interrupts, registers::control::Cr0,
+};
+use mem::{paging::PageTable, FrameAllocator, VirtualAddress, PAGE_SIZE};
+use scheduler::{
+    fpu::FpuState,
+    memory::AddressSpace,
+    policy::Policy,
+    process::Process,
+    task::{Priority, Task, TaskState},
+    timer::TimerQueue,
+    Entry, Scheduler,
+};
 use sync::locked::Locked;
 
 use crate::{
-    gdt::{KERNEL_CS, KERNEL_DS},
+    elf::Executable,
+    gdt::{self, KERNEL_CS, KERNEL_DS, USER_CS, USER_DS},
+    io::timer::lapict::INTERVAL_MILLIS,
     serial_println,
-    vmm::{error::VmmError, object::VmFlags, AllocationType, VMM},
+    vmm::{self, error::VmmError, object::VmFlags, AllocationType, VMM},
 };
 
-mod error;
+pub(crate) mod error;
+#[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+mod join;
+#[cfg(feature = "selftest")]
+pub(crate) mod selftest;
+pub(crate) mod stats;
+
+pub(crate) use join::JoinHandle;
+
+#[cfg(not(any(feature = "round-robin", feature = "mlfq", feature = "fixed-priority")))]
+compile_error!("a scheduling policy must be selected");
+
+#[cfg(any(
+    all(feature = "mlfq", feature = "fixed-priority"),
+    all(feature = "mlfq", feature = "round-robin"),
+    all(feature = "fixed-priority", feature = "round-robin")
+))]
+compile_error!(
+    "only one scheduling policy can be selected, build with `--no-default-features` to replace the default one"
+);
+
+/// Scheduling policy selected at build time. Exactly one alias is defined even if several
+/// policies are enabled, so that only the error above is reported.
+#[cfg(feature = "mlfq")]
+type SchedulingPolicy = scheduler::policy::mlfq::Mlfq;
+#[cfg(all(feature = "fixed-priority", not(feature = "mlfq")))]
+type SchedulingPolicy = scheduler::policy::fixed_priority::FixedPriority;
+#[cfg(all(
+    feature = "round-robin",
+    not(any(feature = "mlfq", feature = "fixed-priority"))
+))]
+type SchedulingPolicy = scheduler::policy::round_robin::RoundRobin;
+
+/// PID and TID of the idle task, which is neither part of the queue of tasks nor of the process
+/// table.
+const IDLE_ID: u64 = 0;
+
+/// PID of the init process, which is the first process spawned.
+const INIT_PID: u64 = IDLE_ID + 1;
+
+/// Path of the executable of the init process in the root filesystem.
+pub(crate) const INIT_PATH: &str = "/sbin/init";
+
+/// Exit code of tasks that have been terminated by the kernel.
+pub(crate) const EXIT_KILLED: i32 = -1;
+
+/// Size of the unmapped guard region below each task stack.
+const STACK_GUARD_SIZE: usize = PAGE_SIZE;
+
+/// Software interrupt used by tasks to give up the remainder of their time slice.
+pub(crate) const YIELD_VECTOR: u8 = 0x30;
+
+/// Number of LAPIC timer interrupts that have occurred.
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Enables the FPU and initializes the scheduler of the current core. The init process is
+/// created from the ELF64 `executable`, or runs [`init`] in the kernel if there is none.
+pub(crate) fn initialize(executable: Option<&[u8]>) -> Result<(), SchedulerError> {
+    let cpuid = Cpuid::new().ok_or(SchedulerError::CpuidUnavailable)?;
+    let fpu = SaveMechanism::detect(cpuid).ok_or(SchedulerError::FpuUnavailable)?;
+    unsafe {
+        fpu.enable();
+    }
+
+    without_interrupts(|| {
+        SCHEDULER.initialize(PerCoreScheduler::try_new(idle, fpu)?);
+        Ok::<_, SchedulerError>(())
+    })?;
+
+    // init must be the first process, so it receives its well-known PID
+    match executable {
+        Some(data) => {
+            spawn_elf_process(data, &[INIT_PATH], &[], Priority::Normal, Some(IDLE_ID)).map(|_| ())
+        }
+        None => spawn_process(init, Priority::Normal, Some(IDLE_ID)).map(|_| ()),
+    }
+}
+
+macro_rules! scheduler {
+    ($locked:expr) => {{
+        $locked
+            .get_mut()
+            .ok_or(SchedulerError::SchedulerUninitialized)?
+    }};
+}
+
+/// Creates a new kernel process with a single thread executing `entry` with the specified
+/// priority and adds it to the scheduling policy. The process is a child of the process of the
+/// current task, or of init if it is spawned during boot. Returns a handle to wait for the result
+/// of the thread.
+///
+/// Note: Joining the thread does not reap the process, see [`wait`].
+pub(crate) fn spawn<F, T>(entry: F, priority: Priority) -> Result<JoinHandle<T>, SchedulerError>
+where
+    F: FnOnce() -> T + 'static,
+    T: 'static,
+{
+    spawn_process(entry, priority, None)
+}
+
+/// Creates a new child process of `parent`, defaulting to the process of the current task. See
+/// [`spawn`].
+fn spawn_process<F, T>(
+    entry: F,
+    priority: Priority,
+    parent: Option<u64>,
+) -> Result<JoinHandle<T>, SchedulerError>
+where
+    F: FnOnce() -> T + 'static,
+    T: 'static,
+{
+    let (entry, result) = with_result(entry);
+
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        // processes spawned by the idle task, i.e. during boot, are adopted by init
+        let parent = parent.unwrap_or_else(|| match scheduler.active.borrow().pid() {
+            IDLE_ID => INIT_PID,
+            pid => pid,
+        });
+
+        let pid = scheduler.next_pid();
+        let process = PerCoreScheduler::create_process(pid, parent)?;
+        let process = scheduler.add_process(process)?;
+
+        let tid = scheduler.next_tid();
+        let fpu = FpuState::new(scheduler.fpu);
+        let thread = PerCoreScheduler::create_thread(&process, tid, entry, priority, fpu)?;
+        let thread = scheduler.add_thread(thread)?;
+
+        Ok(JoinHandle::new(thread, result))
+    })
+}
+
+/// Creates a new thread executing `entry` with the specified priority in the process of the
+/// current task, sharing its address space. Returns a handle to wait for the result of the
+/// thread.
+#[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+pub(crate) fn spawn_thread<F, T>(
+    entry: F,
+    priority: Priority,
+) -> Result<JoinHandle<T>, SchedulerError>
+where
+    F: FnOnce() -> T + 'static,
+    T: 'static,
+{
+    let (entry, result) = with_result(entry);
+
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        let process = scheduler.active.borrow().process().clone();
+
+        let tid = scheduler.next_tid();
+        let fpu = FpuState::new(scheduler.fpu);
+        let thread = PerCoreScheduler::create_thread(&process, tid, entry, priority, fpu)?;
+        let thread = scheduler.add_thread(thread)?;
+
+        Ok(JoinHandle::new(thread, result))
+    })
+}
+
+/// Creates a new user process from the ELF64 executable `data` and adds its thread to the
+/// scheduling policy. The process is a child of the process of the current task, see [`spawn`].
+/// Execution starts at the entry point of the executable with `argv` and `envp` passed on the user
+/// stack. Returns the PID of the new process.
+#[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+pub(crate) fn spawn_elf(
+    data: &[u8],
+    argv: &[&str],
+    envp: &[&str],
+    priority: Priority,
+) -> Result<u64, SchedulerError> {
+    spawn_elf_process(data, argv, envp, priority, None)
+}
+
+/// Creates a new user process from the ELF64 executable `data`, which is a child of `parent`,
+/// defaulting to the process of the current task. See [`spawn_elf`].
+fn spawn_elf_process(
+    data: &[u8],
+    argv: &[&str],
+    envp: &[&str],
+    priority: Priority,
+    parent: Option<u64>,
+) -> Result<u64, SchedulerError> {
+    let executable = Executable::parse(data)?;
+
+    spawn_image(priority, parent, |address_space, pmm| {
+        let stack_pointer = executable.load(address_space, pmm, argv, envp)?;
+        Ok((executable.entry(), stack_pointer))
+    })
+}
+
+/// Creates a new user process, whose address space is populated by `load`, and adds its thread
+/// to the scheduling policy. The process is a child of `parent`, defaulting to the process of the
+/// current task. `load` returns the entry point and the initial stack pointer of the thread.
+fn spawn_image<F>(priority: Priority, parent: Option<u64>, load: F) -> Result<u64, SchedulerError>
+where
+    F: FnOnce(
+        &mut AddressSpace,
+        &mut FrameAllocator,
+    ) -> Result<(VirtualAddress, VirtualAddress), SchedulerError>,
+{
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        // processes spawned by the idle task, i.e. during boot, are adopted by init
+        let parent = parent.unwrap_or_else(|| match scheduler.active.borrow().pid() {
+            IDLE_ID => INIT_PID,
+            pid => pid,
+        });
+
+        let pid = scheduler.next_pid();
+        let process = PerCoreScheduler::create_process(pid, parent)?;
+        let process = scheduler.add_process(process)?;
+
+        let spawned = (|| -> Result<(), SchedulerError> {
+            let (entry, stack_pointer) = {
+                let mut locked = VMM.locked();
+                let vmm = locked.get_mut().ok_or(VmmError::VmmUnitialized)?;
+                load(process.borrow_mut().address_space_mut(), vmm.ptm().pmm())?
+            };
+
+            let tid = scheduler.next_tid();
+            let fpu = FpuState::new(scheduler.fpu);
+            let thread = PerCoreScheduler::create_user_thread(
+                &process,
+                tid,
+                entry,
+                stack_pointer,
+                priority,
+                fpu,
+            )?;
+            scheduler.add_thread(thread)?;
+            Ok(())
+        })();
+
+        if let Err(err) = spawned {
+            scheduler.discard_process(&process)?;
+            return Err(err);
+        }
+        Ok(pid)
+    })
+}
+
+/// Creates a child process of the process of the current user task, whose address space is a
+/// copy-on-write clone of the parent's, see [`AddressSpace::fork`]. The child shares the open
+/// files of the parent and its single thread resumes from the system call of the current task,
+/// returning 0 with a clean floating point state. Returns the PID of the child.
+///
+/// Note: This must only be called from within a system call, whose entry saves the user context
+/// at the top of the kernel stack.
+pub(crate) fn fork() -> Result<u64, SchedulerError> {
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        let active = scheduler.active.clone();
+        let (parent, context, priority) = {
+            let active = active.borrow();
+            let context = unsafe { active.stack_top().cast::<CpuState>().sub(1).read() };
+            if context.iretq_cs & 0b11 != 3 {
+                return Err(SchedulerError::ForkKernel(active.tid()));
+            }
+            (active.process().clone(), context, active.priority())
+        };
+
+        let pid = scheduler.next_pid();
+        let process = PerCoreScheduler::create_process(pid, parent.borrow().pid())?;
+        let process = scheduler.add_process(process)?;
+
+        let forked = (|| -> Result<(), SchedulerError> {
+            {
+                let mut locked = VMM.locked();
+                let vmm = locked.get_mut().ok_or(VmmError::VmmUnitialized)?;
+                parent
+                    .borrow_mut()
+                    .address_space_mut()
+                    .fork(process.borrow_mut().address_space_mut(), vmm.ptm().pmm())?;
+            }
+            *process.borrow_mut().files_mut() = parent.borrow().files().clone();
+
+            let tid = scheduler.next_tid();
+            let fpu = FpuState::new(scheduler.fpu);
+            let thread =
+                PerCoreScheduler::create_forked_thread(&process, tid, &context, priority, fpu)?;
+            scheduler.add_thread(thread)?;
+            Ok(())
+        })();
+
+        if let Err(err) = forked {
+            scheduler.discard_process(&process)?;
+            return Err(err);
+        }
+        Ok(pid)
+    })
+}
+
+/// Boxes the entry of a thread, storing its return value in the returned slot.
+fn with_result<F, T>(entry: F) -> (Entry, Rc<RefCell<Option<T>>>)
+where
+    F: FnOnce() -> T + 'static,
+    T: 'static,
+{
+    let result = Rc::new(RefCell::new(None));
+    let slot = result.clone();
+    let entry = Box::new(move || {
+        let value = entry();
+        *slot.borrow_mut() = Some(value);
+    });
+
+    (entry, result)
+}
+
+/// Terminates all threads of the process with [`EXIT_KILLED`]. Its resources are released once
+/// it is reaped by its parent, see [`wait`]. Neither the process of the current task nor idle or
+/// init can be killed.
+pub(crate) fn kill(pid: u64) -> Result<(), SchedulerError> {
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        if pid == IDLE_ID || pid == INIT_PID {
+            return Err(SchedulerError::KillProtected(pid));
+        }
+        if scheduler.active.borrow().pid() == pid {
+            return Err(SchedulerError::KillActive(pid));
+        }
+
+        let process = scheduler
+            .process(pid)
+            .ok_or(SchedulerError::ProcessNotFound(pid))?;
+
+        scheduler.kill_process(&process, EXIT_KILLED);
+        Ok(())
+    })
+}
+
+/// Blocks the current task until a child process of its process has exited, either the one with
+/// the specified PID or any of them. The child is reaped, releasing all of its resources. Returns
+/// the PID and exit code of the child.
+pub(crate) fn wait(pid: Option<u64>) -> Result<(u64, i32), SchedulerError> {
+    wait_for(|current| {
+        let mut locked = SCHEDULER.locked();
+        let Some(scheduler) = locked.get_mut() else {
+            return Some(Err(SchedulerError::SchedulerUninitialized));
+        };
+
+        let process = current.borrow().process().clone();
+        let parent = process.borrow().pid();
+
+        let mut found = false;
+        let mut zombie = None;
+        for child in scheduler.processes() {
+            let child = child.borrow();
+            if child.parent() == parent && pid.is_none_or(|pid| child.pid() == pid) {
+                found = true;
+                if child.is_zombie() {
+                    zombie = Some(child.pid());
+                    break;
+                }
+            }
+        }
+
+        if let Some(child) = zombie {
+            return Some(scheduler.reap_process(child).map(|child| {
+                let child = child.borrow();
+                let code = child.exit_code().expect("only zombies are reaped");
+                (child.pid(), code)
+            }));
+        }
+
+        // init waits for orphans to be adopted, rather than failing
+        if !found && !(parent == INIT_PID && pid.is_none()) {
+            return Some(Err(match pid {
+                Some(pid) => SchedulerError::ChildNotFound(pid),
+                None => SchedulerError::NoChildren(parent),
+            }));
+        }
+
+        process
+            .borrow_mut()
+            .park_waiter(current.clone())
+            .expect("the current task cannot have finished");
+        None
+    })?
+}
+
+/// Handles the device-not-available exception (#NM), which is raised by the first floating point
+/// or SIMD instruction of a task after it has been scheduled. The FPU state is switched lazily,
+/// saving the state of its previous owner and restoring the one of the active task.
+pub(crate) fn restore_fpu() {
+    let mut locked = SCHEDULER
+        .try_locked()
+        .expect("FPU must not be used while the scheduler is locked");
+    let scheduler = locked
+        .get_mut()
+        .expect("FPU must not be used before multitasking is initialized");
+
+    scheduler.switch_fpu();
+}
+
+/// Terminates the active task with [`EXIT_KILLED`] from within an exception handler, e.g. after
+/// it overflowed its stack. Returns the terminated task, or `None` if the idle task is active or
+/// the scheduler is unavailable.
+///
+/// Note: The resources of the task are released once its process is reaped.
+pub(crate) fn terminate_active() -> Option<Rc<RefCell<Task>>> {
+    let mut locked = SCHEDULER.try_locked()?;
+    let scheduler = locked.get_mut()?;
+
+    if Rc::ptr_eq(&scheduler.active, &scheduler.idle) {
+        return None;
+    }
+
+    let active = scheduler.active.clone();
+    scheduler.exit_thread(&active, EXIT_KILLED);
+    Some(active)
+}
+
+/// Releases the resources of a finished thread. A thread, which has already been released
+/// together with its process, see [`wait`], is skipped, since TIDs are never reused.
+#[cfg_attr(not(feature = "selftest"), allow(dead_code))]
+fn reap(tid: u64) -> Result<(), SchedulerError> {
+    without_interrupts(|| {
+        let mut locked = SCHEDULER.locked();
+        let scheduler = scheduler!(locked);
+
+        if scheduler.thread(tid).is_none() {
+            return Ok(());
+        }
+        scheduler.kill_thread(tid)
+    })
+}
+
+/// Gives up the remainder of the current time slice.
+pub(crate) fn yield_now() {
+    unsafe {
+        asm!("int {vector}", vector = const YIELD_VECTOR);
+    }
+}
+
+/// Blocks the current task until `poll` yields a value.
+///
+/// `poll` is called with interrupts disabled and receives the current task. If no value is
+/// available yet, it must park the task on the wait queue of the event it is waiting for, which
+/// is then responsible for waking it up again.
+pub(crate) fn wait_for<T>(
+    mut poll: impl FnMut(&Rc<RefCell<Task>>) -> Option<T>,
+) -> Result<T, SchedulerError> {
+    without_interrupts(|| loop {
+        let current = current()?;
+
+        if let Some(value) = poll(&current) {
+            return Ok(value);
+        }
+
+        // interrupts are re-enabled, once another task is scheduled
+        yield_now();
+    })
+}
+
+/// Puts the current task to sleep for at least the given duration, which is rounded up to whole
+/// timer intervals.
+pub(crate) fn sleep(duration: Duration) -> Result<(), SchedulerError> {
+    let ticks = duration.as_millis().div_ceil(INTERVAL_MILLIS as u128) as u64;
+    // the current interval has already partially passed
+    let deadline = TICKS.load(Ordering::Relaxed) + ticks + 1;
+
+    wait_for(|current| {
+        if TICKS.load(Ordering::Relaxed) >= deadline {
+            return Some(());
+        }
+
+        if let Some(scheduler) = SCHEDULER.locked().get_mut() {
+            scheduler
+                .sleeping
+                .insert(deadline, current.clone())
+                .expect("the current task cannot have finished");
+        }
+
+        None
+    })
+}
+
+/// Advances the time of the scheduler by one timer interval. Must only be called from the timer
+/// interrupt.
+pub(crate) fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
 
-pub(crate) fn initialize() -> Result<(), SchedulerError> {
-    SCHEDULER.initialize(PerCoreScheduler::try_new(idle)?);
-    Ok(())
+/// Returns the currently active task.
+pub(crate) fn current() -> Result<Rc<RefCell<Task>>, SchedulerError> {
+    without_interrupts(|| {
+        SCHEDULER
+            .locked()
+            .get()
+            .map(|scheduler| scheduler.active.clone())
+            .ok_or(SchedulerError::SchedulerUninitialized)
+    })
 }
 
 fn idle() {
-    serial_println!("now idle");
     hlt_loop();
 }
 
-fn test() {
-    serial_println!("now tst");
-    hlt_loop();
+/// Entry of the init process if the root filesystem does not provide one at [`INIT_PATH`]. It
+/// reaps its children including all orphaned processes.
+fn init() {
+    loop {
+        if let Err(err) = wait(None) {
+            serial_println!(" [ERROR]: init failed to reap a process: {}", err);
+        }
+    }
 }
 
 static SCHEDULER: Locked<PerCoreScheduler> = Locked::new();
@@ -38,28 +560,171 @@ macro_rules! vmm {
 }
 #[derive(Debug)]
 pub(crate) struct PerCoreScheduler {
-    tasks: LinkedList<Rc<RefCell<Task>>>,
+    policy: SchedulingPolicy,
     active: Rc<RefCell<Task>>,
     idle: Rc<RefCell<Task>>,
+    /// All processes except for the idle process, including zombies.
+    processes: BTreeMap<u64, Rc<RefCell<Process>>>,
+    sleeping: TimerQueue,
+    /// Value of [`TICKS`] when the scheduler has been initialized.
+    first_tick: u64,
+    /// Value of [`TICKS`] at the last scheduling decision.
+    last_tick: u64,
     pid_counter: u64,
+    tid_counter: u64,
+    /// Mechanism used to save and restore the FPU state of tasks.
+    fpu: SaveMechanism,
+    /// TID of the task whose state is currently loaded into the FPU.
+    fpu_owner: Option<u64>,
 }
 
-fn dump(t: &Task) {
-    let mut locked = VMM.locked();
-    let vmm = locked.get_mut().unwrap();
-    serial_println!("current: {:?}", unsafe {
-        vmm.ptm().mappings_ref().pml4_virtual().as_ref().entries
-    });
+impl PerCoreScheduler {
+    /// Initializes a new scheduler with an idle task.
+    ///
+    /// Note: The idle task is the initially active one, but it is only started on the first
+    /// timer interrupt. The context of the code that enabled the scheduler is discarded at that
+    /// point, since it is not owned by any task.
+    pub(crate) fn try_new(
+        idle: fn(),
+        fpu: SaveMechanism,
+    ) -> Result<PerCoreScheduler, SchedulerError> {
+        let process = Rc::new(RefCell::new(Self::create_process(IDLE_ID, IDLE_ID)?));
+        let idle = Rc::new(RefCell::new(Self::create_thread(
+            &process,
+            IDLE_ID,
+            Box::new(idle),
+            Priority::Normal,
+            FpuState::new(fpu),
+        )?));
 
-    serial_println!("task: {:?}", unsafe {
-        t.mappings().pml4_virtual().as_ref().entries
-    });
+        let now = TICKS.load(Ordering::Relaxed);
+        Ok(PerCoreScheduler {
+            policy: SchedulingPolicy::new(),
+            active: idle.clone(),
+            idle,
+            processes: BTreeMap::new(),
+            sleeping: TimerQueue::new(),
+            first_tick: now,
+            last_tick: now,
+            pid_counter: IDLE_ID + 1,
+            tid_counter: IDLE_ID + 1,
+            fpu,
+            fpu_owner: None,
+        })
+    }
+
+    /// Returns a new unique PID.
+    fn next_pid(&mut self) -> u64 {
+        let pid = self.pid_counter;
+        self.pid_counter += 1;
+        pid
+    }
+
+    /// Returns a new unique TID.
+    fn next_tid(&mut self) -> u64 {
+        let tid = self.tid_counter;
+        self.tid_counter += 1;
+        tid
+    }
+
+    /// Removes a process, which could not be set up and thus has no threads, from the process
+    /// table and deletes its address space, dropping its share of any forked frames.
+    fn discard_process(&mut self, process: &Rc<RefCell<Process>>) -> Result<(), SchedulerError> {
+        let mut process = process.borrow_mut();
+        self.processes.remove(&process.pid());
+
+        // Safety: the process never ran, so its address space is not active.
+        unsafe { Self::delete_address_space(process.address_space_mut()) }
+    }
 }
 
 impl PerCoreScheduler {
-    /// Initializes a new scheduler with an idle task.
-    pub(crate) fn try_new(_idle: fn()) -> Result<PerCoreScheduler, SchedulerError> {
-        todo!("create new per core scheduler")
+    /// Saves the context of the active task, wakes sleeping tasks whose deadline has passed and
+    /// switches to the task selected by the scheduling policy, falling back to the idle task if
+    /// none is ready. Returns the context to continue with.
+    fn schedule(&mut self, context: NonNull<CpuState>) -> NonNull<CpuState> {
+        // the active task is only ready if it has not been started yet. In that case, the
+        // interrupted context is not owned by any task.
+        if self.active.borrow().state() != TaskState::Ready {
+            unsafe {
+                self.active.borrow_mut().update(context);
+            }
+        }
+
+        let now = TICKS.load(Ordering::Relaxed);
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
+        self.active.borrow_mut().account(elapsed);
+
+        self.sleeping.wake_expired(now);
+
+        let next = self
+            .policy
+            .select(&self.active, elapsed)
+            .unwrap_or_else(|| self.idle.clone());
+
+        if Rc::ptr_eq(&next, &self.active) && next.borrow().state() == TaskState::Running {
+            return context;
+        }
+
+        // the VMM must operate on the mappings of the next task. If it is in use by the
+        // interrupted task, the switch is postponed to the next tick.
+        if unsafe { vmm::update(next.borrow().mappings()) }.is_err() {
+            return context;
+        }
+
+        // tasks with a poisoned address space are never scheduled
+        _ = self.active.borrow_mut().pause();
+
+        next.borrow_mut()
+            .activate()
+            .expect("only ready tasks with a valid address space are scheduled");
+
+        // the FPU state is only switched once it is used by the next task
+        let owns_fpu = self.fpu_owner == Some(next.borrow().tid());
+        unsafe {
+            if owns_fpu {
+                Cr0::clear_task_switched();
+            } else {
+                (Cr0::read() | Cr0::TASK_SWITCHED).write();
+            }
+        }
+
+        // interrupts of user tasks are handled on their kernel stack
+        unsafe {
+            gdt::set_kernel_stack(next.borrow().stack_top().as_ptr());
+        }
+
+        let next_context = next.borrow().context();
+        self.active = next;
+
+        next_context
+    }
+}
+
+impl PerCoreScheduler {
+    /// Loads the FPU state of the active task, saving the state of the previous owner.
+    fn switch_fpu(&mut self) {
+        unsafe {
+            Cr0::clear_task_switched();
+        }
+
+        let active = self.active.borrow().tid();
+        if self.fpu_owner == Some(active) {
+            return;
+        }
+
+        // the previous owner might have been reaped in the meantime
+        if let Some(owner) = self.fpu_owner.and_then(|tid| self.thread(tid)) {
+            unsafe {
+                owner.borrow_mut().fpu_mut().save(self.fpu);
+            }
+        }
+
+        unsafe {
+            self.active.borrow().fpu().restore(self.fpu);
+        }
+        self.fpu_owner = Some(active);
     }
 }
 
@@ -67,6 +732,9 @@ impl Scheduler for PerCoreScheduler {
     const STACK_SIZE: usize = 0x4000;
     const KERNEL_DS: u16 = KERNEL_DS;
     const KERNEL_CS: u16 = KERNEL_CS;
+    const USER_DS: u16 = USER_DS;
+    const USER_CS: u16 = USER_CS;
+    const INIT_PID: u64 = INIT_PID;
 
     type SchedulerError = SchedulerError;
 
@@ -74,77 +742,183 @@ impl Scheduler for PerCoreScheduler {
     ///
     /// Note: Memory allocated by the VMM is guaranteed to be page-aligned. [`mem::VMM_VIRTUAL`] and subsequent addresses are multiples of [`mem::PAGE_SIZE`].
     fn create_address_space() -> Result<AddressSpace, Self::SchedulerError> {
-        let mut locked = VMM.locked();
-        let vmm = vmm!(locked);
+        without_interrupts(|| {
+            let mut locked = VMM.locked();
+            let vmm = vmm!(locked);
 
-        let pml4 = vmm
-            .alloc(PAGE_SIZE, VmFlags::WRITE, AllocationType::AnyPages)?
-            .cast::<PageTable>();
+            let pml4 = vmm
+                .alloc(PAGE_SIZE, VmFlags::WRITE, AllocationType::AnyPages)?
+                .cast::<PageTable>();
 
-        let pml4_phys = vmm
-            .ptm()
-            .mappings()
-            .get(pml4.as_ptr() as u64)
-            .unwrap()
-            .cast::<PageTable>();
-        Ok(AddressSpace::new(pml4_phys, pml4, vmm.ptm()))
+            let pml4_phys = vmm
+                .ptm()
+                .mappings()
+                .get(pml4.as_ptr() as u64)
+                .unwrap()
+                .cast::<PageTable>();
+            Ok(AddressSpace::new(pml4_phys, pml4, vmm.ptm()))
+        })
     }
 
     unsafe fn delete_address_space(
         address_space: &mut AddressSpace,
     ) -> Result<(), Self::SchedulerError> {
-        let mut locked = VMM.locked();
-        let vmm = vmm!(locked);
+        without_interrupts(|| {
+            let mut locked = VMM.locked();
+            let vmm = vmm!(locked);
 
-        // free all subsequent page tables
-        unsafe {
-            address_space.clean(vmm.ptm().pmm())?;
+            // free all subsequent page tables
+            unsafe {
+                address_space.clean(vmm.ptm().pmm())?;
+            }
 
-            // free the pml4 frame
-            address_space.free(vmm.ptm()).map_err(SchedulerError::from)
-        }
+            // the pml4 has been allocated as an object of the VMM
+            vmm.free(address_space.pml4_to_free()?)
+                .map_err(SchedulerError::from)
+        })
     }
 
-    /// Allocates a new task stack using the global virtual memory manager.
+    /// Allocates a new task stack using the global virtual memory manager. The stack is preceded
+    /// by an unmapped guard page, so that a stack overflow causes a page fault. Its pages are only
+    /// backed once they are used.
     ///
     /// Note: Memory allocated by the VMM is guaranteeed to be 16-byte-aligned. [`mem::VMM_VIRTUAL`] and subsequent addresses are multiples of 16.
     fn allocate_stack() -> Result<NonNull<u8>, Self::SchedulerError> {
-        let mut locked = VMM.locked();
-        let vmm = vmm!(locked);
+        without_interrupts(|| {
+            let mut locked = VMM.locked();
+            let vmm = vmm!(locked);
 
-        vmm.alloc(Self::STACK_SIZE, VmFlags::WRITE, AllocationType::AnyPages)
+            // the stack grows downwards, starting at the end of the allocated object
+            vmm.alloc_guarded(
+                Self::STACK_SIZE,
+                STACK_GUARD_SIZE,
+                VmFlags::WRITE | VmFlags::LAZY,
+                AllocationType::AnyPages,
+            )
+            .map(|bottom| unsafe { bottom.add(Self::STACK_SIZE) })
             .map_err(SchedulerError::from)
+        })
     }
     fn free_stack(stack_top: NonNull<u8>) -> Result<(), Self::SchedulerError> {
-        let mut locked = VMM.locked();
-        let vmm = vmm!(locked);
+        without_interrupts(|| {
+            let mut locked = VMM.locked();
+            let vmm = vmm!(locked);
 
-        vmm.free(stack_top.as_ptr() as VirtualAddress)
-            .map_err(SchedulerError::from)
+            vmm.free(stack_top.as_ptr() as VirtualAddress - Self::STACK_SIZE as u64)
+                .map_err(SchedulerError::from)
+        })
     }
 
-    /// Removes a process from the queue of tasks. This only succeeds if the process has the state
-    /// [`scheduler::task::TaskState::Done`].
-    fn remove_process(&mut self, _pid: u64) -> Result<Rc<RefCell<Task>>, Self::SchedulerError> {
-        unimplemented!();
+    /// Removes a thread from the scheduling policy and the sleeping threads. This only succeeds if
+    /// the thread has the state [`scheduler::task::TaskState::Done`].
+    fn remove_thread(&mut self, tid: u64) -> Result<Rc<RefCell<Task>>, Self::SchedulerError> {
+        let thread = self
+            .policy
+            .get(tid)
+            .ok_or(SchedulerError::ThreadNotFound(tid))?;
+
+        if thread.borrow().state() != TaskState::Done {
+            return Err(SchedulerError::RemoveNoDone);
+        }
+
+        // the timer interrupt must not drop the last reference of a killed sleeping thread
+        let thread = self
+            .policy
+            .remove(tid)
+            .ok_or(SchedulerError::ThreadNotFound(tid))?;
+        self.sleeping.remove(&thread);
+        Ok(thread)
     }
 
-    fn add_process(&mut self, _process: Task) -> Result<(), Self::SchedulerError> {
-        unimplemented!();
+    /// Adds a thread to the scheduling policy.
+    fn add_thread(&mut self, thread: Task) -> Result<Rc<RefCell<Task>>, Self::SchedulerError> {
+        let tid = thread.tid();
+        if tid == IDLE_ID || self.policy.get(tid).is_some() {
+            // the rejected thread is dropped, so its stack must not outlive it
+            Self::free_stack(thread.stack_top())?;
+            return Err(SchedulerError::DuplicateTid(tid));
+        }
+
+        let thread = Rc::new(RefCell::new(thread));
+        self.policy.insert(thread.clone());
+        Ok(thread)
     }
 
-    fn run(context: &CpuState) -> &CpuState {
-        /*
-        // set old address space & task to deactived
-        current_task.pause().expect("scheduler paused - failed");
-        let mut next_task = next_task.borrow_mut();
-        next_task.activate().unwrap();
-        // update VMM
-        unsafe {
-            vmm::update(next_task.mappings()).unwrap();
+    fn thread(&self, tid: u64) -> Option<Rc<RefCell<Task>>> {
+        self.policy.get(tid).cloned()
+    }
+
+    /// Adds a process to the process table and wakes its parent, which might be waiting for
+    /// children to be spawned.
+    fn add_process(
+        &mut self,
+        mut process: Process,
+    ) -> Result<Rc<RefCell<Process>>, Self::SchedulerError> {
+        let pid = process.pid();
+        if pid == IDLE_ID || self.processes.contains_key(&pid) {
+            // the rejected process is dropped, so its address space must not outlive it
+            unsafe { Self::delete_address_space(process.address_space_mut())? };
+            return Err(SchedulerError::DuplicatePid(pid));
+        }
+
+        if let Some(parent) = self.process(process.parent()) {
+            parent.borrow_mut().wake_waiters();
+        }
+
+        let process = Rc::new(RefCell::new(process));
+        self.processes.insert(pid, process.clone());
+        Ok(process)
+    }
+
+    /// Removes a process from the process table. This only succeeds if the process is a zombie.
+    fn remove_process(&mut self, pid: u64) -> Result<Rc<RefCell<Process>>, Self::SchedulerError> {
+        let process = self
+            .process(pid)
+            .ok_or(SchedulerError::ProcessNotFound(pid))?;
+
+        if !process.borrow().is_zombie() {
+            return Err(SchedulerError::NotZombie(pid));
+        }
+
+        self.processes.remove(&pid);
+        Ok(process)
+    }
+
+    fn process(&self, pid: u64) -> Option<Rc<RefCell<Process>>> {
+        self.processes.get(&pid).cloned()
+    }
+
+    fn processes(&self) -> impl Iterator<Item = &Rc<RefCell<Process>>> {
+        self.processes.values()
+    }
+
+    fn exit(code: i32) -> ! {
+        without_interrupts(|| {
+            if let Some(scheduler) = SCHEDULER.locked().get_mut() {
+                let active = scheduler.active.clone();
+                scheduler.exit_thread(&active, code);
+            }
+        });
+
+        // the task is never scheduled again
+        loop {
+            yield_now();
         }
-        return unsafe { next_task.context().as_ref() };
-        */
-        context
+    }
+
+    fn run(context: &CpuState) -> &CpuState {
+        // the timer interrupt must never spin on the scheduler lock
+        let Some(mut locked) = SCHEDULER.try_locked() else {
+            return context;
+        };
+        let Some(scheduler) = locked.get_mut() else {
+            return context;
+        };
+
+        let next = scheduler.schedule(NonNull::from(context));
+
+        // SAFETY: the context either belongs to the interrupted or a previously paused task and
+        // lives on the task's stack.
+        unsafe { next.as_ref() }
     }
 }
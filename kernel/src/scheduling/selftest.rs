@@ -0,0 +1,40 @@
+use alloc::vec::Vec;
+use framebuffer::color;
+use scheduler::task::Priority;
+
+use super::{current, error::SchedulerError, spawn_thread};
+use crate::println;
+
+/// Number of threads spawned by [`thread_test`].
+const TEST_THREADS: u64 = 4;
+
+/// Kernel task checking that threads spawned by [`spawn_thread`] belong to the process of the
+/// spawning task and that joining them returns their results. The outcome is printed to the
+/// console.
+pub(crate) fn thread_test() {
+    let passed = (|| -> Result<bool, SchedulerError> {
+        let pid = current()?.borrow().pid();
+        let threads = (0..TEST_THREADS)
+            .map(|index| spawn_thread(move || index * index, Priority::Normal))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut passed = true;
+        for (index, thread) in (0..TEST_THREADS).zip(threads) {
+            passed &= thread.pid() == pid;
+            passed &= thread.join()? == index * index;
+        }
+        Ok(passed)
+    })();
+
+    match passed {
+        Ok(true) => {
+            println!(color::LOG, "[threads] passed");
+        }
+        Ok(false) => {
+            println!(color::ERROR, "[threads] failed");
+        }
+        Err(err) => {
+            println!(color::ERROR, "[threads] failed: {}", err);
+        }
+    }
+}
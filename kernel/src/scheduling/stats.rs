@@ -0,0 +1,129 @@
+use alloc::{format, vec::Vec};
+use core::{fmt, sync::atomic::Ordering, time::Duration};
+use hal::interrupts::without_interrupts;
+use scheduler::{policy::Policy, task::stats::TaskSnapshot};
+
+use crate::{io::timer::lapict::INTERVAL_MILLIS, serial_print};
+
+use super::{error::SchedulerError, sleep, IDLE_ID, SCHEDULER, TICKS};
+
+/// Interval in which the task monitor dumps the task list.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// State and accounting information of all tasks of the scheduler at a specific point in time.
+///
+/// Note: The ticks of the current time slice are only charged to the active task at the next
+/// scheduling decision.
+#[derive(Debug)]
+pub(crate) struct Snapshot {
+    /// Number of timer ticks since the scheduler has been initialized.
+    pub(crate) uptime: u64,
+    /// All tasks including the idle task, ordered by TID.
+    pub(crate) tasks: Vec<TaskSnapshot>,
+}
+
+impl Snapshot {
+    /// Returns the share of the uptime the idle task has been running for in per mille.
+    pub(crate) fn idle_per_mille(&self) -> u64 {
+        let idle = self
+            .tasks
+            .iter()
+            .find(|task| task.tid == IDLE_ID)
+            .map_or(0, |task| task.stats.runtime);
+
+        (idle * 1000).checked_div(self.uptime).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Snapshot {
+    /// Formats the snapshot as a `ps`-style list of all tasks.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let idle = self.idle_per_mille();
+        writeln!(
+            f,
+            "uptime: {} ms, idle: {}.{}%",
+            self.uptime * INTERVAL_MILLIS,
+            idle / 10,
+            idle % 10
+        )?;
+        writeln!(
+            f,
+            "{:>5} {:>5} {:<9} {:<12} {:>12} {:>9} {:>9}",
+            "TID", "PID", "STATE", "PRIORITY", "RUNTIME(ms)", "SWITCHES", "WAKEUPS"
+        )?;
+
+        for task in self.tasks.iter() {
+            writeln!(
+                f,
+                "{:>5} {:>5} {:<9} {:<12} {:>12} {:>9} {:>9}",
+                task.tid,
+                task.pid,
+                format!("{:?}", task.state),
+                format!("{:?}", task.priority),
+                task.stats.runtime * INTERVAL_MILLIS,
+                task.stats.switches,
+                task.stats.wakeups
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Takes a snapshot of all tasks of the scheduler.
+pub(crate) fn snapshot() -> Result<Snapshot, SchedulerError> {
+    // reserve memory up front, the heap must not be used with the scheduler locked
+    let mut tasks = Vec::with_capacity(without_interrupts(|| {
+        SCHEDULER
+            .locked()
+            .get()
+            .map(|scheduler| scheduler.policy.len() + 1)
+            .ok_or(SchedulerError::SchedulerUninitialized)
+    })?);
+
+    without_interrupts(|| {
+        let locked = SCHEDULER.locked();
+        let scheduler = locked.get().ok_or(SchedulerError::SchedulerUninitialized)?;
+
+        tasks.push(scheduler.idle.borrow().snapshot());
+        // threads spawned in the meantime are skipped, rather than growing the vector
+        tasks.extend(
+            scheduler
+                .policy
+                .iter()
+                .take(tasks.capacity() - 1)
+                .map(|thread| thread.borrow().snapshot()),
+        );
+
+        let uptime = TICKS.load(Ordering::Relaxed) - scheduler.first_tick;
+        Ok(uptime)
+    })
+    .map(|uptime| {
+        tasks.sort_unstable_by_key(|task| task.tid);
+        Snapshot { uptime, tasks }
+    })
+}
+
+/// Returns the time since the scheduler has been initialized.
+pub(crate) fn uptime() -> Result<Duration, SchedulerError> {
+    let ticks = without_interrupts(|| {
+        SCHEDULER
+            .locked()
+            .get()
+            .map(|scheduler| TICKS.load(Ordering::Relaxed) - scheduler.first_tick)
+            .ok_or(SchedulerError::SchedulerUninitialized)
+    })?;
+    Ok(Duration::from_millis(ticks * INTERVAL_MILLIS))
+}
+
+/// Prints a `ps`-style list of all tasks to the serial port.
+pub(crate) fn dump() -> Result<(), SchedulerError> {
+    serial_print!("{}", snapshot()?);
+    Ok(())
+}
+
+/// Kernel task periodically dumping the task list to the serial port.
+#[cfg_attr(not(feature = "task-monitor"), allow(dead_code))]
+pub(crate) fn monitor() {
+    while sleep(MONITOR_INTERVAL).is_ok() && dump().is_ok() {}
+}
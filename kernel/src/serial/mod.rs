@@ -1,5 +1,6 @@
 use bitflags::bitflags;
 use core::{cell::LazyCell, fmt};
+use hal::interrupts::without_interrupts;
 use sync::spin::SpinLock;
 use uart::SerialPort;
 
@@ -15,6 +16,17 @@ static PORT: SpinLock<LazyCell<SerialPort>> = SpinLock::new(LazyCell::new(|| {
 pub(crate) mod macros;
 pub(crate) mod uart;
 
+/// Sends the bytes on the serial port.
+pub(crate) fn write(bytes: &[u8]) {
+    without_interrupts(|| {
+        let mut locked = PORT.lock();
+        let port = LazyCell::<SerialPort>::force_mut(&mut locked);
+        for byte in bytes {
+            port.send(*byte);
+        }
+    });
+}
+
 bitflags! {
     /// Interrupt enable flags
     #[repr(transparent)]
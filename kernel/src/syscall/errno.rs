@@ -0,0 +1,116 @@
+use vfs::error::VfsError;
+
+use crate::{
+    fs::error::FsError, memory::user::error::UserAccessError, scheduling::error::SchedulerError,
+};
+
+/// Error numbers of failed system calls, which are returned negated in `rax`. The values match
+/// the ones used by Linux.
+#[repr(i64)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Errno {
+    /// Operation not permitted (EPERM)
+    NotPermitted = 1,
+    /// No such file or directory (ENOENT)
+    NoEntry = 2,
+    /// No such process (ESRCH)
+    NoProcess = 3,
+    /// I/O error (EIO)
+    Io = 5,
+    /// Bad file descriptor (EBADF)
+    BadDescriptor = 9,
+    /// No child processes (ECHILD)
+    NoChild = 10,
+    /// Out of memory (ENOMEM)
+    OutOfMemory = 12,
+    /// Bad address (EFAULT)
+    BadAddress = 14,
+    /// Device or resource busy (EBUSY)
+    Busy = 16,
+    /// File exists (EEXIST)
+    Exists = 17,
+    /// Not a directory (ENOTDIR)
+    NotDirectory = 20,
+    /// Is a directory (EISDIR)
+    IsDirectory = 21,
+    /// Invalid argument (EINVAL)
+    InvalidArgument = 22,
+    /// Too many open files (EMFILE)
+    TooManyFiles = 24,
+    /// No space left on device (ENOSPC)
+    NoSpace = 28,
+    /// Read-only file system (EROFS)
+    ReadOnlyFs = 30,
+    /// Function not implemented (ENOSYS)
+    NotImplemented = 38,
+    /// Directory not empty (ENOTEMPTY)
+    NotEmpty = 39,
+    /// Too many levels of symbolic links (ELOOP)
+    Loop = 40,
+    /// Operation not supported (EOPNOTSUPP)
+    NotSupported = 95,
+}
+
+impl Errno {
+    /// Returns the value of `rax` indicating the error.
+    pub(crate) fn to_return_value(self) -> u64 {
+        (-(self as i64)) as u64
+    }
+}
+
+impl From<SchedulerError> for Errno {
+    fn from(value: SchedulerError) -> Self {
+        match value {
+            SchedulerError::ProcessNotFound(_) | SchedulerError::ThreadNotFound(_) => {
+                Errno::NoProcess
+            }
+            SchedulerError::KillActive(_) | SchedulerError::KillProtected(_) => Errno::NotPermitted,
+            SchedulerError::NoChildren(_) | SchedulerError::ChildNotFound(_) => Errno::NoChild,
+            SchedulerError::Vmm(_) | SchedulerError::AddressSpace(_) => Errno::OutOfMemory,
+            _ => Errno::InvalidArgument,
+        }
+    }
+}
+
+impl From<UserAccessError> for Errno {
+    fn from(value: UserAccessError) -> Self {
+        match value {
+            UserAccessError::Scheduler(err) => err.into(),
+            _ => Errno::BadAddress,
+        }
+    }
+}
+
+impl From<VfsError> for Errno {
+    fn from(value: VfsError) -> Self {
+        match value {
+            VfsError::NotFound | VfsError::InvalidPath => Errno::NoEntry,
+            VfsError::AlreadyExists => Errno::Exists,
+            VfsError::NotDirectory => Errno::NotDirectory,
+            VfsError::IsDirectory => Errno::IsDirectory,
+            VfsError::NotEmpty => Errno::NotEmpty,
+            VfsError::TooManyLinks => Errno::Loop,
+            VfsError::ReadOnly => Errno::ReadOnlyFs,
+            VfsError::NotSupported => Errno::NotSupported,
+            VfsError::NoSpace => Errno::NoSpace,
+            VfsError::Io => Errno::Io,
+            VfsError::BadDescriptor(_) | VfsError::NotReadable | VfsError::NotWritable => {
+                Errno::BadDescriptor
+            }
+            VfsError::TooManyOpenFiles => Errno::TooManyFiles,
+            VfsError::Busy | VfsError::AlreadyMounted => Errno::Busy,
+            _ => Errno::InvalidArgument,
+        }
+    }
+}
+
+impl From<FsError> for Errno {
+    fn from(value: FsError) -> Self {
+        match value {
+            FsError::Vfs(err) => err.into(),
+            FsError::Scheduler(err) => err.into(),
+            FsError::Vmm(_) | FsError::Format(_) => Errno::Io,
+            FsError::Uninitialized => Errno::NoEntry,
+        }
+    }
+}
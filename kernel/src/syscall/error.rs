@@ -0,0 +1,15 @@
+use hal::registers::msr::{efer::EferError, syscall::SyscallError as MsrSyscallError};
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SyscallError {
+    #[error("The CPUID feature is unavailable to the CPU")]
+    CpuidUnavailable,
+    #[error("The CPU does not support model specific registers")]
+    MsrUnavailable,
+    #[error("{0}")]
+    Msr(#[from] MsrSyscallError),
+    #[error("The EFER register could not be read")]
+    EferUnreadable,
+    #[error("{0}")]
+    Efer(#[from] EferError),
+}
@@ -0,0 +1,173 @@
+use core::arch::naked_asm;
+
+use errno::Errno;
+use error::SyscallError;
+use hal::{
+    cpu_state::CpuState,
+    instructions::cpuid::Cpuid,
+    registers::{
+        msr::{
+            efer::Efer,
+            msr_guard::Msr,
+            syscall::{LStar, SfMask, Star},
+            ModelSpecificRegister,
+        },
+        rflags::RFlags,
+    },
+};
+use table::SYSCALLS;
+
+use crate::gdt::{KERNEL_CS, RSP0_OFFSET, SYSRET_BASE, TSS, USER_CS, USER_DS};
+
+pub(crate) mod errno;
+pub(crate) mod error;
+pub(crate) mod table;
+
+/// Vector number stored in the context of a task that entered the kernel via SYSCALL. It does not
+/// collide with any interrupt vector.
+const SYSCALL_VECTOR: u64 = 0x100;
+
+/// Stack pointer of the user task while the system call entry switches to the kernel stack.
+///
+/// Note: The value is only used until it has been pushed onto the kernel stack, during which
+/// interrupts are disabled by SFMASK, so no other system call can overwrite it. This only holds as
+/// long as the kernel runs on a single core, otherwise it must be stored per core.
+static mut USER_RSP: u64 = 0;
+
+/// Enables the SYSCALL and SYSRET instructions and registers the system call entry.
+pub(crate) fn initialize() -> Result<(), SyscallError> {
+    let cpuid = Cpuid::new().ok_or(SyscallError::CpuidUnavailable)?;
+    let msr = Msr::new(cpuid).ok_or(SyscallError::MsrUnavailable)?;
+
+    // SAFETY: the kernel runs in privilege level 0 and the GDT layout matches the selectors.
+    unsafe {
+        Star::new(KERNEL_CS, SYSRET_BASE).write(msr)?;
+        LStar::new(syscall_entry as *const () as usize as u64).write(msr)?;
+        // interrupts are disabled until the user context has been saved on the kernel stack
+        SfMask::new(
+            RFlags::INTERRUPTS_ENABLED
+                | RFlags::TRAP
+                | RFlags::DIRECTION
+                | RFlags::ACCESS_CONTROL_ALIGNMENT_CHECK,
+        )
+        .write(msr)?;
+
+        let mut efer = Efer::read(msr).map_err(|()| SyscallError::EferUnreadable)?;
+        efer.insert(Efer::SCE);
+        efer.write(msr)?;
+    }
+
+    Ok(())
+}
+
+/// Dispatches the system call saved in the context by [`syscall_entry`]. The number is passed in
+/// `rax`, the arguments in `rdi`, `rsi`, `rdx`, `r10`, `r8` and `r9`. On return, `rax` holds
+/// either the result or the negated [`Errno`].
+///
+/// Note: Blocking system calls give up the CPU via [`crate::scheduling::yield_now`]. The task is
+/// resumed on its kernel stack.
+extern "sysv64" fn dispatch(state: &mut CpuState) -> &CpuState {
+    // the system call runs on the kernel stack of the task, so it can be interrupted
+    hal::interrupts::enable();
+
+    let args = [
+        state.rdi, state.rsi, state.rdx, state.r10, state.r8, state.r9,
+    ];
+
+    let result = usize::try_from(state.rax)
+        .ok()
+        .and_then(|number| SYSCALLS.get(number))
+        .map_or(Err(Errno::NotImplemented), |handler| handler(&args));
+
+    state.rax = match result {
+        Ok(value) => value,
+        Err(errno) => errno.to_return_value(),
+    };
+
+    // SYSRET loads the user stack pointer while still in ring 0
+    hal::interrupts::disable();
+    state
+}
+
+/// Entry of the SYSCALL instruction. Switches to the kernel stack of the active task and saves
+/// the user context in the same layout as [`crate::idt::dispatch`] does, so the task can be
+/// resumed either by SYSRET or IRETQ.
+#[unsafe(naked)]
+extern "C" fn syscall_entry() {
+    naked_asm!(
+        // SYSCALL does not switch stacks, the kernel stack of the task is stored in the TSS
+        "mov [rip + {user_rsp}], rsp",
+        "mov rsp, [rip + {tss} + {rsp0}]",
+
+        // interrupt stack frame: rip is stored in rcx, rflags in r11 by SYSCALL
+        "push {user_ds}",
+        "push [rip + {user_rsp}]",
+        "push r11",
+        "push {user_cs}",
+        "push rcx",
+        // dummy error code and vector number
+        "push 0",
+        "push {vector}",
+
+        "push rax",
+        "push rbx",
+        "push rcx",
+        "push rdx",
+        "push rsi",
+        "push rdi",
+        "push rbp",
+        "push r8",
+        "push r9",
+        "push r10",
+        "push r11",
+        "push r12",
+        "push r13",
+        "push r14",
+        "push r15",
+        // pass rsp to the dispatch handler (stack pointer)
+        "mov rdi, rsp",
+        "call {dispatch}",
+
+        // restore the stack pointer returned by the dispatch handler
+        "mov rsp, rax",
+
+        "pop r15",
+        "pop r14",
+        "pop r13",
+        "pop r12",
+        "pop r11",
+        "pop r10",
+        "pop r9",
+        "pop r8",
+        "pop rbp",
+        "pop rdi",
+        "pop rsi",
+        "pop rdx",
+        "pop rcx",
+        "pop rbx",
+        "pop rax",
+
+        // only contexts saved by SYSCALL can be resumed by SYSRET, since it clobbers rcx and r11
+        "cmp qword ptr [rsp], {vector}",
+        "jne 2f",
+        "mov rcx, [rsp + 16]",
+        // SYSRET faults in ring 0 on non-canonical addresses
+        "bt rcx, 47",
+        "jc 2f",
+        "mov r11, [rsp + 32]",
+        "mov rsp, [rsp + 40]",
+        "sysretq",
+
+        "2:",
+        // remove vector number + error code (16 bytes)
+        "add rsp, 16",
+        "iretq",
+        user_rsp = sym USER_RSP,
+        tss = sym TSS,
+        rsp0 = const RSP0_OFFSET,
+        user_ds = const USER_DS as u64,
+        user_cs = const USER_CS as u64,
+        vector = const SYSCALL_VECTOR,
+        dispatch = sym dispatch,
+    );
+}
@@ -0,0 +1,241 @@
+use alloc::{string::String, vec};
+use core::time::Duration;
+
+use framebuffer::color;
+use scheduler::Scheduler;
+use vfs::{
+    file::{OpenFlags, SeekFrom},
+    path::PATH_MAX,
+};
+
+use crate::{
+    fs,
+    memory::user::{copy_from_user, copy_to_user},
+    print,
+    scheduling::{self, PerCoreScheduler},
+};
+
+use super::errno::Errno;
+
+/// Handles a system call with the arguments passed in `rdi`, `rsi`, `rdx`, `r10`, `r8` and `r9`.
+pub(super) type Handler = fn(&[u64; 6]) -> Result<u64, Errno>;
+
+/// Terminates the calling thread with the exit code in the first argument.
+pub(crate) const EXIT: usize = 0;
+/// Gives up the remainder of the current time slice.
+pub(crate) const YIELD: usize = 1;
+/// Returns the PID of the calling process.
+pub(crate) const GETPID: usize = 2;
+/// Returns the TID of the calling thread.
+pub(crate) const GETTID: usize = 3;
+/// Returns the PID of the parent of the calling process.
+pub(crate) const GETPPID: usize = 4;
+/// Blocks the calling thread for at least the number of milliseconds in the first argument.
+pub(crate) const SLEEP: usize = 5;
+/// Terminates the process with the PID in the first argument.
+pub(crate) const KILL: usize = 6;
+/// Writes the buffer at the second argument with the length in the third argument to the file
+/// descriptor in the first argument. Returns the number of bytes written. Standard output (1) and
+/// error (2) are written to the console, unless they refer to an open file.
+pub(crate) const WRITE: usize = 7;
+/// Waits for the child with the PID in the first argument, or any child if it is `-1`. The exit
+/// code is stored at the second argument, unless it is null. Returns the PID of the child.
+pub(crate) const WAIT: usize = 8;
+
+/// Opens the file at the path at the first argument with the length in the second argument. The
+/// third argument contains the [`OpenFlags`]. Returns the new file descriptor.
+pub(crate) const OPEN: usize = 9;
+/// Reads from the file descriptor in the first argument into the buffer at the second argument
+/// with the length in the third argument. Returns the number of bytes read, which is 0 at the end
+/// of the file.
+pub(crate) const READ: usize = 10;
+/// Closes the file descriptor in the first argument.
+pub(crate) const CLOSE: usize = 11;
+/// Sets the offset of the file descriptor in the first argument to the second argument, relative
+/// to the start (0), the current offset (1) or the end (2) according to the third argument.
+/// Returns the new offset.
+pub(crate) const SEEK: usize = 12;
+
+/// Creates a child of the calling process with a copy-on-write copy of its address space and its
+/// open files. Returns the PID of the child to the parent and 0 to the child.
+pub(crate) const FORK: usize = 13;
+
+/// Number of system calls.
+const COUNT: usize = 14;
+
+/// Size of the kernel buffer user data is copied through.
+const BUFFER_SIZE: usize = 256;
+
+/// System call handlers, indexed by their number.
+pub(super) static SYSCALLS: [Handler; COUNT] = {
+    let mut table: [Handler; COUNT] = [not_implemented; COUNT];
+    table[EXIT] = exit;
+    table[YIELD] = yield_now;
+    table[GETPID] = getpid;
+    table[GETTID] = gettid;
+    table[GETPPID] = getppid;
+    table[SLEEP] = sleep;
+    table[KILL] = kill;
+    table[WRITE] = write;
+    table[WAIT] = wait;
+    table[OPEN] = open;
+    table[READ] = read;
+    table[CLOSE] = close;
+    table[SEEK] = seek;
+    table[FORK] = fork;
+    table
+};
+
+fn not_implemented(_: &[u64; 6]) -> Result<u64, Errno> {
+    Err(Errno::NotImplemented)
+}
+
+fn exit(args: &[u64; 6]) -> Result<u64, Errno> {
+    <PerCoreScheduler as Scheduler>::exit(args[0] as i32)
+}
+
+fn yield_now(_: &[u64; 6]) -> Result<u64, Errno> {
+    scheduling::yield_now();
+    Ok(0)
+}
+
+fn getpid(_: &[u64; 6]) -> Result<u64, Errno> {
+    Ok(scheduling::current()?.borrow().pid())
+}
+
+fn gettid(_: &[u64; 6]) -> Result<u64, Errno> {
+    Ok(scheduling::current()?.borrow().tid())
+}
+
+fn getppid(_: &[u64; 6]) -> Result<u64, Errno> {
+    Ok(scheduling::current()?.borrow().process().borrow().parent())
+}
+
+fn sleep(args: &[u64; 6]) -> Result<u64, Errno> {
+    scheduling::sleep(Duration::from_millis(args[0]))?;
+    Ok(0)
+}
+
+fn kill(args: &[u64; 6]) -> Result<u64, Errno> {
+    scheduling::kill(args[0])?;
+    Ok(0)
+}
+
+fn write(args: &[u64; 6]) -> Result<u64, Errno> {
+    let fd = args[0] as usize;
+    let fg = match fd {
+        _ if fs::is_open(fd)? => None,
+        1 => Some(color::LOG),
+        2 => Some(color::ERROR),
+        _ => return Err(Errno::BadDescriptor),
+    };
+
+    let (start, length) = (args[1], args[2] as usize);
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut written = 0;
+    while written < length {
+        let chunk = &mut buffer[..BUFFER_SIZE.min(length - written)];
+        copy_from_user(chunk, start + written as u64)?;
+        // a character split by the end of the chunk is copied again with the next one, so the
+        // console does not print its halves as replacement characters
+        let chunk = match written + chunk.len() < length {
+            true => &chunk[..chunk.len() - incomplete_character(chunk)],
+            false => &chunk[..],
+        };
+        let count = match fg {
+            Some(fg) => {
+                print!(fg, "{}", String::from_utf8_lossy(chunk));
+                chunk.len()
+            }
+            None => fs::write(fd, chunk)?,
+        };
+        written += count;
+        if count < chunk.len() {
+            break;
+        }
+    }
+
+    Ok(written as u64)
+}
+
+/// Returns the number of bytes at the end of `bytes`, which start a UTF-8 encoded character
+/// without completing it.
+fn incomplete_character(bytes: &[u8]) -> usize {
+    (1..=bytes.len().min(3))
+        .find(|&length| {
+            matches!(
+                core::str::from_utf8(&bytes[bytes.len() - length..]),
+                Err(err) if err.valid_up_to() == 0 && err.error_len().is_none()
+            )
+        })
+        .unwrap_or(0)
+}
+
+fn wait(args: &[u64; 6]) -> Result<u64, Errno> {
+    let pid = match args[0] {
+        u64::MAX => None,
+        pid => Some(pid),
+    };
+
+    let (pid, code) = scheduling::wait(pid)?;
+    if args[1] != 0 {
+        copy_to_user(args[1], &code.to_ne_bytes())?;
+    }
+
+    Ok(pid)
+}
+
+fn open(args: &[u64; 6]) -> Result<u64, Errno> {
+    let length = args[1] as usize;
+    if length > PATH_MAX {
+        return Err(Errno::InvalidArgument);
+    }
+
+    let mut path = vec![0; length];
+    copy_from_user(&mut path, args[0])?;
+    let path = str::from_utf8(&path).map_err(|_| Errno::InvalidArgument)?;
+    let flags = u32::try_from(args[2])
+        .ok()
+        .and_then(OpenFlags::from_bits)
+        .ok_or(Errno::InvalidArgument)?;
+
+    Ok(fs::open(path, flags)? as u64)
+}
+
+fn read(args: &[u64; 6]) -> Result<u64, Errno> {
+    let fd = args[0] as usize;
+    let (start, length) = (args[1], args[2] as usize);
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut read = 0;
+    while read < length {
+        let chunk = &mut buffer[..BUFFER_SIZE.min(length - read)];
+        let count = fs::read(fd, chunk)?;
+        copy_to_user(start + read as u64, &chunk[..count])?;
+        read += count;
+        if count < chunk.len() {
+            break;
+        }
+    }
+
+    Ok(read as u64)
+}
+
+fn close(args: &[u64; 6]) -> Result<u64, Errno> {
+    fs::close(args[0] as usize)?;
+    Ok(0)
+}
+
+fn seek(args: &[u64; 6]) -> Result<u64, Errno> {
+    let position = match args[2] {
+        0 => SeekFrom::Start(args[1]),
+        1 => SeekFrom::Current(args[1] as i64),
+        2 => SeekFrom::End(args[1] as i64),
+        _ => return Err(Errno::InvalidArgument),
+    };
+
+    Ok(fs::seek(args[0] as usize, position)?)
+}
+
+fn fork(_: &[u64; 6]) -> Result<u64, Errno> {
+    Ok(scheduling::fork()?)
+}
@@ -24,6 +24,11 @@ impl BitMap {
         self.buffer = unsafe { slice::from_raw_parts_mut(ptr, len) };
     }
 
+    /// Returns the address of the bitmap.
+    pub(crate) fn address(&self) -> u64 {
+        self.buffer.as_ptr() as u64
+    }
+
     /// Retrieve raw pointer to bitmap
     ///
     /// # Safety
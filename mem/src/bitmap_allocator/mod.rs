@@ -1,7 +1,7 @@
 use core::slice;
 
 use crate::{
-    PAGE_SIZE, PAS_VIRTUAL_MAX, PhysicalAddress,
+    PAGE_SIZE, PAS_VIRTUAL_MAX, PhysicalAddress, align_up,
     error::FrameAllocatorError,
     map::{MemoryDescriptor, MemoryMap, MemoryType},
 };
@@ -13,6 +13,8 @@ pub mod map;
 pub struct BitMapAllocator {
     memory_map: MemoryMap,
     bit_map: BitMap,
+    /// Number of additional owners of each allocated frame, stored after the bitmap.
+    shares: &'static mut [u16],
     current_descriptor_index: usize,
     current_address: PhysicalAddress,
     free_memory: u64,
@@ -31,6 +33,7 @@ impl BitMapAllocator {
         // total memory size in bytes => / PAGE_SIZE is the amount of pages. In the bitmap each page is one bit => /8 gives out the amount of bytes to allocate
         let total_pages = (memory_map.last_addr as usize).div_ceil(PAGE_SIZE);
         let bit_map_size = total_pages.div_ceil(8);
+        let (shares_offset, metadata_size) = metadata_layout(total_pages);
 
         // find memory region to store bitmap in
         let mem = memory_map
@@ -39,7 +42,7 @@ impl BitMapAllocator {
             .filter(|mem| {
                 mem.phys_end < PAS_VIRTUAL_MAX
                     && mem.r#type == MemoryType::Available
-                    && mem.size() >= bit_map_size as u64
+                    && mem.size() >= metadata_size as u64
             })
             .min_by(|a, b| a.size().cmp(&b.size()))
             .ok_or(FrameAllocatorError::InvalidMemoryMap)?;
@@ -47,9 +50,13 @@ impl BitMapAllocator {
         let mem_ptr = mem.phys_start as *mut u8;
 
         let buffer = unsafe { slice::from_raw_parts_mut(mem_ptr, bit_map_size) };
+        let shares = unsafe {
+            slice::from_raw_parts_mut(mem_ptr.add(shares_offset).cast::<u16>(), total_pages)
+        };
 
         // clear any pre-existing data
         buffer.fill(0);
+        shares.fill(0);
 
         let bit_map = BitMap::new(buffer);
 
@@ -58,6 +65,7 @@ impl BitMapAllocator {
         let mut instance = Self {
             memory_map,
             bit_map,
+            shares,
             free_memory,
             used_memory: 0,
             reserved_memory: 0,
@@ -67,8 +75,8 @@ impl BitMapAllocator {
             ignore_acpi: true,
         };
 
-        // reserve frames of bitmap
-        instance.reserve_frames(mem_ptr as u64, instance.bit_map.pages())?;
+        // reserve frames of bitmap and share counts
+        instance.reserve_frames(mem_ptr as u64, metadata_size.div_ceil(PAGE_SIZE))?;
 
         // reserve frames for reserved memory descriptors
         let mmap = instance.memory_map;
@@ -88,10 +96,7 @@ impl BitMapAllocator {
         for desc_index in self.current_descriptor_index..self.memory_map.descriptors().len() {
             let desc = &self.memory_map.descriptors()[desc_index];
 
-            if desc.r#type == MemoryType::Available
-                || !self.ignore_loader && desc.r#type == MemoryType::Loader
-                || !self.ignore_acpi && desc.r#type == MemoryType::AcpiData
-            {
+            if self.is_usable(desc) {
                 for addr in
                     (self.current_address.max(desc.phys_start)..desc.phys_end).step_by(PAGE_SIZE)
                 {
@@ -114,6 +119,55 @@ impl BitMapAllocator {
         // todo: page frame swap
         Err(FrameAllocatorError::NoMoreFreePages)
     }
+
+    /// Returns the address of `page_count` physically contiguous free pages, which is aligned to
+    /// `alignment` bytes. The alignment must be a power of two.
+    ///
+    /// The pages are searched linearly in all usable memory descriptors.
+    pub fn request_pages(
+        &mut self,
+        page_count: usize,
+        alignment: usize,
+    ) -> Result<PhysicalAddress, FrameAllocatorError> {
+        if page_count == 0 || !alignment.is_power_of_two() {
+            return Err(FrameAllocatorError::InvalidRequest(page_count));
+        }
+        let alignment = alignment.max(PAGE_SIZE);
+        let size = (page_count * PAGE_SIZE) as u64;
+
+        for desc_index in 0..self.memory_map.descriptors().len() {
+            let desc = self.memory_map.descriptors()[desc_index];
+            if !self.is_usable(&desc) {
+                continue;
+            }
+
+            let mut start = align_up(desc.phys_start, alignment);
+            while start + size <= desc.phys_end {
+                // continue after the last allocated frame of the run
+                let used = (start..start + size)
+                    .step_by(PAGE_SIZE)
+                    .map(|addr| Ok((addr, self.bit_map.get(addr / PAGE_SIZE as u64)?)))
+                    .find(|result| !matches!(result, Ok((_, false))))
+                    .transpose()?;
+
+                match used {
+                    Some((addr, _)) => start = align_up(addr + PAGE_SIZE as u64, alignment),
+                    None => {
+                        self.allocate_frames(start, page_count)?;
+                        return Ok(start);
+                    }
+                }
+            }
+        }
+
+        Err(FrameAllocatorError::NoMoreFreePages)
+    }
+
+    fn is_usable(&self, desc: &MemoryDescriptor) -> bool {
+        desc.r#type == MemoryType::Available
+            || !self.ignore_loader && desc.r#type == MemoryType::Loader
+            || !self.ignore_acpi && desc.r#type == MemoryType::AcpiData
+    }
 }
 
 impl BitMapAllocator {
@@ -144,13 +198,20 @@ impl BitMapAllocator {
         Ok(())
     }
 
-    /// Attempt to free a single allocated frame
+    /// Attempt to free a single allocated frame. A shared frame only drops a reference and is
+    /// freed by its last owner, see [`BitMapAllocator::share_frame`].
     pub fn free_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
         let index = address / PAGE_SIZE as u64;
         if !self.bit_map.get(index)? {
             return Err(FrameAllocatorError::OperationFailed(address));
         }
 
+        let shares = &mut self.shares[index as usize];
+        if *shares > 0 {
+            *shares -= 1;
+            return Ok(());
+        }
+
         self.bit_map.set(index, false)?;
         self.free_memory += PAGE_SIZE as u64;
         self.used_memory -= PAGE_SIZE as u64;
@@ -171,6 +232,32 @@ impl BitMapAllocator {
         Ok(())
     }
 
+    /// Adds another owner to the allocated frame, which is then only freed once all of its owners
+    /// have freed it.
+    pub fn share_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
+        let index = address / PAGE_SIZE as u64;
+        if !self.bit_map.get(index)? {
+            return Err(FrameAllocatorError::OperationFailed(address));
+        }
+
+        let shares = &mut self.shares[index as usize];
+        *shares = shares
+            .checked_add(1)
+            .ok_or(FrameAllocatorError::OperationFailed(address))?;
+
+        Ok(())
+    }
+
+    /// Returns the number of owners of the allocated frame.
+    pub fn references(&self, address: PhysicalAddress) -> Result<usize, FrameAllocatorError> {
+        let index = address / PAGE_SIZE as u64;
+        if !self.bit_map.get(index)? {
+            return Err(FrameAllocatorError::OperationFailed(address));
+        }
+
+        Ok(self.shares[index as usize] as usize + 1)
+    }
+
     /// Attempt to reserve a single free frame
     pub fn reserve_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
         let index = address / PAGE_SIZE as u64;
@@ -246,16 +333,20 @@ impl BitMapAllocator {
 }
 
 impl BitMapAllocator {
-    /// Update the bitmap buffer pointer. Mainly used to make the allocator available after
-    /// switching to a new paging scheme
+    /// Update the metadata pointer, which is the bitmap buffer followed by the share counts.
+    /// Mainly used to make the allocator available after switching to a new paging scheme
     ///
     /// # Safety
     /// Caller must guarantee that the new offset pointer is valid.
-    pub unsafe fn update_bit_map_ptr(&mut self, offset: u64) {
+    pub unsafe fn update_metadata_ptr(&mut self, offset: u64) {
         unsafe {
             let old = self.bit_map.ptr() as u64;
             // todo: handle case of buffer overflow
             self.bit_map.update_ptr((offset + old) as *mut u8);
+
+            let len = self.shares.len();
+            let shares = (offset + self.shares.as_ptr() as u64) as *mut u16;
+            self.shares = slice::from_raw_parts_mut(shares, len);
         }
     }
 
@@ -302,14 +393,25 @@ impl BitMapAllocator {
 }
 
 impl BitMapAllocator {
-    pub fn address(&mut self) -> u64 {
-        unsafe { self.bit_map.ptr() as u64 }
+    pub fn address(&self) -> u64 {
+        self.bit_map.address()
     }
 
-    pub fn pages(&mut self) -> usize {
-        self.bit_map.pages()
+    pub fn pages(&self) -> usize {
+        let (_, metadata_size) = metadata_layout(self.shares.len());
+        metadata_size.div_ceil(PAGE_SIZE)
     }
 }
+
+/// Returns the offset of the share counts within the metadata of `total_pages` frames and the
+/// size of the metadata in bytes.
+fn metadata_layout(total_pages: usize) -> (usize, usize) {
+    let shares_offset = total_pages.div_ceil(8).next_multiple_of(align_of::<u16>());
+    (
+        shares_offset,
+        shares_offset + total_pages * size_of::<u16>(),
+    )
+}
 /// Returns total amount of memory in bytes based on memory map.
 pub fn total_memory(mmap: &MemoryMap) -> u64 {
     mmap.descriptors().iter().map(|desc| desc.size()).sum()
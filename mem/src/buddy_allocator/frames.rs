@@ -0,0 +1,220 @@
+use core::{ptr, slice};
+
+use crate::PAGE_SIZE;
+
+use super::MAX_ORDER;
+
+/// Marks the end of a free list.
+const NONE: u32 = u32::MAX;
+
+/// Largest number of frames that can be indexed by the free lists.
+pub(super) const FRAME_COUNT_MAX: usize = NONE as usize;
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum FrameState {
+    /// Neither handed out nor the first frame of a free block. This includes frames which are
+    /// not backed by memory and frames within free blocks.
+    Unused = 0,
+    /// First frame of a free block, which is part of the free list of its order.
+    FreeHead = 1,
+    Used = 2,
+    Reserved = 3,
+}
+
+/// Metadata of a physical frame.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Frame {
+    /// Next block in the free list, only valid for the first frame of a free block.
+    next: u32,
+    /// Previous block in the free list, only valid for the first frame of a free block.
+    prev: u32,
+    /// The block spans 2^order frames, only valid for the first frame of a free block.
+    order: u8,
+    state: FrameState,
+    /// Number of additional owners of a used frame, which is only released once it is no longer
+    /// shared.
+    shares: u16,
+}
+
+/// Metadata of all frames of the physical address space with the free lists of each order, which
+/// are linked through the first frames of the free blocks.
+#[derive(Debug)]
+pub(super) struct FrameTable {
+    frames: &'static mut [Frame],
+    heads: [u32; MAX_ORDER + 1],
+}
+
+impl FrameTable {
+    /// Returns the number of bytes required for the metadata of `count` frames.
+    pub(super) fn size(count: usize) -> usize {
+        count * size_of::<Frame>()
+    }
+
+    /// Creates a table without free blocks, in which all frames are unused.
+    ///
+    /// # Safety
+    /// The caller must guarantee that the buffer is valid for [`FrameTable::size`] bytes, aligned
+    /// for the metadata and not used otherwise.
+    pub(super) unsafe fn new(buffer: *mut u8, count: usize) -> FrameTable {
+        let frames = buffer.cast::<Frame>();
+        unsafe {
+            // zeroed metadata is a valid unused frame
+            ptr::write_bytes(frames, 0, count);
+            FrameTable {
+                frames: slice::from_raw_parts_mut(frames, count),
+                heads: [NONE; MAX_ORDER + 1],
+            }
+        }
+    }
+}
+
+impl FrameTable {
+    /// Update the metadata pointer by adding an offset.
+    ///
+    /// # Safety
+    /// The caller must guarantee that the new pointer is valid.
+    pub(super) unsafe fn update_ptr(&mut self, offset: u64) {
+        let len = self.frames.len();
+        let ptr = (self.frames.as_mut_ptr() as u64 + offset) as *mut Frame;
+        self.frames = unsafe { slice::from_raw_parts_mut(ptr, len) };
+    }
+
+    /// Retrieve raw pointer to the metadata.
+    pub(super) fn ptr(&self) -> *const u8 {
+        self.frames.as_ptr().cast()
+    }
+
+    /// Number of pages occupied by the metadata.
+    pub(super) fn pages(&self) -> usize {
+        Self::size(self.frames.len()).div_ceil(PAGE_SIZE)
+    }
+
+    /// Number of frames of the physical address space.
+    pub(super) fn len(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl FrameTable {
+    pub(super) fn state(&self, index: usize) -> FrameState {
+        self.frames[index].state
+    }
+
+    pub(super) fn set_state(&mut self, index: usize, state: FrameState) {
+        self.frames[index].state = state;
+    }
+
+    pub(super) fn shares(&self, index: usize) -> u16 {
+        self.frames[index].shares
+    }
+
+    pub(super) fn set_shares(&mut self, index: usize, shares: u16) {
+        self.frames[index].shares = shares;
+    }
+
+    /// Whether a free block of the order starts at the index.
+    pub(super) fn is_free(&self, index: usize, order: usize) -> bool {
+        self.frames.get(index).is_some_and(|frame| {
+            frame.state == FrameState::FreeHead && frame.order as usize == order
+        })
+    }
+
+    /// Returns the order of the free block starting at the index.
+    pub(super) fn order(&self, index: usize) -> Option<usize> {
+        let frame = &self.frames[index];
+        (frame.state == FrameState::FreeHead).then_some(frame.order as usize)
+    }
+
+    /// Returns the first free block of the order.
+    pub(super) fn first(&self, order: usize) -> Option<usize> {
+        (self.heads[order] != NONE).then_some(self.heads[order] as usize)
+    }
+
+    /// Adds the block of the order starting at the index to its free list.
+    pub(super) fn push(&mut self, index: usize, order: usize) {
+        let head = self.heads[order];
+        if head != NONE {
+            self.frames[head as usize].prev = index as u32;
+        }
+
+        self.frames[index] = Frame {
+            next: head,
+            prev: NONE,
+            order: order as u8,
+            state: FrameState::FreeHead,
+            shares: 0,
+        };
+        self.heads[order] = index as u32;
+    }
+
+    /// Removes the free block starting at the index from its free list. The first frame of the
+    /// block is marked as unused.
+    pub(super) fn remove(&mut self, index: usize) {
+        let Frame {
+            next, prev, order, ..
+        } = self.frames[index];
+
+        match prev {
+            NONE => self.heads[order as usize] = next,
+            prev => self.frames[prev as usize].next = next,
+        }
+        if next != NONE {
+            self.frames[next as usize].prev = prev;
+        }
+
+        self.frames[index].state = FrameState::Unused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn table(count: usize) -> FrameTable {
+        let buffer = vec![0u64; FrameTable::size(count).div_ceil(8)].leak();
+        unsafe { FrameTable::new(buffer.as_mut_ptr().cast(), count) }
+    }
+
+    #[test]
+    fn links_free_lists() {
+        let mut table = table(16);
+        assert_eq!(table.first(2), None);
+
+        table.push(0, 2);
+        table.push(4, 2);
+        table.push(8, 2);
+        table.push(12, 1);
+        assert_eq!(table.first(2), Some(8));
+        assert_eq!(table.first(1), Some(12));
+        assert!(table.is_free(4, 2));
+        assert!(!table.is_free(4, 1));
+        assert_eq!(table.order(12), Some(1));
+        assert_eq!(table.order(13), None);
+
+        // removing from the middle and the front keeps the remaining blocks linked
+        table.remove(4);
+        assert_eq!(table.state(4), FrameState::Unused);
+        table.remove(8);
+        assert_eq!(table.first(2), Some(0));
+        table.remove(0);
+        assert_eq!(table.first(2), None);
+        assert_eq!(table.first(1), Some(12));
+    }
+
+    #[test]
+    fn resets_shares_of_free_blocks() {
+        let mut table = table(4);
+        table.set_state(1, FrameState::Used);
+        table.set_shares(1, 2);
+        assert_eq!(table.shares(1), 2);
+
+        table.push(1, 0);
+        assert_eq!(table.shares(1), 0);
+        assert_eq!(table.pages(), 1);
+        assert_eq!(table.len(), 4);
+    }
+}
@@ -0,0 +1,657 @@
+use crate::{
+    PAGE_SIZE, PAS_VIRTUAL_MAX, PhysicalAddress,
+    error::FrameAllocatorError,
+    map::{MemoryDescriptor, MemoryMap, MemoryType},
+};
+use frames::{FRAME_COUNT_MAX, FrameState, FrameTable};
+
+mod frames;
+
+/// Largest order of a block, which spans 2^order frames (1 GiB).
+pub const MAX_ORDER: usize = 18;
+
+/// Physical frame allocator based on the buddy system. Free memory is kept in blocks of 2^order
+/// frames, which are aligned to their size. Blocks are split to serve smaller requests and merged
+/// with their buddy once both are free again.
+///
+/// The metadata of each frame is stored in physical memory, which is taken from the memory map.
+#[derive(Debug)]
+pub struct BuddyAllocator {
+    memory_map: MemoryMap,
+    frames: FrameTable,
+    free_memory: u64,
+    used_memory: u64,
+    reserved_memory: u64,
+}
+
+impl BuddyAllocator {
+    /// Attempts to initialize a new buddy allocator with the given memory map. Only memory of the
+    /// available type is free, all other memory is reserved.
+    pub fn try_new(memory_map: MemoryMap) -> Result<BuddyAllocator, FrameAllocatorError> {
+        // the metadata is accessed by its physical address until the paging scheme is switched
+        unsafe { Self::try_new_at(memory_map, 0) }
+    }
+
+    /// Like [`BuddyAllocator::try_new`], but accesses the metadata at its physical address plus
+    /// the offset.
+    ///
+    /// # Safety
+    /// Caller must guarantee that the memory chosen for the metadata is valid at the offset.
+    unsafe fn try_new_at(
+        memory_map: MemoryMap,
+        offset: u64,
+    ) -> Result<BuddyAllocator, FrameAllocatorError> {
+        let frame_count = (memory_map.last_addr as usize).div_ceil(PAGE_SIZE);
+        if frame_count > FRAME_COUNT_MAX {
+            return Err(FrameAllocatorError::InvalidMemoryMap);
+        }
+        let table_size = FrameTable::size(frame_count);
+
+        // find memory region to store metadata in
+        let mem = memory_map
+            .descriptors()
+            .iter()
+            .filter(|mem| {
+                mem.phys_end < PAS_VIRTUAL_MAX
+                    && mem.r#type == MemoryType::Available
+                    && mem.size() >= table_size as u64
+            })
+            .min_by(|a, b| a.size().cmp(&b.size()))
+            .ok_or(FrameAllocatorError::InvalidMemoryMap)?;
+
+        let buffer = mem.phys_start.wrapping_add(offset) as *mut u8;
+        let frames = unsafe { FrameTable::new(buffer, frame_count) };
+
+        let mut instance = Self {
+            memory_map,
+            frames,
+            free_memory: 0,
+            used_memory: 0,
+            reserved_memory: 0,
+        };
+
+        let mmap = instance.memory_map;
+        for desc in mmap.descriptors() {
+            let (start, count) = instance.range(desc)?;
+            if desc.r#type == MemoryType::Available {
+                instance.release(start, count);
+                instance.free_memory += desc.num_pages * PAGE_SIZE as u64;
+            } else {
+                (start..start + count)
+                    .for_each(|index| instance.frames.set_state(index, FrameState::Reserved));
+                instance.reserved_memory += desc.num_pages * PAGE_SIZE as u64;
+            }
+        }
+
+        // reserve frames of metadata
+        instance.reserve_frames(mem.phys_start, instance.frames.pages())?;
+
+        Ok(instance)
+    }
+}
+
+impl BuddyAllocator {
+    /// Returns any available free page
+    pub fn request_page(&mut self) -> Result<PhysicalAddress, FrameAllocatorError> {
+        self.request_pages(1, PAGE_SIZE)
+    }
+
+    /// Returns the address of `page_count` physically contiguous free pages, which is aligned to
+    /// `alignment` bytes. The alignment must be a power of two.
+    ///
+    /// The pages are taken from the smallest sufficient block, of which the remaining pages are
+    /// kept free.
+    pub fn request_pages(
+        &mut self,
+        page_count: usize,
+        alignment: usize,
+    ) -> Result<PhysicalAddress, FrameAllocatorError> {
+        if page_count == 0 || !alignment.is_power_of_two() {
+            return Err(FrameAllocatorError::InvalidRequest(page_count));
+        }
+
+        let order = (page_count.next_power_of_two().trailing_zeros() as usize)
+            .max((alignment / PAGE_SIZE).max(1).trailing_zeros() as usize);
+        if order > MAX_ORDER {
+            return Err(FrameAllocatorError::InvalidRequest(page_count));
+        }
+
+        let (head, head_order) = (order..=MAX_ORDER)
+            .find_map(|order| self.frames.first(order).map(|head| (head, order)))
+            .ok_or(FrameAllocatorError::NoMoreFreePages)?;
+        self.split(head, head_order, head, order);
+
+        (head..head + page_count).for_each(|index| self.frames.set_state(index, FrameState::Used));
+        self.free_memory -= (page_count * PAGE_SIZE) as u64;
+        self.used_memory += (page_count * PAGE_SIZE) as u64;
+
+        // return the remainder of the block
+        self.release(head + page_count, (1 << order) - page_count);
+
+        Ok((head * PAGE_SIZE) as PhysicalAddress)
+    }
+}
+
+impl BuddyAllocator {
+    /// Attempt to allocate a single free frame
+    pub fn allocate_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
+        self.take(address, FrameState::Used)?;
+        self.free_memory -= PAGE_SIZE as u64;
+        self.used_memory += PAGE_SIZE as u64;
+
+        Ok(())
+    }
+
+    /// Attempt to allocate a series of free frames
+    pub fn allocate_frames(
+        &mut self,
+        start_address: PhysicalAddress,
+        page_count: usize,
+    ) -> Result<(), FrameAllocatorError> {
+        for i in 0..page_count {
+            self.allocate_frame(start_address + (i * PAGE_SIZE) as u64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Attempt to free a single allocated frame
+    pub fn free_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
+        self.free_frames(address, 1)
+    }
+
+    /// Attempt to free a series of allocated frames. Nothing is freed if any of the frames is not
+    /// allocated. Shared frames only drop a reference and are released by their last owner, see
+    /// [`BuddyAllocator::share_frame`].
+    pub fn free_frames(
+        &mut self,
+        start_address: PhysicalAddress,
+        page_count: usize,
+    ) -> Result<(), FrameAllocatorError> {
+        let start = self.check(start_address, page_count, FrameState::Used)?;
+        let end = start + page_count;
+
+        // release the runs of frames in between the shared ones
+        let mut run = start;
+        for index in start..end {
+            let shares = self.frames.shares(index);
+            if shares > 0 {
+                self.frames.set_shares(index, shares - 1);
+                self.release_used(run, index - run);
+                run = index + 1;
+            }
+        }
+        self.release_used(run, end - run);
+
+        Ok(())
+    }
+
+    /// Adds another owner to the allocated frame, which is then only released once all of its
+    /// owners have freed it.
+    pub fn share_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
+        let index = self.check(address, 1, FrameState::Used)?;
+        let shares = self.frames.shares(index);
+        if shares == u16::MAX {
+            return Err(FrameAllocatorError::OperationFailed(address));
+        }
+
+        self.frames.set_shares(index, shares + 1);
+        Ok(())
+    }
+
+    /// Returns the number of owners of the allocated frame.
+    pub fn references(&self, address: PhysicalAddress) -> Result<usize, FrameAllocatorError> {
+        let index = self.check(address, 1, FrameState::Used)?;
+        Ok(self.frames.shares(index) as usize + 1)
+    }
+
+    /// Attempt to reserve a single free frame
+    pub fn reserve_frame(&mut self, address: PhysicalAddress) -> Result<(), FrameAllocatorError> {
+        self.take(address, FrameState::Reserved)?;
+        self.free_memory -= PAGE_SIZE as u64;
+        self.reserved_memory += PAGE_SIZE as u64;
+
+        Ok(())
+    }
+
+    /// Attempt to reserve a series of free frames
+    pub fn reserve_frames(
+        &mut self,
+        start_address: PhysicalAddress,
+        page_count: usize,
+    ) -> Result<(), FrameAllocatorError> {
+        for i in 0..page_count {
+            self.reserve_frame(start_address + (i * PAGE_SIZE) as u64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Attempt to free a single reserved frame
+    pub fn free_reserved_frame(
+        &mut self,
+        address: PhysicalAddress,
+    ) -> Result<(), FrameAllocatorError> {
+        self.free_reserved_frames(address, 1)
+    }
+
+    /// Attempt to free a series of reserved frames. Nothing is freed if any of the frames is not
+    /// reserved.
+    pub fn free_reserved_frames(
+        &mut self,
+        start_address: PhysicalAddress,
+        page_count: usize,
+    ) -> Result<(), FrameAllocatorError> {
+        let start = self.check(start_address, page_count, FrameState::Reserved)?;
+
+        self.release(start, page_count);
+        self.free_memory += (page_count * PAGE_SIZE) as u64;
+        self.reserved_memory -= (page_count * PAGE_SIZE) as u64;
+
+        Ok(())
+    }
+}
+
+impl BuddyAllocator {
+    /// Returns the amount of free memory in bytes
+    pub fn free_memory(&self) -> u64 {
+        self.free_memory
+    }
+
+    /// Returns the amount of used memory in bytes
+    pub fn used_memory(&self) -> u64 {
+        self.used_memory
+    }
+
+    /// Returns the amount of reserved memory in bytes
+    pub fn reserved_memory(&self) -> u64 {
+        self.reserved_memory
+    }
+}
+
+impl BuddyAllocator {
+    /// Update the metadata pointer. Mainly used to make the allocator available after switching
+    /// to a new paging scheme
+    ///
+    /// # Safety
+    /// Caller must guarantee that the new offset pointer is valid.
+    pub unsafe fn update_metadata_ptr(&mut self, offset: u64) {
+        unsafe { self.frames.update_ptr(offset) };
+    }
+
+    /// Update the memory map descriptor pointer. Mainly used to make the allocator avaiable after
+    /// switching to a new paging scheme
+    ///
+    /// # Safety
+    /// Caller must guarantee that the new offset pointer is valid.
+    pub unsafe fn update_memory_map_ptr(&mut self, offset: u64) {
+        let old = self.memory_map.descriptors;
+        self.memory_map.descriptors = (offset + old as u64) as *mut MemoryDescriptor;
+    }
+
+    /// Make the Loader and BootService memory types available.
+    ///
+    /// # Safety
+    /// Caller must ensure that this function can be called. Must only be called from the kernel.
+    pub unsafe fn use_loader_memory(&mut self) -> Result<(), FrameAllocatorError> {
+        self.use_memory(MemoryType::Loader)
+    }
+
+    /// Make the ACPI Tables memory types available.
+    ///
+    /// # Safety
+    /// Caller must ensure that this function can be called.
+    pub unsafe fn use_acpi_memory(&mut self) -> Result<(), FrameAllocatorError> {
+        self.use_memory(MemoryType::AcpiData)
+    }
+
+    fn use_memory(&mut self, r#type: MemoryType) -> Result<(), FrameAllocatorError> {
+        let mmap = self.memory_map;
+        mmap.descriptors()
+            .iter()
+            .filter(|desc| desc.r#type == r#type)
+            .try_for_each(|desc| {
+                self.free_reserved_frames(desc.phys_start, desc.num_pages as usize)
+            })
+    }
+}
+
+impl BuddyAllocator {
+    /// Physical address of the metadata.
+    pub fn address(&self) -> u64 {
+        self.frames.ptr() as u64
+    }
+
+    /// Number of pages occupied by the metadata.
+    pub fn pages(&self) -> usize {
+        self.frames.pages()
+    }
+}
+
+impl BuddyAllocator {
+    /// Returns the frame index of the first page of the descriptor and its number of pages.
+    fn range(&self, desc: &MemoryDescriptor) -> Result<(usize, usize), FrameAllocatorError> {
+        let start = desc.phys_start as usize / PAGE_SIZE;
+        let count = desc.num_pages as usize;
+        match start.checked_add(count) {
+            Some(end) if end <= self.frames.len() => Ok((start, count)),
+            _ => Err(FrameAllocatorError::InvalidMemoryMap),
+        }
+    }
+
+    /// Checks that all `page_count` frames starting at the address are in the state. Returns the
+    /// index of the first frame.
+    fn check(
+        &self,
+        address: PhysicalAddress,
+        page_count: usize,
+        state: FrameState,
+    ) -> Result<usize, FrameAllocatorError> {
+        let start = address as usize / PAGE_SIZE;
+        (start..start + page_count).try_for_each(|index| {
+            let address = (index * PAGE_SIZE) as PhysicalAddress;
+            if index >= self.frames.len() {
+                Err(FrameAllocatorError::InvalidFrame(address))
+            } else if self.frames.state(index) != state {
+                Err(FrameAllocatorError::OperationFailed(address))
+            } else {
+                Ok(())
+            }
+        })?;
+        Ok(start)
+    }
+
+    /// Takes the free frame at the address out of its free block and puts it into the state.
+    fn take(
+        &mut self,
+        address: PhysicalAddress,
+        state: FrameState,
+    ) -> Result<(), FrameAllocatorError> {
+        let index = address as usize / PAGE_SIZE;
+        if index >= self.frames.len() {
+            return Err(FrameAllocatorError::InvalidFrame(address));
+        }
+
+        // the free block containing the frame starts at the frame aligned to the block size
+        let (head, order) = (0..=MAX_ORDER)
+            .map(|order| index & !((1 << order) - 1))
+            .find_map(|head| {
+                self.frames
+                    .order(head)
+                    .filter(|order| index < head + (1 << order))
+                    .map(|order| (head, order))
+            })
+            .ok_or(FrameAllocatorError::OperationFailed(address))?;
+
+        self.split(head, order, index, 0);
+        self.frames.set_state(index, state);
+        Ok(())
+    }
+
+    /// Removes the free block of `head_order` starting at `head` and splits it, until the block
+    /// of the order starting at `index` remains. The other halves are kept free.
+    fn split(&mut self, head: usize, head_order: usize, index: usize, order: usize) {
+        self.frames.remove(head);
+
+        let (mut head, mut head_order) = (head, head_order);
+        while head_order > order {
+            head_order -= 1;
+            let half = 1 << head_order;
+            if index >= head + half {
+                self.frames.push(head, head_order);
+                head += half;
+            } else {
+                self.frames.push(head + half, head_order);
+            }
+        }
+    }
+
+    /// Adds `count` frames starting at the index to the free lists, in the largest aligned blocks
+    /// possible.
+    fn release(&mut self, mut start: usize, mut count: usize) {
+        (start..start + count).for_each(|index| self.frames.set_state(index, FrameState::Unused));
+
+        while count > 0 {
+            let order = (start.trailing_zeros() as usize)
+                .min(count.ilog2() as usize)
+                .min(MAX_ORDER);
+            self.merge(start, order);
+            start += 1 << order;
+            count -= 1 << order;
+        }
+    }
+
+    /// Releases `count` used frames starting at the index.
+    fn release_used(&mut self, start: usize, count: usize) {
+        self.release(start, count);
+        self.free_memory += (count * PAGE_SIZE) as u64;
+        self.used_memory -= (count * PAGE_SIZE) as u64;
+    }
+
+    /// Adds the block of the order starting at the index to the free lists, after merging it with
+    /// its buddies as long as they are free.
+    fn merge(&mut self, mut index: usize, mut order: usize) {
+        while order < MAX_ORDER {
+            let buddy = index ^ (1 << order);
+            if !self.frames.is_free(buddy, order) {
+                break;
+            }
+
+            self.frames.remove(buddy);
+            index = index.min(buddy);
+            order += 1;
+        }
+
+        self.frames.push(index, order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+
+    /// Memory of the test allocator, with a hole between 1 MiB and 2 MiB which is not described by
+    /// the memory map. The metadata of 1024 frames occupies 3 pages, so it is placed in the
+    /// smallest sufficient region at 2 MiB.
+    const DESCRIPTORS: [(PhysicalAddress, PhysicalAddress, MemoryType); 4] = [
+        (0x0, 0x1_0000, MemoryType::Reserved),
+        (0x1_0000, 0x10_0000, MemoryType::Available),
+        (0x20_0000, 0x20_4000, MemoryType::Available),
+        (0x20_4000, 0x40_0000, MemoryType::Available),
+    ];
+    const METADATA: PhysicalAddress = 0x20_0000;
+    const FREE_PAGES: usize = 0xf0 + 0x4 + 0x1fc - 3;
+
+    /// Creates an allocator for the test memory, of which the metadata is stored on the heap.
+    fn allocator() -> BuddyAllocator {
+        let descriptors: Vec<MemoryDescriptor> = DESCRIPTORS
+            .iter()
+            .map(|&(phys_start, phys_end, r#type)| MemoryDescriptor {
+                phys_start,
+                phys_end,
+                num_pages: (phys_end - phys_start) / PAGE_SIZE as u64,
+                r#type,
+            })
+            .collect();
+        let memory_map = MemoryMap {
+            descriptors_len: descriptors.len() as u64,
+            descriptors: descriptors.leak().as_mut_ptr(),
+            first_addr: 0x0,
+            first_available_addr: 0x1_0000,
+            last_addr: 0x40_0000,
+            last_available_addr: 0x40_0000,
+        };
+
+        let buffer = vec![0u64; FrameTable::size(0x400).div_ceil(8)].leak();
+        let offset = (buffer.as_mut_ptr() as u64).wrapping_sub(METADATA);
+        unsafe { BuddyAllocator::try_new_at(memory_map, offset) }.unwrap()
+    }
+
+    /// Returns the first frame and the order of all free blocks.
+    fn blocks(allocator: &BuddyAllocator) -> Vec<(usize, usize)> {
+        (0..allocator.frames.len())
+            .filter_map(|index| allocator.frames.order(index).map(|order| (index, order)))
+            .collect()
+    }
+
+    fn free_pages(allocator: &BuddyAllocator) -> usize {
+        allocator.free_memory() as usize / PAGE_SIZE
+    }
+
+    #[test]
+    fn initializes_from_memory_map_with_holes() {
+        let mut allocator = allocator();
+        assert_eq!(free_pages(&allocator), FREE_PAGES);
+        assert_eq!(allocator.used_memory(), 0);
+        assert_eq!(allocator.reserved_memory(), (0x10 + 3) * PAGE_SIZE as u64);
+        assert_eq!(allocator.pages(), 3);
+
+        // the free blocks cover exactly the free frames
+        let pages: usize = blocks(&allocator).iter().map(|(_, order)| 1 << order).sum();
+        assert_eq!(pages, FREE_PAGES);
+
+        // neither the hole, reserved memory nor the metadata can be allocated
+        for address in [0x10_0000, 0x1f_f000, 0x0, METADATA, METADATA + 0x2000] {
+            assert!(matches!(
+                allocator.allocate_frame(address),
+                Err(FrameAllocatorError::OperationFailed(_))
+            ));
+        }
+        assert!(matches!(
+            allocator.allocate_frame(0x40_0000),
+            Err(FrameAllocatorError::InvalidFrame(0x40_0000))
+        ));
+        assert!(allocator.allocate_frame(METADATA + 0x3000).is_ok());
+    }
+
+    #[test]
+    fn allocates_aligned_blocks() {
+        let mut allocator = allocator();
+
+        let address = allocator.request_pages(3, PAGE_SIZE).unwrap();
+        assert_eq!(address % (4 * PAGE_SIZE) as u64, 0);
+        // the remainder of the block stays free
+        assert!(
+            allocator
+                .allocate_frame(address + 3 * PAGE_SIZE as u64)
+                .is_ok()
+        );
+        allocator
+            .free_frame(address + 3 * PAGE_SIZE as u64)
+            .unwrap();
+
+        let address = allocator.request_pages(1, 0x4_0000).unwrap();
+        assert_eq!(address % 0x4_0000, 0);
+
+        let address = allocator.request_pages(0x40, PAGE_SIZE).unwrap();
+        assert_eq!(address % 0x4_0000, 0);
+        assert_eq!(allocator.used_memory(), (3 + 1 + 0x40) * PAGE_SIZE as u64);
+        assert_eq!(free_pages(&allocator), FREE_PAGES - 3 - 1 - 0x40);
+
+        assert!(matches!(
+            allocator.request_pages(0, PAGE_SIZE),
+            Err(FrameAllocatorError::InvalidRequest(0))
+        ));
+        assert!(matches!(
+            allocator.request_pages(1, 0x3000),
+            Err(FrameAllocatorError::InvalidRequest(1))
+        ));
+    }
+
+    #[test]
+    fn splits_and_coalesces_blocks() {
+        let mut allocator = allocator();
+        let initial = blocks(&allocator);
+
+        let mut addresses: Vec<PhysicalAddress> =
+            (0..5).map(|_| allocator.request_page().unwrap()).collect();
+        addresses.push(allocator.request_pages(7, PAGE_SIZE).unwrap());
+        assert_ne!(blocks(&allocator), initial);
+
+        // freeing in a different order merges the buddies back into the initial blocks
+        for &address in addresses[..5].iter().rev() {
+            allocator.free_frame(address).unwrap();
+        }
+        allocator.free_frames(addresses[5], 7).unwrap();
+        assert_eq!(blocks(&allocator), initial);
+        assert_eq!(free_pages(&allocator), FREE_PAGES);
+        assert_eq!(allocator.used_memory(), 0);
+    }
+
+    #[test]
+    fn rejects_double_free() {
+        let mut allocator = allocator();
+        let address = allocator.request_pages(2, PAGE_SIZE).unwrap();
+        allocator.free_frame(address).unwrap();
+        assert!(matches!(
+            allocator.free_frame(address),
+            Err(FrameAllocatorError::OperationFailed(_))
+        ));
+
+        // nothing is freed if one of the frames is not allocated
+        assert!(allocator.free_frames(address, 2).is_err());
+        assert_eq!(allocator.used_memory(), PAGE_SIZE as u64);
+        assert_eq!(allocator.references(address + PAGE_SIZE as u64).unwrap(), 1);
+
+        assert!(allocator.free_reserved_frame(METADATA).is_ok());
+        assert!(allocator.free_reserved_frame(METADATA).is_err());
+    }
+
+    #[test]
+    fn runs_out_of_memory() {
+        let mut allocator = allocator();
+
+        // the largest free block spans 1 MiB, although there is more free memory
+        assert!(allocator.request_pages(0x100, PAGE_SIZE).is_ok());
+        assert!(matches!(
+            allocator.request_pages(0x100, PAGE_SIZE),
+            Err(FrameAllocatorError::NoMoreFreePages)
+        ));
+        assert!(matches!(
+            allocator.request_pages((1 << MAX_ORDER) + 1, PAGE_SIZE),
+            Err(FrameAllocatorError::InvalidRequest(_))
+        ));
+
+        let mut addresses = Vec::new();
+        while let Ok(address) = allocator.request_page() {
+            assert!(DESCRIPTORS.iter().any(|&(start, end, r#type)| {
+                r#type == MemoryType::Available && (start..end).contains(&address)
+            }));
+            addresses.push(address);
+        }
+        assert_eq!(addresses.len(), FREE_PAGES - 0x100);
+        assert_eq!(allocator.free_memory(), 0);
+        assert!(matches!(
+            allocator.request_page(),
+            Err(FrameAllocatorError::NoMoreFreePages)
+        ));
+
+        allocator.free_frame(addresses[0]).unwrap();
+        assert_eq!(allocator.request_page().unwrap(), addresses[0]);
+    }
+
+    #[test]
+    fn releases_shared_frames_with_last_owner() {
+        let mut allocator = allocator();
+        let address = allocator.request_pages(3, PAGE_SIZE).unwrap();
+        let shared = address + PAGE_SIZE as u64;
+        allocator.share_frame(shared).unwrap();
+        allocator.share_frame(shared).unwrap();
+        assert_eq!(allocator.references(shared).unwrap(), 3);
+
+        // only the frames which are not shared are released
+        allocator.free_frames(address, 3).unwrap();
+        assert_eq!(allocator.used_memory(), PAGE_SIZE as u64);
+        assert_eq!(allocator.references(shared).unwrap(), 2);
+        assert!(allocator.references(address).is_err());
+
+        allocator.free_frame(shared).unwrap();
+        allocator.free_frame(shared).unwrap();
+        assert!(allocator.references(shared).is_err());
+        assert!(allocator.free_frame(shared).is_err());
+        assert!(allocator.share_frame(shared).is_err());
+        assert_eq!(free_pages(&allocator), FREE_PAGES);
+    }
+}
@@ -1,13 +1,21 @@
-use crate::PhysicalAddress;
+use crate::{PhysicalAddress, VirtualAddress};
 
 #[derive(Debug, thiserror::Error)]
 pub enum FrameAllocatorError {
     #[error("Invalid index to bitmap")]
     InvalidBitMapIndex,
+    #[error("Frame with the address {0} is outside of the memory map")]
+    InvalidFrame(PhysicalAddress),
     #[error("Invalid memory map")]
     InvalidMemoryMap,
+    #[error("Invalid request of {0} pages")]
+    InvalidRequest(usize),
     #[error("No more free pages available")]
     NoMoreFreePages,
+    #[error("Address {0:#x} is not aligned to the page size")]
+    UnalignedAddress(VirtualAddress),
+    #[error("Address {0:#x} is already mapped by a page of a different size")]
+    MappingConflict(VirtualAddress),
     #[error("Operation failed - frame with the address {0} already allocated/reserved or free")]
     OperationFailed(PhysicalAddress),
 }
@@ -22,3 +30,20 @@ pub enum HeapError {
     #[error("Invalid heap block size {0}")]
     InvalidBlockSize(usize),
 }
+
+#[cfg(feature = "region")]
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RegionError {
+    #[error("Invalid region length {0:#x}")]
+    InvalidLength(u64),
+    #[error("No free gap of {0:#x} bytes")]
+    NoSpace(u64),
+    #[error("Range starting at {0:#x} is outside of the managed range")]
+    OutOfRange(VirtualAddress),
+    #[error("Range starting at {0:#x} is already allocated")]
+    Occupied(VirtualAddress),
+    #[error("No region has been allocated at {0:#x}")]
+    NotAllocated(VirtualAddress),
+    #[error("Region at {0:#x} cannot be merged with the following one")]
+    NotMergeable(VirtualAddress),
+}
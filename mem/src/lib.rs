@@ -1,17 +1,35 @@
 #![no_std]
 
+#[cfg(any(test, feature = "region"))]
+extern crate alloc;
+
+#[cfg(feature = "bitmap")]
 pub mod bitmap_allocator;
+pub mod buddy_allocator;
 pub mod error;
 
 #[cfg(feature = "alloc")]
 pub mod heap;
 pub mod map;
 pub mod paging;
+#[cfg(feature = "region")]
+pub mod region;
+
+/// Physical frame allocator selected at build time.
+#[cfg(feature = "bitmap")]
+pub type FrameAllocator = bitmap_allocator::BitMapAllocator;
+/// Physical frame allocator selected at build time.
+#[cfg(not(feature = "bitmap"))]
+pub type FrameAllocator = buddy_allocator::BuddyAllocator;
 
 pub type PhysicalAddress = u64;
 pub type VirtualAddress = u64;
 
 pub const PAGE_SIZE: usize = 0x1000;
+/// Size of a page mapped by a level 2 page table entry
+pub const LARGE_PAGE_SIZE: usize = 0x20_0000; // 2 MiB
+/// Size of a page mapped by a level 3 page table entry
+pub const HUGE_PAGE_SIZE: usize = 0x4000_0000; // 1 GiB
 
 /// Size of initial kernel stack
 pub const KERNEL_STACK_SIZE: usize = 1024 * 16 * 4; // 64 KB
@@ -35,6 +53,16 @@ pub const KHEAP_PAGE_COUNT_MAX: usize = 0x180;
 pub const VMM_VIRTUAL: VirtualAddress = 0xffff_ffff_d000_0000;
 /// Number of pages used by the virtual memory manager
 pub const VMM_PAGE_COUNT: usize = 0x100;
+/// Virtual address of the local APIC registers, which are mapped to the last page of the virtual
+/// memory manager segment
+pub const LAPIC_VIRTUAL: VirtualAddress = VMM_VIRTUAL + ((VMM_PAGE_COUNT - 1) * PAGE_SIZE) as u64;
+/// End of the lower half (exclusive), which is private to each process and accessible by user
+/// code.
+pub const USER_VIRTUAL_MAX: VirtualAddress = 0x0000_8000_0000_0000;
+/// Size of the stack of a user thread
+pub const USER_STACK_SIZE: usize = 1024 * 16 * 4; // 64 KB
+/// Virtual top address of the user stack, followed by an unmapped page
+pub const USER_STACK_TOP: VirtualAddress = USER_VIRTUAL_MAX - PAGE_SIZE as u64;
 
 /// Aligns a given number up to the specified alignment.
 pub const fn align_up(number: u64, align: usize) -> u64 {
@@ -55,4 +55,6 @@ pub enum MemoryType {
     AcpiData = 5,
     /// loader code,data
     Loader = 6,
+    /// initial ramdisk archive
+    Initrd = 7,
 }
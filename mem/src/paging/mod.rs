@@ -1,5 +1,7 @@
 use bitflags::bitflags;
 
+use crate::{HUGE_PAGE_SIZE, LARGE_PAGE_SIZE, PAGE_SIZE};
+
 pub mod index;
 pub mod ptm;
 
@@ -30,6 +32,10 @@ bitflags! {
         ///
         /// For Page Table Entry: Global: Tells the processor not to invalidate the TLB entry corresponding to the page upon a MOV to CR3 instruction.
         const GLOBAL_AVL        = 1 << 8;
+        /// Available for use: Marks a non-present Page Table Entry, whose page has been reserved by [`ptm::PageTableMappings::reserve_memory`] and is backed on first access.
+        const LAZY = 1 << 9;
+        /// Available for use: Marks a present, read-only Page Table Entry of a writable page, whose frame is shared with another address space and copied on the first write, see [`ptm::PageTableMappings::share_memory`].
+        const COPY_ON_WRITE = 1 << 10;
         const AVAILABLE_MASK = 0b111 << 9;
         /// For Page Directory (Pointer) Entry / PML4: Available for use
         ///
@@ -52,6 +58,28 @@ impl PageEntryFlags {
     }
 }
 
+/// Size of the memory mapped by a single page table entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PageSize {
+    /// 4 KiB page, mapped by a level 1 entry
+    Small,
+    /// 2 MiB page, mapped by a level 2 entry
+    Large,
+    /// 1 GiB page, mapped by a level 3 entry. Requires CPU support.
+    Huge,
+}
+
+impl PageSize {
+    /// Size of the page in bytes
+    pub const fn bytes(self) -> u64 {
+        match self {
+            PageSize::Small => PAGE_SIZE as u64,
+            PageSize::Large => LARGE_PAGE_SIZE as u64,
+            PageSize::Huge => HUGE_PAGE_SIZE as u64,
+        }
+    }
+}
+
 /// Page Directory or Page Table
 #[derive(Copy, Clone, Debug)]
 #[repr(transparent)]
@@ -82,7 +110,13 @@ impl PageEntry {
         self.0 & 0x000f_ffff_ffff_f000
     }
 
-    /// Get address of page entry
+    /// Whether the entry of a level 2 or 3 table maps a page instead of pointing to the next table.
+    pub fn is_huge(&self) -> bool {
+        self.flags()
+            .contains(PageEntryFlags::PRESENT | PageEntryFlags::PAT_PAGE_SIZE)
+    }
+
+    /// Get flags of page entry
     pub fn flags(&self) -> PageEntryFlags {
         PageEntryFlags::from_bits_truncate(self.0 & 0xfff) // Mask to get only the lower 12 bits for flags
     }
@@ -1,15 +1,16 @@
 use core::{arch::asm, ptr::NonNull};
 
 use crate::{
-    PhysicalAddress, VirtualAddress, bitmap_allocator::BitMapAllocator, error::FrameAllocatorError,
+    FrameAllocator, PAGE_SIZE, PhysicalAddress, VirtualAddress, align_down,
+    error::FrameAllocatorError,
 };
 
-use super::{PageEntryFlags, PageTable, index::PageMapIndexer};
+use super::{PageEntry, PageEntryFlags, PageSize, PageTable, index::PageMapIndexer};
 
 /// Manages Page Table Mappings
 #[derive(Debug)]
 pub struct PageTableManager {
-    frame_allocator: BitMapAllocator,
+    frame_allocator: FrameAllocator,
     mappings: PageTableMappings,
 }
 
@@ -17,7 +18,7 @@ impl PageTableManager {
     /// Create a new page table manager instance. By default, a virtual `offset` of 0 is used. This can be changed manually using [`PageTableMappings::update_offset()`].
     pub fn new(
         pml4: NonNull<PageTable>,
-        frame_allocator: BitMapAllocator,
+        frame_allocator: FrameAllocator,
         nx: bool,
     ) -> PageTableManager {
         PageTableManager {
@@ -29,7 +30,7 @@ impl PageTableManager {
 
 impl PageTableManager {
     /// Get a mutable reference of the physical page frame allocator.
-    pub fn pmm(&mut self) -> &mut BitMapAllocator {
+    pub fn pmm(&mut self) -> &mut FrameAllocator {
         &mut self.frame_allocator
     }
 
@@ -43,7 +44,7 @@ impl PageTableManager {
         &self.mappings
     }
     /// Get a mutable refernce to the physical frame allocator and page table mappings.
-    pub fn inner(&mut self) -> (&mut PageTableMappings, &mut BitMapAllocator) {
+    pub fn inner(&mut self) -> (&mut PageTableMappings, &mut FrameAllocator) {
         (&mut self.mappings, &mut self.frame_allocator)
     }
 
@@ -81,6 +82,19 @@ impl PageTableManager {
         let (mappings, pmm) = self.inner();
         mappings.map_memory(virtual_address, physical_address, flags, pmm)
     }
+
+    /// Map a page of the given size at the virtual address to the physical address. See
+    /// [`PageTableMappings::map_page()`].
+    pub fn map_page(
+        &mut self,
+        virtual_address: VirtualAddress,
+        physical_address: PhysicalAddress,
+        flags: PageEntryFlags,
+        size: PageSize,
+    ) -> Result<(), FrameAllocatorError> {
+        let (mappings, pmm) = self.inner();
+        mappings.map_page(virtual_address, physical_address, flags, size, pmm)
+    }
 }
 
 /// Mutable collection of page table entries
@@ -160,7 +174,7 @@ impl PageTableMappings {
     /// Furthermore, if "invalidated" the mapping is also activated.
     pub unsafe fn clean(
         &mut self,
-        pmm: &mut BitMapAllocator,
+        pmm: &mut FrameAllocator,
         invalidate: bool,
     ) -> Result<(), FrameAllocatorError> {
         let pml4 = unsafe { self.pml4_virtual().as_mut() };
@@ -176,11 +190,13 @@ impl PageTableMappings {
                         .ok_or(FrameAllocatorError::OperationFailed(entry.address()))?
                 };
 
-                // iterate over each level 3 entry
+                // iterate over each level 3 entry, huge pages have no tables to free
                 level3
                     .entries
                     .iter_mut()
-                    .filter(|entry| entry.flags().contains(PageEntryFlags::PRESENT))
+                    .filter(|entry| {
+                        entry.flags().contains(PageEntryFlags::PRESENT) && !entry.is_huge()
+                    })
                     .try_for_each(|entry| -> Result<(), FrameAllocatorError> {
                         let level2 = unsafe {
                             ((entry.address() + offset) as *mut PageTable)
@@ -188,11 +204,14 @@ impl PageTableMappings {
                                 .ok_or(FrameAllocatorError::OperationFailed(entry.address()))?
                         };
 
-                        // iterate over each level 2 entry
+                        // iterate over each level 2 entry, large pages have no tables to free
                         level2
                             .entries
                             .iter_mut()
-                            .filter(|entry| entry.flags().contains(PageEntryFlags::PRESENT))
+                            .filter(|entry| {
+                                entry.flags().contains(PageEntryFlags::PRESENT)
+                                    && !entry.is_huge()
+                            })
                             .try_for_each(|entry|
                                 // free level 1 table frame
                                 pmm.free_frame(entry.address())
@@ -227,66 +246,195 @@ impl PageTableMappings {
         virtual_address: VirtualAddress,
         physical_address: PhysicalAddress,
         flags: PageEntryFlags,
-        pmm: &mut BitMapAllocator,
+        pmm: &mut FrameAllocator,
     ) -> Result<(), FrameAllocatorError> {
-        let indexer = PageMapIndexer::new(virtual_address);
-        let pml4 = self.pml4_virtual();
-        let user = flags.contains(PageEntryFlags::USER_SUPER);
-
-        // Map Level 3
-        let page_map_level3 = self.get_or_create_next_table(pml4, indexer.pdp_i(), pmm, user)?;
-        // Map Level 2
-        let page_map_level2 =
-            self.get_or_create_next_table(page_map_level3, indexer.pd_i(), pmm, user)?;
-        // Map Level 1
-        let mut page_map_level1 =
-            self.get_or_create_next_table(page_map_level2, indexer.pt_i(), pmm, user)?;
-
-        let page_entry = &mut unsafe { page_map_level1.as_mut() }.entries[indexer.p_i() as usize];
+        self.map(
+            virtual_address,
+            physical_address,
+            flags,
+            PageSize::Small,
+            pmm,
+        )
+    }
 
-        page_entry.set_address(physical_address);
-        page_entry.set_flags(flags);
+    /// Map a page of the given size at the virtual address to the physical address. Both
+    /// addresses must be aligned to the page size.
+    ///
+    /// Note: [`PageSize::Huge`] pages must only be used if the CPU supports them.
+    pub fn map_page(
+        &mut self,
+        virtual_address: VirtualAddress,
+        physical_address: PhysicalAddress,
+        flags: PageEntryFlags,
+        size: PageSize,
+        pmm: &mut FrameAllocator,
+    ) -> Result<(), FrameAllocatorError> {
+        if !virtual_address.is_multiple_of(size.bytes())
+            || !physical_address.is_multiple_of(size.bytes())
+        {
+            return Err(FrameAllocatorError::UnalignedAddress(virtual_address));
+        }
 
-        Ok(())
+        self.map(virtual_address, physical_address, flags, size, pmm)
     }
 
     /// Remove the mapping for given virtual address. Returns the physical address the virtual address previously pointed to.
+    ///
+    /// Note: Pages larger than [`PageSize::Small`] are unmapped as a whole, the start address of
+    /// the page is returned.
     pub fn unmap_memory(&mut self, virtual_memory: VirtualAddress) -> Option<PhysicalAddress> {
-        let indexer = PageMapIndexer::new(virtual_memory);
-        let page_map_level4 = self.pml4_virtual();
-        // Map Level 3
-        let page_map_level3 = self.get_next_table(page_map_level4, indexer.pdp_i())?;
-        // Map Level 2
-        let page_map_level2 = self.get_next_table(page_map_level3, indexer.pd_i())?;
-        // Map Level 1
-        let mut page_map_level1 = self.get_next_table(page_map_level2, indexer.pt_i())?;
+        self.unmap_page(virtual_memory)
+            .map(|(physical_address, _)| physical_address)
+    }
 
-        let page_entry = &mut unsafe { page_map_level1.as_mut() }.entries[indexer.p_i() as usize];
-        let physical_address = page_entry.address();
+    /// Remove the page mapping the given virtual address. Returns the physical start address and
+    /// the size of the page.
+    ///
+    /// Note: The reservation of a page, which has not been backed yet, is removed as well.
+    pub fn unmap_page(
+        &mut self,
+        virtual_memory: VirtualAddress,
+    ) -> Option<(PhysicalAddress, PageSize)> {
+        let Some((mut page_entry, size)) = self.leaf_entry(virtual_memory) else {
+            if let Some(mut page_entry) = self.reserved_entry(virtual_memory) {
+                unsafe { page_entry.as_mut() }.set_flags(PageEntryFlags::empty());
+            }
+            return None;
+        };
+        let page_entry = unsafe { page_entry.as_mut() };
+        let physical_address = page_entry.address() & !(size.bytes() - 1);
 
         page_entry.set_address(0);
         page_entry.set_flags(PageEntryFlags::empty());
 
-        unsafe { Self::invalidate_tlb_entry(physical_address) };
+        unsafe { Self::invalidate_tlb_entry(virtual_memory & !(size.bytes() - 1)) };
 
-        Some(physical_address)
+        Some((physical_address, size))
     }
 
-    /// Retrieves the physical address of the provided virtual address
+    /// Retrieves the physical address of the page containing the provided virtual address
     pub fn get(&self, virtual_memory: VirtualAddress) -> Option<NonNull<u8>> {
-        let indexer = PageMapIndexer::new(virtual_memory);
-        let page_map_level4 = self.pml4_virtual();
-        // Map Level 3
-        let page_map_level3 = self.get_next_table(page_map_level4, indexer.pdp_i())?;
-        // Map Level 2
-        let page_map_level2 = self.get_next_table(page_map_level3, indexer.pd_i())?;
-        // Map Level 1
-        let mut page_map_level1 = self.get_next_table(page_map_level2, indexer.pt_i())?;
+        let (physical_address, _) = self.translate(align_down(virtual_memory, PAGE_SIZE))?;
+
+        Some(unsafe { NonNull::new_unchecked(physical_address as *mut u8) })
+    }
+
+    /// Translates the virtual address to its physical address. Returns the size of the page
+    /// mapping it as well.
+    pub fn translate(&self, virtual_memory: VirtualAddress) -> Option<(PhysicalAddress, PageSize)> {
+        let (page_entry, size) = self.leaf_entry(virtual_memory)?;
+        let page_entry = unsafe { page_entry.as_ref() };
+        let offset = virtual_memory & (size.bytes() - 1);
+
+        Some(((page_entry.address() & !(size.bytes() - 1)) + offset, size))
+    }
+
+    /// Returns the page table entry of the provided virtual address, if it is present. This may
+    /// be the entry of a larger page.
+    pub fn entry(&self, virtual_memory: VirtualAddress) -> Option<PageEntry> {
+        self.leaf_entry(virtual_memory)
+            .map(|(page_entry, _)| unsafe { *page_entry.as_ref() })
+    }
+
+    /// Reserves the page at the virtual address without backing it. The page tables are created
+    /// and the entry stores the flags, but is not present. The page can then be backed using
+    /// [`PageTableMappings::commit_memory`], which does not need to allocate page tables.
+    pub fn reserve_memory(
+        &mut self,
+        virtual_address: VirtualAddress,
+        flags: PageEntryFlags,
+        pmm: &mut FrameAllocator,
+    ) -> Result<(), FrameAllocatorError> {
+        let flags = flags.difference(PageEntryFlags::PRESENT) | PageEntryFlags::LAZY;
+        self.map(virtual_address, 0, flags, PageSize::Small, pmm)
+    }
+
+    /// Returns the flags the reserved page containing the virtual address is mapped with, once it
+    /// is backed.
+    pub fn reserved(&self, virtual_memory: VirtualAddress) -> Option<PageEntryFlags> {
+        let page_entry = unsafe { self.reserved_entry(virtual_memory)?.as_ref() };
+        Some(page_entry.flags().difference(PageEntryFlags::LAZY) | PageEntryFlags::PRESENT)
+    }
+
+    /// Backs the reserved page containing the virtual address with the frame. Returns `false` if
+    /// the page has not been reserved.
+    pub fn commit_memory(
+        &mut self,
+        virtual_memory: VirtualAddress,
+        physical_address: PhysicalAddress,
+    ) -> bool {
+        let Some(mut page_entry) = self.reserved_entry(virtual_memory) else {
+            return false;
+        };
+
+        let page_entry = unsafe { page_entry.as_mut() };
+        let flags = page_entry.flags().difference(PageEntryFlags::LAZY) | PageEntryFlags::PRESENT;
+        page_entry.set_address(physical_address);
+        page_entry.set_flags(flags);
+        true
+    }
+
+    /// Maps the page at the virtual address to the same frame in the other mappings. If the page
+    /// is writable, it becomes read-only and copy-on-write in both mappings, such that neither
+    /// sees the writes of the other, see [`PageTableMappings::copy_written`]. Returns the shared
+    /// frame, or `None` if no page is mapped.
+    ///
+    /// Note: The caller must add the other mappings as an owner of the frame and invalidate the
+    /// TLB entry of the page, if these mappings are active.
+    pub fn share_memory(
+        &mut self,
+        virtual_memory: VirtualAddress,
+        other: &mut PageTableMappings,
+        pmm: &mut FrameAllocator,
+    ) -> Result<Option<PhysicalAddress>, FrameAllocatorError> {
+        let Some((mut page_entry, PageSize::Small)) = self.leaf_entry(virtual_memory) else {
+            return Ok(None);
+        };
+
+        let page_entry = unsafe { page_entry.as_mut() };
+        let mut flags = page_entry.flags();
+        if flags.contains(PageEntryFlags::READ_WRITE) {
+            flags = flags.difference(PageEntryFlags::READ_WRITE) | PageEntryFlags::COPY_ON_WRITE;
+            page_entry.set_flags(flags);
+        }
 
-        let page_entry = &mut unsafe { page_map_level1.as_mut() }.entries[indexer.p_i() as usize];
         let physical_address = page_entry.address();
+        other.map_memory(virtual_memory, physical_address, flags, pmm)?;
+        Ok(Some(physical_address))
+    }
 
-        Some(unsafe { NonNull::new_unchecked(physical_address as *mut u8) })
+    /// Returns the frame of the copy-on-write page containing the virtual address.
+    pub fn copy_on_write(&self, virtual_memory: VirtualAddress) -> Option<PhysicalAddress> {
+        let (page_entry, size) = self.leaf_entry(virtual_memory)?;
+        let page_entry = unsafe { page_entry.as_ref() };
+        (size == PageSize::Small && page_entry.flags().contains(PageEntryFlags::COPY_ON_WRITE))
+            .then_some(page_entry.address())
+    }
+
+    /// Maps the copy-on-write page containing the virtual address writable to the frame, which
+    /// holds its copy or the original frame once it is no longer shared. Returns `false` if the
+    /// page is not copy-on-write.
+    pub fn copy_written(
+        &mut self,
+        virtual_memory: VirtualAddress,
+        physical_address: PhysicalAddress,
+    ) -> bool {
+        let Some((mut page_entry, PageSize::Small)) = self.leaf_entry(virtual_memory) else {
+            return false;
+        };
+
+        let page_entry = unsafe { page_entry.as_mut() };
+        if !page_entry.flags().contains(PageEntryFlags::COPY_ON_WRITE) {
+            return false;
+        }
+
+        let flags = page_entry.flags().difference(PageEntryFlags::COPY_ON_WRITE)
+            | PageEntryFlags::READ_WRITE;
+        page_entry.set_address(physical_address);
+        page_entry.set_flags(flags);
+
+        unsafe { Self::invalidate_tlb_entry(align_down(virtual_memory, PAGE_SIZE)) };
+        true
     }
 
     /// Used to update cache when unmapping addresses
@@ -309,6 +457,99 @@ impl PageTableMappings {
         }
     }
 
+    /// Walks the page tables down to the present entry mapping the virtual address.
+    fn leaf_entry(&self, virtual_memory: VirtualAddress) -> Option<(NonNull<PageEntry>, PageSize)> {
+        let indexer = PageMapIndexer::new(virtual_memory);
+        let page_map_level4 = self.pml4_virtual();
+        // Map Level 3
+        let mut page_map_level3 = self.get_next_table(page_map_level4, indexer.pdp_i())?;
+        let entry = &mut unsafe { page_map_level3.as_mut() }.entries[indexer.pd_i() as usize];
+        if entry.is_huge() {
+            return Some((NonNull::from(entry), PageSize::Huge));
+        }
+
+        // Map Level 2
+        let mut page_map_level2 = self.get_next_table(page_map_level3, indexer.pd_i())?;
+        let entry = &mut unsafe { page_map_level2.as_mut() }.entries[indexer.pt_i() as usize];
+        if entry.is_huge() {
+            return Some((NonNull::from(entry), PageSize::Large));
+        }
+
+        // Map Level 1
+        let mut page_map_level1 = self.get_next_table(page_map_level2, indexer.pt_i())?;
+        let entry = &mut unsafe { page_map_level1.as_mut() }.entries[indexer.p_i() as usize];
+        entry
+            .flags()
+            .contains(PageEntryFlags::PRESENT)
+            .then_some((NonNull::from(entry), PageSize::Small))
+    }
+
+    /// Walks the page tables down to the level 1 entry of the virtual address, if it has been
+    /// reserved and is not present yet.
+    fn reserved_entry(&self, virtual_memory: VirtualAddress) -> Option<NonNull<PageEntry>> {
+        let indexer = PageMapIndexer::new(virtual_memory);
+        let page_map_level4 = self.pml4_virtual();
+        // Map Level 3
+        let page_map_level3 = self.get_next_table(page_map_level4, indexer.pdp_i())?;
+        // Map Level 2
+        let page_map_level2 = self.get_next_table(page_map_level3, indexer.pd_i())?;
+        // Map Level 1
+        let mut page_map_level1 = self.get_next_table(page_map_level2, indexer.pt_i())?;
+
+        let entry = &mut unsafe { page_map_level1.as_mut() }.entries[indexer.p_i() as usize];
+        let flags = entry.flags();
+        (flags.contains(PageEntryFlags::LAZY) && !flags.contains(PageEntryFlags::PRESENT))
+            .then_some(NonNull::from(entry))
+    }
+
+    /// Sets the entry of the table at the level of the page size.
+    fn map(
+        &mut self,
+        virtual_address: VirtualAddress,
+        physical_address: PhysicalAddress,
+        flags: PageEntryFlags,
+        size: PageSize,
+        pmm: &mut FrameAllocator,
+    ) -> Result<(), FrameAllocatorError> {
+        let indexer = PageMapIndexer::new(virtual_address);
+        let pml4 = self.pml4_virtual();
+        let user = flags.contains(PageEntryFlags::USER_SUPER);
+
+        // Map Level 3
+        let mut page_map =
+            self.get_or_create_next_table(pml4, indexer.pdp_i(), virtual_address, pmm, user)?;
+        let mut index = indexer.pd_i();
+        if size != PageSize::Huge {
+            // Map Level 2
+            page_map =
+                self.get_or_create_next_table(page_map, index, virtual_address, pmm, user)?;
+            index = indexer.pt_i();
+        }
+        if size == PageSize::Small {
+            // Map Level 1
+            page_map =
+                self.get_or_create_next_table(page_map, index, virtual_address, pmm, user)?;
+            index = indexer.p_i();
+        }
+
+        let page_entry = &mut unsafe { page_map.as_mut() }.entries[index as usize];
+        let flags = match size {
+            PageSize::Small => flags,
+            _ => {
+                // the next table would no longer be reachable
+                if page_entry.flags().contains(PageEntryFlags::PRESENT) && !page_entry.is_huge() {
+                    return Err(FrameAllocatorError::MappingConflict(virtual_address));
+                }
+                flags | PageEntryFlags::PAT_PAGE_SIZE
+            }
+        };
+
+        page_entry.set_address(physical_address);
+        page_entry.set_flags(flags);
+
+        Ok(())
+    }
+
     /// Attempt the get the next table
     fn get_next_table(
         &self,
@@ -316,7 +557,7 @@ impl PageTableMappings {
         index: u64,
     ) -> Option<NonNull<PageTable>> {
         let entry = &mut unsafe { current_table.as_mut() }.entries[index as usize];
-        if entry.flags().contains(PageEntryFlags::PRESENT) {
+        if entry.flags().contains(PageEntryFlags::PRESENT) && !entry.is_huge() {
             unsafe {
                 Some(NonNull::new_unchecked(
                     (entry.address() + self.offset) as *mut PageTable,
@@ -327,17 +568,21 @@ impl PageTableMappings {
         }
     }
 
-    /// Get a pointer to next table or create it if it does not exist yet.
+    /// Get a pointer to next table or create it if it does not exist yet. Fails if the entry
+    /// already maps a larger page containing the virtual address.
     fn get_or_create_next_table(
         &mut self,
         mut current_table: NonNull<PageTable>,
         index: u64,
-        pmm: &mut BitMapAllocator,
+        virtual_address: VirtualAddress,
+        pmm: &mut FrameAllocator,
         user: bool,
     ) -> Result<NonNull<PageTable>, FrameAllocatorError> {
         let entry = &mut unsafe { current_table.as_mut() }.entries[index as usize];
 
-        if entry.flags().contains(PageEntryFlags::PRESENT) {
+        if entry.is_huge() {
+            Err(FrameAllocatorError::MappingConflict(virtual_address))
+        } else if entry.flags().contains(PageEntryFlags::PRESENT) {
             // path to entry user accessible as well
             if user && !entry.flags().contains(PageEntryFlags::USER_SUPER) {
                 entry.set_flags(entry.flags() | PageEntryFlags::USER_SUPER);
@@ -0,0 +1,514 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use crate::{VirtualAddress, error::RegionError};
+
+/// Allocated region of a [`RegionTree`], which carries the data of its owner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Region<T> {
+    pub start: VirtualAddress,
+    pub length: u64,
+    pub data: T,
+}
+
+impl<T> Region<T> {
+    /// Returns the end address (exclusive).
+    pub fn end(&self) -> VirtualAddress {
+        self.start + self.length
+    }
+
+    pub fn contains(&self, address: VirtualAddress) -> bool {
+        (self.start..self.end()).contains(&address)
+    }
+}
+
+/// Keeps track of the allocated regions of an address range. The regions are stored in a balanced
+/// tree keyed by their start address. The free gaps in between are indexed by their length as
+/// well, so that an allocation takes the smallest gap it fits into in logarithmic time.
+///
+/// Regions can be split, e.g. by unmapping a part of them, and adjacent regions with the same data
+/// can be merged again. Adjacent gaps are always merged.
+#[derive(Debug)]
+pub struct RegionTree<T> {
+    start: VirtualAddress,
+    end: VirtualAddress,
+    regions: BTreeMap<VirtualAddress, Region<T>>,
+    /// Length of each gap, keyed by its start address.
+    gaps: BTreeMap<VirtualAddress, u64>,
+    /// Gaps ordered by their length, then by their start address.
+    gap_lengths: BTreeSet<(u64, VirtualAddress)>,
+    allocated: u64,
+}
+
+impl<T> RegionTree<T> {
+    /// Creates a tree without any regions, managing `length` bytes starting at `start`. The range
+    /// is cut off at the end of the address space.
+    pub fn new(start: VirtualAddress, length: u64) -> RegionTree<T> {
+        let mut tree = RegionTree {
+            start,
+            end: start.saturating_add(length),
+            regions: BTreeMap::new(),
+            gaps: BTreeMap::new(),
+            gap_lengths: BTreeSet::new(),
+            allocated: 0,
+        };
+
+        tree.insert_gap(start, tree.end - start);
+        tree
+    }
+}
+
+impl<T> RegionTree<T> {
+    /// Allocates a region of `length` bytes in the smallest gap it fits into. Returns its start
+    /// address.
+    pub fn allocate(&mut self, length: u64, data: T) -> Result<VirtualAddress, RegionError> {
+        if length == 0 {
+            return Err(RegionError::InvalidLength(length));
+        }
+
+        let &(_, start) = self
+            .gap_lengths
+            .range((length, VirtualAddress::MIN)..)
+            .next()
+            .ok_or(RegionError::NoSpace(length))?;
+
+        self.insert(start, length, data);
+        Ok(start)
+    }
+
+    /// Allocates the region of `length` bytes starting at `address`, which must be free.
+    pub fn allocate_at(
+        &mut self,
+        address: VirtualAddress,
+        length: u64,
+        data: T,
+    ) -> Result<(), RegionError> {
+        let end = self.check_range(address, length)?;
+
+        // the range must lie within a single gap, since adjacent gaps are merged
+        match self.gaps.range(..=address).next_back() {
+            Some((&start, &gap)) if end <= start + gap => {
+                self.insert(address, length, data);
+                Ok(())
+            }
+            _ => Err(RegionError::Occupied(address)),
+        }
+    }
+
+    /// Frees the region starting at `address`. Returns the removed region.
+    pub fn free(&mut self, address: VirtualAddress) -> Result<Region<T>, RegionError> {
+        if !self.regions.contains_key(&address) {
+            return Err(RegionError::NotAllocated(address));
+        }
+
+        Ok(self.remove(address))
+    }
+
+    /// Returns the region containing the address.
+    pub fn get(&self, address: VirtualAddress) -> Option<&Region<T>> {
+        self.regions
+            .range(..=address)
+            .next_back()
+            .map(|(_, region)| region)
+            .filter(|region| region.contains(address))
+    }
+
+    /// Returns the region containing the address.
+    pub fn get_mut(&mut self, address: VirtualAddress) -> Option<&mut Region<T>> {
+        self.regions
+            .range_mut(..=address)
+            .next_back()
+            .map(|(_, region)| region)
+            .filter(|region| region.contains(address))
+    }
+
+    /// Returns all regions ordered by their start address.
+    pub fn regions(&self) -> impl Iterator<Item = &Region<T>> {
+        self.regions.values()
+    }
+
+    /// Returns the start address and length of all gaps ordered by their start address.
+    pub fn gaps(&self) -> impl Iterator<Item = (VirtualAddress, u64)> + '_ {
+        self.gaps.iter().map(|(&start, &length)| (start, length))
+    }
+
+    /// Returns the number of bytes allocated by all regions.
+    pub fn allocated(&self) -> u64 {
+        self.allocated
+    }
+
+    /// Returns the number of bytes managed by the tree.
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+impl<T: Clone> RegionTree<T> {
+    /// Splits the region containing the address into two regions with the same data, the second
+    /// one starting at the address. Nothing is done if a region already starts at the address.
+    pub fn split(&mut self, address: VirtualAddress) -> Result<(), RegionError> {
+        let region = self
+            .get_mut(address)
+            .ok_or(RegionError::NotAllocated(address))?;
+        if region.start == address {
+            return Ok(());
+        }
+
+        let tail = Region {
+            start: address,
+            length: region.end() - address,
+            data: region.data.clone(),
+        };
+        region.length = address - region.start;
+        self.regions.insert(address, tail);
+
+        Ok(())
+    }
+
+    /// Removes the range of `length` bytes starting at `address` from all regions. Regions only
+    /// partially covered by the range are split, gaps within the range are skipped. Returns the
+    /// removed parts ordered by their start address.
+    pub fn unmap(
+        &mut self,
+        address: VirtualAddress,
+        length: u64,
+    ) -> Result<Vec<Region<T>>, RegionError> {
+        let end = self.check_range(address, length)?;
+
+        for boundary in [address, end] {
+            if self.get(boundary).is_some() {
+                self.split(boundary)?;
+            }
+        }
+
+        let starts: Vec<VirtualAddress> = self
+            .regions
+            .range(address..end)
+            .map(|(&start, _)| start)
+            .collect();
+        Ok(starts.into_iter().map(|start| self.remove(start)).collect())
+    }
+}
+
+impl<T: PartialEq> RegionTree<T> {
+    /// Merges the region starting at `address` with the region directly following it. Both must
+    /// carry the same data.
+    pub fn merge(&mut self, address: VirtualAddress) -> Result<(), RegionError> {
+        let region = self
+            .regions
+            .get(&address)
+            .ok_or(RegionError::NotAllocated(address))?;
+
+        let end = region.end();
+        match self.regions.get(&end) {
+            Some(next) if next.data == region.data => {
+                let next = self.regions.remove(&end).map_or(0, |next| next.length);
+                if let Some(region) = self.regions.get_mut(&address) {
+                    region.length += next;
+                }
+                Ok(())
+            }
+            _ => Err(RegionError::NotMergeable(address)),
+        }
+    }
+}
+
+impl<T> RegionTree<T> {
+    /// Checks that the range is not empty and lies within the managed range. Returns its end.
+    fn check_range(
+        &self,
+        address: VirtualAddress,
+        length: u64,
+    ) -> Result<VirtualAddress, RegionError> {
+        if length == 0 {
+            return Err(RegionError::InvalidLength(length));
+        }
+
+        address
+            .checked_add(length)
+            .filter(|end| address >= self.start && *end <= self.end)
+            .ok_or(RegionError::OutOfRange(address))
+    }
+
+    /// Adds the region, which must lie within a gap, and shrinks the gap accordingly.
+    fn insert(&mut self, start: VirtualAddress, length: u64, data: T) {
+        let (gap, gap_length) = self
+            .gaps
+            .range(..=start)
+            .next_back()
+            .map(|(&gap, &gap_length)| (gap, gap_length))
+            .expect("region must lie within a gap");
+        self.remove_gap(gap);
+
+        let end = start + length;
+        if start > gap {
+            self.add_gap(gap, start - gap);
+        }
+        if end < gap + gap_length {
+            self.add_gap(end, gap + gap_length - end);
+        }
+
+        self.regions.insert(
+            start,
+            Region {
+                start,
+                length,
+                data,
+            },
+        );
+        self.allocated += length;
+    }
+
+    /// Removes the region starting at the address, which must exist, and frees its range.
+    fn remove(&mut self, start: VirtualAddress) -> Region<T> {
+        let region = self
+            .regions
+            .remove(&start)
+            .expect("region must have been allocated");
+
+        self.allocated -= region.length;
+        self.insert_gap(region.start, region.length);
+        region
+    }
+
+    /// Adds the gap after merging it with the adjacent gaps.
+    fn insert_gap(&mut self, mut start: VirtualAddress, mut length: u64) {
+        if length == 0 {
+            return;
+        }
+
+        if let Some((&previous, &previous_length)) = self.gaps.range(..start).next_back()
+            && previous + previous_length == start
+        {
+            self.remove_gap(previous);
+            start = previous;
+            length += previous_length;
+        }
+        if let Some(next_length) = self.remove_gap(start + length) {
+            length += next_length;
+        }
+
+        self.add_gap(start, length);
+    }
+
+    fn add_gap(&mut self, start: VirtualAddress, length: u64) {
+        self.gaps.insert(start, length);
+        self.gap_lengths.insert((length, start));
+    }
+
+    /// Removes the gap starting at the address. Returns its length.
+    fn remove_gap(&mut self, start: VirtualAddress) -> Option<u64> {
+        let length = self.gaps.remove(&start)?;
+        self.gap_lengths.remove(&(length, start));
+        Some(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    const START: VirtualAddress = 0x10_0000;
+    const SIZE: u64 = 0x10_0000;
+
+    fn tree() -> RegionTree<u8> {
+        RegionTree::new(START, SIZE)
+    }
+
+    /// Checks that regions and gaps cover the managed range without overlapping and that no gaps
+    /// are adjacent.
+    fn assert_consistent(tree: &RegionTree<u8>) {
+        let mut ranges: Vec<(VirtualAddress, u64, bool)> = tree
+            .regions()
+            .map(|region| (region.start, region.length, true))
+            .chain(tree.gaps().map(|(start, length)| (start, length, false)))
+            .collect();
+        ranges.sort();
+
+        let mut address = START;
+        let mut previous_gap = false;
+        for (start, length, allocated) in ranges {
+            assert_eq!(start, address);
+            assert!(allocated || !previous_gap, "adjacent gaps at {start:#x}");
+            address += length;
+            previous_gap = !allocated;
+        }
+        assert_eq!(address, START + SIZE);
+
+        let allocated: u64 = tree.regions().map(|region| region.length).sum();
+        assert_eq!(tree.allocated(), allocated);
+        assert_eq!(tree.gaps.len(), tree.gap_lengths.len());
+    }
+
+    #[test]
+    fn allocates_in_order() {
+        let mut tree = tree();
+        assert_eq!(tree.allocate(0x1000, 0), Ok(START));
+        assert_eq!(tree.allocate(0x2000, 1), Ok(START + 0x1000));
+        assert_eq!(tree.allocated(), 0x3000);
+        assert_eq!(tree.get(START + 0x2fff).map(|region| region.data), Some(1));
+        assert!(tree.get(START + 0x3000).is_none());
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_allocations() {
+        let mut tree = tree();
+        assert_eq!(tree.allocate(0, 0), Err(RegionError::InvalidLength(0)));
+        assert_eq!(
+            tree.allocate(SIZE + 1, 0),
+            Err(RegionError::NoSpace(SIZE + 1))
+        );
+        assert_eq!(tree.allocate(SIZE, 0), Ok(START));
+        assert_eq!(tree.allocate(1, 0), Err(RegionError::NoSpace(1)));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn allocates_best_fit() {
+        let mut tree = tree();
+        let a = tree.allocate(0x4000, 0).unwrap();
+        tree.allocate(0x1000, 0).unwrap();
+        let c = tree.allocate(0x2000, 0).unwrap();
+        tree.allocate(0x1000, 0).unwrap();
+        tree.free(a).unwrap();
+        tree.free(c).unwrap();
+
+        // the gap left by c is smaller than the one of a
+        assert_eq!(tree.allocate(0x2000, 1), Ok(c));
+        assert_eq!(tree.allocate(0x3000, 1), Ok(a));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn allocates_at_fixed_address() {
+        let mut tree = tree();
+        assert_eq!(tree.allocate_at(START + 0x5000, 0x2000, 0), Ok(()));
+        assert_eq!(
+            tree.allocate_at(START + 0x6000, 0x1000, 0),
+            Err(RegionError::Occupied(START + 0x6000))
+        );
+        assert_eq!(
+            tree.allocate_at(START + 0x4000, 0x2000, 0),
+            Err(RegionError::Occupied(START + 0x4000))
+        );
+        assert_eq!(
+            tree.allocate_at(START - 0x1000, 0x1000, 0),
+            Err(RegionError::OutOfRange(START - 0x1000))
+        );
+        assert_eq!(
+            tree.allocate_at(START + SIZE - 0x1000, 0x2000, 0),
+            Err(RegionError::OutOfRange(START + SIZE - 0x1000))
+        );
+        assert_eq!(tree.allocate_at(START + 0x4000, 0x1000, 0), Ok(()));
+        assert_eq!(tree.allocate_at(START, 0x4000, 0), Ok(()));
+
+        // the gaps before the fixed regions are filled, the next one follows them
+        assert_eq!(tree.allocate(0x1000, 0), Ok(START + 0x7000));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn free_merges_gaps() {
+        let mut tree = tree();
+        let a = tree.allocate(0x1000, 0).unwrap();
+        let b = tree.allocate(0x1000, 0).unwrap();
+        let c = tree.allocate(0x1000, 0).unwrap();
+
+        tree.free(a).unwrap();
+        tree.free(c).unwrap();
+        assert_eq!(tree.gaps().count(), 2);
+        assert_eq!(tree.free(b).map(|region| region.start), Ok(b));
+        assert_eq!(tree.gaps().collect::<Vec<_>>(), vec![(START, SIZE)]);
+        assert_eq!(tree.free(b), Err(RegionError::NotAllocated(b)));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn splits_and_merges() {
+        let mut tree = tree();
+        let a = tree.allocate(0x4000, 0).unwrap();
+        let b = tree.allocate(0x1000, 1).unwrap();
+
+        assert_eq!(tree.split(a + 0x1000), Ok(()));
+        assert_eq!(tree.split(a + 0x1000), Ok(()));
+        assert_eq!(tree.regions().count(), 3);
+        assert_eq!(tree.get(a).map(|region| region.length), Some(0x1000));
+        assert_eq!(
+            tree.split(b + 0x1000),
+            Err(RegionError::NotAllocated(b + 0x1000))
+        );
+
+        assert_eq!(
+            tree.merge(a + 0x1000),
+            Err(RegionError::NotMergeable(a + 0x1000))
+        );
+        assert_eq!(tree.merge(a), Ok(()));
+        assert_eq!(tree.get(a).map(|region| region.length), Some(0x4000));
+        assert_eq!(tree.merge(b), Err(RegionError::NotMergeable(b)));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn unmaps_part_of_a_region() {
+        let mut tree = tree();
+        let a = tree.allocate(0x4000, 0).unwrap();
+
+        let removed = tree.unmap(a + 0x1000, 0x2000).unwrap();
+        assert_eq!(
+            removed,
+            vec![Region {
+                start: a + 0x1000,
+                length: 0x2000,
+                data: 0
+            }]
+        );
+        assert_eq!(
+            tree.regions()
+                .map(|region| (region.start, region.length))
+                .collect::<Vec<_>>(),
+            vec![(a, 0x1000), (a + 0x3000, 0x1000)]
+        );
+        assert_eq!(tree.allocated(), 0x2000);
+
+        // the hole is reused
+        assert_eq!(tree.allocate(0x2000, 1), Ok(a + 0x1000));
+        assert_consistent(&tree);
+    }
+
+    #[test]
+    fn unmaps_across_regions_and_gaps() {
+        let mut tree = tree();
+        let a = tree.allocate(0x2000, 0).unwrap();
+        let b = tree.allocate(0x2000, 1).unwrap();
+        let c = tree.allocate(0x2000, 2).unwrap();
+        tree.free(b).unwrap();
+
+        let removed = tree.unmap(a + 0x1000, 0x4000).unwrap();
+        assert_eq!(
+            removed
+                .iter()
+                .map(|region| (region.start, region.length, region.data))
+                .collect::<Vec<_>>(),
+            vec![(a + 0x1000, 0x1000, 0), (c, 0x1000, 2)]
+        );
+        assert_eq!(
+            tree.regions()
+                .map(|region| (region.start, region.length))
+                .collect::<Vec<_>>(),
+            vec![(a, 0x1000), (c + 0x1000, 0x1000)]
+        );
+        assert_eq!(tree.unmap(a, 0), Err(RegionError::InvalidLength(0)));
+        assert_eq!(
+            tree.unmap(START + SIZE, 0x1000),
+            Err(RegionError::OutOfRange(START + SIZE))
+        );
+        assert_eq!(tree.unmap(START, SIZE).map(|removed| removed.len()), Ok(2));
+        assert_eq!(tree.gaps().collect::<Vec<_>>(), vec![(START, SIZE)]);
+        assert_consistent(&tree);
+    }
+}
@@ -2,6 +2,8 @@
   pkgs,
   kernel,
   loader,
+  # adds the user test program, which is only started by a kernel built with `selftest`
+  selftest ? false,
 }:
 
 pkgs.stdenv.mkDerivation {
@@ -12,6 +14,8 @@ pkgs.stdenv.mkDerivation {
     pkgs.dosfstools # mkfs.fat
     pkgs.mtools # mcopy, mmd
     pkgs.coreutils
+    pkgs.cpio # initial ramdisk archive
+    pkgs.binutils-unwrapped # as, ld for the user programs
   ];
 
   buildInputs = [
@@ -23,10 +27,30 @@ pkgs.stdenv.mkDerivation {
 
   buildPhase = ''
     IMG=boot.img
+
+    echo "Building init..."
+    as --64 -o init.o ${../user/init.s}
+    ld -static -nostdlib -o init init.o
+
+    echo "Creating initial ramdisk..."
+    cp -r ${../initrd} initrd
+    chmod -R u+w initrd
+    mkdir -p initrd/sbin
+    cp init initrd/sbin/init
+    ${pkgs.lib.optionalString selftest ''
+      echo "Building user test program..."
+      as --64 -o test.o ${../user/test.s}
+      ld -static -nostdlib -o test test.o
+      mkdir -p initrd/bin
+      cp test initrd/bin/test
+    ''}
+    (cd initrd && find . -mindepth 1 | LC_ALL=C sort | cpio -o -H newc --reproducible) > initrd.cpio
+
     FILES=(
       ${loader}/bin/uefi-loader.efi
       ${kernel}/bin/kernel.elf
       ${../psf/light16.psf}
+      initrd.cpio
     )
 
     # Sum file sizes in bytes
@@ -57,6 +81,9 @@ pkgs.stdenv.mkDerivation {
     echo "Copying font..."
     mcopy -i $IMG "''${FILES[2]}" ::/font.psf
 
+    echo "Copying initial ramdisk..."
+    mcopy -i $IMG "''${FILES[3]}" ::/initrd
+
     chmod +w boot.img
   '';
 
@@ -0,0 +1,54 @@
+use core::{alloc::Layout, ptr::NonNull};
+
+use alloc::alloc::{alloc, dealloc, handle_alloc_error};
+use hal::fpu::{SAVE_AREA_ALIGNMENT, SaveMechanism};
+
+/// Save area for the floating point and SIMD state of a task.
+#[derive(Debug)]
+pub struct FpuState {
+    area: NonNull<u8>,
+    layout: Layout,
+}
+
+impl FpuState {
+    /// Allocates a new save area for the mechanism, holding a clean state.
+    pub fn new(mechanism: SaveMechanism) -> FpuState {
+        let layout = Layout::from_size_align(mechanism.size(), SAVE_AREA_ALIGNMENT)
+            .expect("save area size must not overflow");
+
+        let area =
+            NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout));
+
+        unsafe {
+            mechanism.initialize(area.as_ptr());
+        }
+
+        FpuState { area, layout }
+    }
+}
+
+impl FpuState {
+    /// Saves the current floating point and SIMD state.
+    ///
+    /// # Safety
+    /// The mechanism must be the one the area has been created for and it must be enabled.
+    pub unsafe fn save(&mut self, mechanism: SaveMechanism) {
+        unsafe { mechanism.save(self.area.as_ptr()) }
+    }
+
+    /// Restores the saved floating point and SIMD state.
+    ///
+    /// # Safety
+    /// The mechanism must be the one the area has been created for and it must be enabled.
+    pub unsafe fn restore(&self, mechanism: SaveMechanism) {
+        unsafe { mechanism.restore(self.area.as_ptr()) }
+    }
+}
+
+impl Drop for FpuState {
+    fn drop(&mut self) {
+        unsafe {
+            dealloc(self.area.as_ptr(), self.layout);
+        }
+    }
+}
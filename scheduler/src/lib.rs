@@ -2,15 +2,25 @@
 
 use core::{cell::RefCell, ptr::NonNull};
 
-use alloc::rc::Rc;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use fpu::FpuState;
 use hal::{cpu_state::CpuState, registers::rflags::RFlags};
+use mem::VirtualAddress;
 use memory::AddressSpace;
-use task::Task;
+use process::Process;
+use task::{Priority, Task};
+pub mod fpu;
 pub mod memory;
+pub mod policy;
+pub mod process;
 pub mod task;
+pub mod timer;
+pub mod wait;
 
 extern crate alloc;
-// note: for now a process is a mix of process and thread, it will be extended later on.
+
+/// Code executed by a task.
+pub type Entry = Box<dyn FnOnce()>;
 
 pub trait Scheduler {
     type SchedulerError;
@@ -21,6 +31,12 @@ pub trait Scheduler {
     const STACK_SIZE: usize;
     const KERNEL_DS: u16;
     const KERNEL_CS: u16;
+    /// Data segment selector of user code, including the requested privilege level.
+    const USER_DS: u16;
+    /// Code segment selector of user code, including the requested privilege level.
+    const USER_CS: u16;
+    /// PID of the init process, which adopts orphaned processes and reaps them.
+    const INIT_PID: u64;
 
     /// Allocates the stack for a new thread. Returning the address of the stack top.
     fn allocate_stack() -> Result<NonNull<u8>, Self::SchedulerError>;
@@ -42,19 +58,52 @@ pub trait Scheduler {
         address_space: &mut AddressSpace,
     ) -> Result<(), Self::SchedulerError>;
 
-    /// Removes a process from the queue of tasks.
-    fn remove_process(&mut self, pid: u64) -> Result<Rc<RefCell<Task>>, Self::SchedulerError>;
+    /// Removes a thread from the scheduling policy.
+    fn remove_thread(&mut self, tid: u64) -> Result<Rc<RefCell<Task>>, Self::SchedulerError>;
+
+    /// Adds a thread to the scheduling policy. Returning the shared task.
+    fn add_thread(&mut self, thread: Task) -> Result<Rc<RefCell<Task>>, Self::SchedulerError>;
+
+    /// Returns the thread with the specified id.
+    fn thread(&self, tid: u64) -> Option<Rc<RefCell<Task>>>;
+
+    /// Adds a process to the process table. Returning the shared process.
+    fn add_process(
+        &mut self,
+        process: Process,
+    ) -> Result<Rc<RefCell<Process>>, Self::SchedulerError>;
 
-    /// Adds a process to the queue of tasks.
-    fn add_process(&mut self, process: Task) -> Result<(), Self::SchedulerError>;
+    /// Removes a process from the process table.
+    fn remove_process(&mut self, pid: u64) -> Result<Rc<RefCell<Process>>, Self::SchedulerError>;
 
-    /// Creates a new process.
+    /// Returns the process with the specified id.
+    fn process(&self, pid: u64) -> Option<Rc<RefCell<Process>>>;
+
+    /// Returns an iterator over all processes of the process table.
+    fn processes(&self) -> impl Iterator<Item = &Rc<RefCell<Process>>>;
+
+    /// Creates a new child process of `parent` with its own virtual address space, but without
+    /// any threads.
     ///
-    /// Note: the process is not automatically added to any queues.
-    fn create_process(pid: u64, entry: fn()) -> Result<Task, Self::SchedulerError> {
-        let mappings = Self::create_address_space()?;
+    /// Note: the process is not automatically added to the process table.
+    fn create_process(pid: u64, parent: u64) -> Result<Process, Self::SchedulerError> {
+        Ok(Process::new(pid, parent, Self::create_address_space()?))
+    }
 
-        let flags = RFlags::RESERVED_1;
+    /// Creates a new thread of the process with the specified scheduling priority and floating
+    /// point save area. The thread is started in [`trampoline`], which terminates it via
+    /// [`Scheduler::exit`] once `entry` returns.
+    ///
+    /// Note: the thread is not automatically added to any queues.
+    fn create_thread(
+        process: &Rc<RefCell<Process>>,
+        tid: u64,
+        entry: Entry,
+        priority: Priority,
+        fpu: FpuState,
+    ) -> Result<Task, Self::SchedulerError> {
+        // tasks must be interruptible in order to be preempted
+        let flags = RFlags::RESERVED_1 | RFlags::INTERRUPTS_ENABLED;
 
         let stack_top = Self::allocate_stack()?;
 
@@ -63,37 +112,215 @@ pub trait Scheduler {
 
         let context = stack.cast::<CpuState>();
 
+        // the trampoline is entered like a regular function, so the stack must be 16-byte
+        // aligned before the (non-existent) return address is pushed
+        let rsp = ((stack.as_ptr() as u64) & !0xf) - size_of::<u64>() as u64;
         unsafe {
-            context.write(CpuState::new(
-                Self::KERNEL_DS.into(),
-                stack.as_ptr() as u64,
-                flags,
-                Self::KERNEL_CS.into(),
-                entry as usize as u64,
-                0, // indicates we have reached the top-most stack frame
-            ));
+            (rsp as *mut u64).write(0);
         }
 
-        Ok(Task::new(stack, mappings, pid, context))
+        let mut state = CpuState::new(
+            Self::KERNEL_DS.into(),
+            rsp,
+            flags,
+            Self::KERNEL_CS.into(),
+            trampoline::<Self> as extern "sysv64" fn(*mut Entry) -> ! as usize as u64,
+            0, // indicates we have reached the top-most stack frame
+        );
+        // first argument of the trampoline
+        let entry = Box::into_raw(Box::new(entry));
+        state.rdi = entry as u64;
+
+        unsafe {
+            context.write(state);
+        }
+
+        let mut task = Task::new(stack_top, process.clone(), tid, priority, context, fpu);
+        task.entry = NonNull::new(entry);
+        Ok(task)
     }
 
-    /// Deletes an existing process, cleaning up it's memory and removing it from the queue of
-    /// tasks.
-    fn kill_process(&mut self, pid: u64) -> Result<(), Self::SchedulerError> {
-        // remove process from queue
-        let process = self.remove_process(pid)?;
+    /// Creates a new thread of the process executing user code in ring 3, starting at `entry`
+    /// with the stack pointer set to `stack_top`. Both must be mapped as user memory into the
+    /// address space of the process. The kernel stack of the thread is used whenever it enters
+    /// the kernel, see [`task::Task::stack_top`].
+    ///
+    /// Note: the thread is not automatically added to any queues.
+    fn create_user_thread(
+        process: &Rc<RefCell<Process>>,
+        tid: u64,
+        entry: VirtualAddress,
+        stack_top: VirtualAddress,
+        priority: Priority,
+        fpu: FpuState,
+    ) -> Result<Task, Self::SchedulerError> {
+        // tasks must be interruptible in order to be preempted
+        let flags = RFlags::RESERVED_1 | RFlags::INTERRUPTS_ENABLED;
 
-        // free stack
-        Self::free_stack(process.borrow().stack_top)?;
+        let kernel_stack_top = Self::allocate_stack()?;
+
+        // the initial context is restored from the kernel stack, dropping to ring 3
+        let context = unsafe { kernel_stack_top.sub(size_of::<CpuState>()) }.cast::<CpuState>();
+        let state = CpuState::new(
+            Self::USER_DS.into(),
+            stack_top,
+            flags,
+            Self::USER_CS.into(),
+            entry,
+            0, // indicates we have reached the top-most stack frame
+        );
+
+        unsafe {
+            context.write(state);
+        }
+
+        Ok(Task::new(
+            kernel_stack_top,
+            process.clone(),
+            tid,
+            priority,
+            context,
+            fpu,
+        ))
+    }
+
+    /// Creates a new thread of the process resuming user code from `context`, which has been saved
+    /// when another thread entered the kernel via a system call. The new thread returns 0 from
+    /// that system call, see [`memory::AddressSpace::fork`].
+    ///
+    /// Note: the thread is not automatically added to any queues.
+    fn create_forked_thread(
+        process: &Rc<RefCell<Process>>,
+        tid: u64,
+        context: &CpuState,
+        priority: Priority,
+        fpu: FpuState,
+    ) -> Result<Task, Self::SchedulerError> {
+        let kernel_stack_top = Self::allocate_stack()?;
+
+        // the copied context is restored from the kernel stack, like the initial one of a user
+        // thread
+        let stack = unsafe { kernel_stack_top.sub(size_of::<CpuState>()) }.cast::<CpuState>();
+        let mut state = *context;
+        state.rax = 0;
 
-        // free mappings
         unsafe {
-            Self::delete_address_space(&mut process.borrow_mut().address_space)?;
+            stack.write(state);
+        }
+
+        Ok(Task::new(
+            kernel_stack_top,
+            process.clone(),
+            tid,
+            priority,
+            stack,
+            fpu,
+        ))
+    }
+
+    /// Deletes a finished thread, freeing its stack and removing it from the scheduling policy.
+    /// The address space of the process is deleted together with its last thread.
+    fn kill_thread(&mut self, tid: u64) -> Result<(), Self::SchedulerError> {
+        // remove thread from queue
+        let thread = self.remove_thread(tid)?;
+        let thread = thread.borrow();
+
+        // free stack
+        Self::free_stack(thread.stack_top)?;
+
+        let mut process = thread.process.borrow_mut();
+        if process.remove_thread(tid) {
+            // free mappings
+            unsafe {
+                Self::delete_address_space(&mut process.address_space)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Terminates all threads of the process with the exit code, turning it into a zombie. Its
+    /// resources are released once it is reaped, see [`Scheduler::reap_process`].
+    ///
+    /// Note: The caller must ensure that none of the threads is currently running.
+    fn kill_process(&mut self, process: &Rc<RefCell<Process>>, code: i32) {
+        let threads: Vec<u64> = process.borrow().threads().to_vec();
+
+        for tid in threads {
+            if let Some(thread) = self.thread(tid) {
+                self.exit_thread(&thread, code);
+            }
+        }
+    }
+
+    /// Terminates the thread with the exit code, see [`task::Task::exit`]. If it was the last
+    /// live thread of its process, the process becomes a zombie: its children are adopted by the
+    /// init process and its parent is woken to reap it.
+    ///
+    /// Note: This does not (de)allocate any memory, so it is safe to call from within an
+    /// interrupt handler.
+    fn exit_thread(&mut self, thread: &Rc<RefCell<Task>>, code: i32) {
+        let process = thread.borrow().process().clone();
+        if !thread.borrow_mut().exit(code) {
+            return;
+        }
+
+        let (pid, parent) = {
+            let process = process.borrow();
+            (process.pid(), process.parent())
+        };
+
+        // orphans are adopted by init, which must be woken if any of them is a zombie already
+        let mut adopted_zombie = false;
+        for child in self.processes() {
+            let mut child = child.borrow_mut();
+            if child.pid() != Self::INIT_PID && child.parent() == pid {
+                child.set_parent(Self::INIT_PID);
+                adopted_zombie |= child.is_zombie();
+            }
+        }
+
+        if adopted_zombie && let Some(init) = self.process(Self::INIT_PID) {
+            init.borrow_mut().wake_waiters();
+        }
+
+        if let Some(parent) = self.process(parent) {
+            parent.borrow_mut().wake_waiters();
+        }
+    }
+
+    /// Reaps a zombie process, deleting its remaining threads and removing it from the process
+    /// table. The address space is deleted together with the last thread. Returns the reaped
+    /// process, which still holds the exit code.
+    fn reap_process(&mut self, pid: u64) -> Result<Rc<RefCell<Process>>, Self::SchedulerError> {
+        let process = self.remove_process(pid)?;
+        let threads: Vec<u64> = process.borrow().threads().to_vec();
+
+        for tid in threads {
+            self.kill_thread(tid)?;
+        }
+
+        Ok(process)
+    }
+
+    /// Terminates the active task with the exit code by setting it to
+    /// [`task::TaskState::Done`], so it is never scheduled again.
+    fn exit(code: i32) -> !;
+
     /// Schedules the next task.
     fn run(context: &CpuState) -> &CpuState;
 }
+
+/// First code executed by every task. Runs the task's entry and terminates the task with exit
+/// code 0 once it returns.
+extern "sysv64" fn trampoline<S>(entry: *mut Entry) -> !
+where
+    S: Scheduler + ?Sized,
+{
+    // SAFETY: the entry has been leaked by `Scheduler::create_thread` and is only taken once. The
+    // task has given up its ownership when it has been activated, see `Task::activate`.
+    let entry = unsafe { Box::from_raw(entry) };
+    entry();
+
+    S::exit(0)
+}
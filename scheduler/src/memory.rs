@@ -1,11 +1,11 @@
 use core::ptr::NonNull;
 
+use alloc::vec::Vec;
 use mem::{
-    VirtualAddress,
-    bitmap_allocator::BitMapAllocator,
+    FrameAllocator, PAGE_SIZE, USER_VIRTUAL_MAX, VirtualAddress,
     error::FrameAllocatorError,
     paging::{
-        PageTable,
+        PageEntry, PageEntryFlags, PageTable,
         ptm::{PageTableManager, PageTableMappings},
     },
 };
@@ -16,6 +16,8 @@ use mem::{
 pub struct AddressSpace {
     mappings: PageTableMappings,
     pub(crate) state: State,
+    /// Regions of the lower half, whose frames are owned by the address space.
+    regions: Vec<UserRegion>,
 }
 
 impl AddressSpace {
@@ -44,6 +46,18 @@ impl AddressSpace {
         AddressSpace {
             mappings,
             state: State::Inactive,
+            regions: Vec::new(),
+        }
+    }
+
+    /// Creates an address space without any page tables, which must never be activated. Used by
+    /// tests running on the host.
+    #[cfg(test)]
+    pub(crate) fn detached() -> AddressSpace {
+        AddressSpace {
+            mappings: PageTableMappings::new(NonNull::dangling(), false),
+            state: State::Inactive,
+            regions: Vec::new(),
         }
     }
 
@@ -60,24 +74,12 @@ impl AddressSpace {
         };
     }
 
-    /// Frees the level 4 page table and unmaps it.
-    pub fn free(&mut self, ptm: &mut PageTableManager) -> Result<(), AddressSpaceError> {
+    /// Returns the virtual address of the level 4 page table, so that it can be freed by the
+    /// allocator it has been allocated with. The page table must not be used afterwards. This fails
+    /// if the address space is active or poisoned.
+    pub fn pml4_to_free(&self) -> Result<VirtualAddress, AddressSpaceError> {
         match self.state {
-            State::Inactive => {
-                // unmap pml4 in current mapping.
-
-                // SAFETY: this must NOT be called if the specified address space is active. (todo: add an
-                // active flag to VAS)
-                let address = self.mappings.pml4_virtual().as_ptr() as VirtualAddress;
-                ptm.mappings()
-                    .unmap_memory(address)
-                    .ok_or(FrameAllocatorError::OperationFailed(address))?;
-
-                // free frame
-                ptm.pmm()
-                    .free_frame(address)
-                    .map_err(AddressSpaceError::from)
-            }
+            State::Inactive => Ok(self.mappings.pml4_virtual().as_ptr() as VirtualAddress),
             State::Active => Err(AddressSpaceError::FreeActive),
             State::Poisoned => Err(AddressSpaceError::FreePoisoned),
         }
@@ -99,7 +101,7 @@ impl AddressSpace {
         self.mappings
     }
 
-    /// Frees the lower-half page tables of the mappings.
+    /// Frees the frames of all user regions and the lower-half page tables of the mappings.
     ///
     /// Note: The PML4 and higher half entries are
     /// still valid after this operation.
@@ -107,12 +109,204 @@ impl AddressSpace {
     /// # Safety
     /// The pages previously mapped to the lower half are no longer accessible after this action.
     /// The caller must invalidate these entries manually or switch to a new paging scheme.
-    pub unsafe fn clean(&mut self, pmm: &mut BitMapAllocator) -> Result<(), AddressSpaceError> {
+    pub unsafe fn clean(&mut self, pmm: &mut FrameAllocator) -> Result<(), AddressSpaceError> {
+        if self.state == State::Poisoned {
+            return Err(AddressSpaceError::CleanPoisoned);
+        }
+
+        for region in core::mem::take(&mut self.regions) {
+            for page in 0..region.page_count {
+                if let Some(frame) = self
+                    .mappings
+                    .unmap_memory(region.start + (page * PAGE_SIZE) as u64)
+                {
+                    pmm.free_frame(frame)?;
+                }
+            }
+        }
+
+        // SAFETY: the mapping is NOT invalidated here!
+        unsafe { self.mappings.clean(pmm, false) }.map_err(AddressSpaceError::from)
+    }
+}
+
+impl AddressSpace {
+    /// Maps `page_count` zeroed frames to the lower half starting at `start`, which are accessible
+    /// by user code with the specified flags. The frames are owned by the address space and freed
+    /// by [`AddressSpace::clean`].
+    ///
+    /// Note: The address space does not need to be active.
+    pub fn map_user(
+        &mut self,
+        start: VirtualAddress,
+        page_count: usize,
+        flags: PageEntryFlags,
+        pmm: &mut FrameAllocator,
+    ) -> Result<(), AddressSpaceError> {
         if self.state == State::Poisoned {
-            Err(AddressSpaceError::CleanPoisoned)
-        } else {
-            // SAFETY: the mapping is NOT invalidated here!
-            unsafe { self.mappings.clean(pmm, false) }.map_err(AddressSpaceError::from)
+            return Err(AddressSpaceError::MapPoisoned);
+        }
+
+        let region = UserRegion { start, page_count };
+        if !start.is_multiple_of(PAGE_SIZE as u64)
+            || region.end().is_none_or(|end| end > USER_VIRTUAL_MAX)
+            || self.regions.iter().any(|other| other.overlaps(&region))
+        {
+            return Err(AddressSpaceError::InvalidUserRegion(start));
+        }
+
+        // pages are accounted for as soon as they are mapped, so they are freed on failure
+        let index = self.regions.len();
+        self.regions.push(UserRegion {
+            start,
+            page_count: 0,
+        });
+
+        let flags = flags | PageEntryFlags::PRESENT | PageEntryFlags::USER_SUPER;
+        for page in 0..page_count {
+            let frame = pmm.request_page()?;
+            unsafe {
+                ((frame + self.mappings.offset()) as *mut u8).write_bytes(0, PAGE_SIZE);
+            }
+
+            if let Err(err) =
+                self.mappings
+                    .map_memory(start + (page * PAGE_SIZE) as u64, frame, flags, pmm)
+            {
+                pmm.free_frame(frame)?;
+                return Err(err.into());
+            }
+            self.regions[index].page_count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Clones the user regions into the empty `child` address space, which shares all of their
+    /// frames with this address space. Writable pages become read-only and copy-on-write in both,
+    /// such that a frame is only copied once either of them writes to it. The child is added as an
+    /// owner of each frame, which is thus only freed by the last address space using it.
+    ///
+    /// Note: Neither address space needs to be active. The TLB entries of this address space are
+    /// invalidated if it is active.
+    pub fn fork(
+        &mut self,
+        child: &mut AddressSpace,
+        pmm: &mut FrameAllocator,
+    ) -> Result<(), AddressSpaceError> {
+        if self.state == State::Poisoned || child.state == State::Poisoned {
+            return Err(AddressSpaceError::MapPoisoned);
+        }
+        if let Some(region) = child.regions.first() {
+            return Err(AddressSpaceError::InvalidUserRegion(region.start));
+        }
+
+        for region in &self.regions {
+            // pages are accounted for as soon as they are shared, so the child drops them on
+            // failure
+            let index = child.regions.len();
+            child.regions.push(UserRegion {
+                start: region.start,
+                page_count: 0,
+            });
+
+            for page in 0..region.page_count {
+                let address = region.start + (page * PAGE_SIZE) as u64;
+                let shared = self
+                    .mappings
+                    .share_memory(address, &mut child.mappings, pmm)?;
+
+                if let Some(frame) = shared
+                    && let Err(err) = pmm.share_frame(frame)
+                {
+                    child.mappings.unmap_memory(address);
+                    return Err(err.into());
+                }
+                if self.state == State::Active {
+                    unsafe { PageTableMappings::invalidate_tlb_entry(address) };
+                }
+                child.regions[index].page_count += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies `data` to the user memory starting at `address`, which must have been mapped by
+    /// [`AddressSpace::map_user`].
+    ///
+    /// Note: The address space does not need to be active. Frames shared by
+    /// [`AddressSpace::fork`] are written in place, i.e. visible to all of their owners.
+    pub fn write(&self, address: VirtualAddress, data: &[u8]) -> Result<(), AddressSpaceError> {
+        let mut written = 0;
+        while written < data.len() {
+            let current = address + written as u64;
+            let page_offset = current as usize % PAGE_SIZE;
+            let page = current - page_offset as u64;
+
+            if !self.regions.iter().any(|region| region.contains(page)) {
+                return Err(AddressSpaceError::NotMapped(current));
+            }
+
+            let frame = self
+                .mappings
+                .get(page)
+                .ok_or(AddressSpaceError::NotMapped(current))?
+                .as_ptr() as VirtualAddress;
+            let length = (PAGE_SIZE - page_offset).min(data.len() - written);
+
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    data[written..].as_ptr(),
+                    (frame + self.mappings.offset() + page_offset as u64) as *mut u8,
+                    length,
+                );
+            }
+            written += length;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the user regions of the address space.
+    pub fn regions(&self) -> &[UserRegion] {
+        &self.regions
+    }
+
+    /// Returns the page table entry mapping the page of `address`, if it is present.
+    pub fn entry(&self, address: VirtualAddress) -> Option<PageEntry> {
+        self.mappings.entry(address)
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+}
+
+/// Page-aligned region of the lower half, backed by frames owned by an [`AddressSpace`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UserRegion {
+    pub start: VirtualAddress,
+    pub page_count: usize,
+}
+
+impl UserRegion {
+    /// Returns the end address (exclusive), or `None` if it overflows.
+    pub fn end(&self) -> Option<VirtualAddress> {
+        (self.page_count as u64)
+            .checked_mul(PAGE_SIZE as u64)
+            .and_then(|length| self.start.checked_add(length))
+    }
+
+    pub fn contains(&self, address: VirtualAddress) -> bool {
+        self.end()
+            .is_some_and(|end| (self.start..end).contains(&address))
+    }
+
+    pub fn overlaps(&self, other: &UserRegion) -> bool {
+        match (self.end(), other.end()) {
+            (Some(end), Some(other_end)) => self.start < other_end && other.start < end,
+            _ => true,
         }
     }
 }
@@ -129,6 +323,12 @@ pub enum AddressSpaceError {
     CleanPoisoned,
     #[error("Cannot activate a poisoned address space.")]
     ActivatePosioned,
+    #[error("Cannot map memory into a poisoned address space.")]
+    MapPoisoned,
+    #[error("Invalid user region starting at {0:#x}.")]
+    InvalidUserRegion(VirtualAddress),
+    #[error("User address {0:#x} is not mapped.")]
+    NotMapped(VirtualAddress),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
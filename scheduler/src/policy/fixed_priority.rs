@@ -0,0 +1,132 @@
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+
+use super::{Policy, RunQueue};
+use crate::task::{Priority, Task};
+
+/// Strict priority scheduling. Real-time tasks always preempt tasks with a lower priority and
+/// normal tasks only run if no real-time task is ready. Tasks of the same priority are switched
+/// after every timer tick.
+#[derive(Debug, Default)]
+pub struct FixedPriority {
+    /// Queues of real-time tasks, indexed by their level.
+    realtime: [RunQueue; Priority::REALTIME_LEVELS],
+    normal: RunQueue,
+}
+
+impl FixedPriority {
+    pub const fn new() -> FixedPriority {
+        FixedPriority {
+            realtime: [const { RunQueue::new() }; Priority::REALTIME_LEVELS],
+            normal: RunQueue::new(),
+        }
+    }
+
+    fn queues(&self) -> impl Iterator<Item = &RunQueue> {
+        self.realtime.iter().chain(core::iter::once(&self.normal))
+    }
+}
+
+impl Policy for FixedPriority {
+    fn insert(&mut self, task: Rc<RefCell<Task>>) {
+        let queue = match task.borrow().priority().realtime_level() {
+            Some(level) => &mut self.realtime[level],
+            None => &mut self.normal,
+        };
+        queue.push(task);
+    }
+
+    fn remove(&mut self, tid: u64) -> Option<Rc<RefCell<Task>>> {
+        self.realtime
+            .iter_mut()
+            .chain(core::iter::once(&mut self.normal))
+            .find_map(|queue| queue.remove(tid))
+    }
+
+    fn get(&self, tid: u64) -> Option<&Rc<RefCell<Task>>> {
+        self.queues().find_map(|queue| queue.get(tid))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Task>>> {
+        self.queues().flat_map(RunQueue::iter)
+    }
+
+    fn len(&self) -> usize {
+        self.queues().map(RunQueue::len).sum()
+    }
+
+    fn select(&mut self, _active: &Rc<RefCell<Task>>, _elapsed: u64) -> Option<Rc<RefCell<Task>>> {
+        // highest real-time level first
+        self.realtime
+            .iter_mut()
+            .rev()
+            .chain(core::iter::once(&mut self.normal))
+            .find_map(RunQueue::next_runnable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::policy::tests::{switch, task};
+
+    fn tids(policy: &mut FixedPriority, mut active: Rc<RefCell<Task>>, count: usize) -> Vec<u64> {
+        let mut tids = Vec::new();
+        for _ in 0..count {
+            active = switch(policy, &active, 1).unwrap();
+            tids.push(active.borrow().tid());
+        }
+        tids
+    }
+
+    #[test]
+    fn higher_priorities_run_first() {
+        let mut policy = FixedPriority::new();
+        policy.insert(task(1, Priority::Normal));
+        policy.insert(task(2, Priority::RealTime(1)));
+        policy.insert(task(3, Priority::RealTime(5)));
+
+        let active = switch(&mut policy, &task(0, Priority::Normal), 0).unwrap();
+        assert_eq!(active.borrow().tid(), 3);
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(active.borrow().tid(), 3);
+
+        active.borrow_mut().block().unwrap();
+        let active = switch(&mut policy, &active, 0).unwrap();
+        assert_eq!(active.borrow().tid(), 2);
+
+        active.borrow_mut().block().unwrap();
+        let active = switch(&mut policy, &active, 0).unwrap();
+        assert_eq!(active.borrow().tid(), 1);
+    }
+
+    #[test]
+    fn equal_priorities_take_turns() {
+        let mut policy = FixedPriority::new();
+        for tid in 1..=3 {
+            policy.insert(task(tid, Priority::Normal));
+        }
+
+        assert_eq!(
+            tids(&mut policy, task(0, Priority::Normal), 4),
+            [1, 2, 3, 1]
+        );
+    }
+
+    #[test]
+    fn levels_above_the_highest_are_limited() {
+        let mut policy = FixedPriority::new();
+        let highest = Priority::REALTIME_LEVELS as u8 - 1;
+        policy.insert(task(1, Priority::RealTime(highest)));
+        policy.insert(task(2, Priority::RealTime(u8::MAX)));
+        policy.insert(task(3, Priority::RealTime(highest - 1)));
+
+        assert_eq!(
+            tids(&mut policy, task(0, Priority::Normal), 4),
+            [1, 2, 1, 2]
+        );
+    }
+}
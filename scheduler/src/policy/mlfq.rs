@@ -0,0 +1,262 @@
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+
+use super::{Policy, RunQueue};
+use crate::task::{Priority, Task, TaskState};
+
+/// Number of feedback levels for normal tasks.
+const LEVELS: usize = 4;
+
+/// Number of timer ticks after which all normal tasks are moved back to the highest level, so
+/// that long running tasks cannot starve.
+const BOOST_INTERVAL: u64 = 100;
+
+/// Time slice in timer ticks of a task on the specified level. Lower levels get longer slices.
+const fn quantum(level: usize) -> u64 {
+    1 << level
+}
+
+/// Multi-level feedback queue.
+///
+/// Normal tasks start on the highest level and are moved down a level whenever they use up
+/// their time slice. Tasks that give up the CPU early, e.g. by waiting for input, keep their
+/// level and thus preempt compute-bound tasks. Real-time tasks are never demoted and run before
+/// all normal tasks, their level is ignored.
+#[derive(Debug, Default)]
+pub struct Mlfq {
+    realtime: RunQueue,
+    /// Queues of normal tasks, the first one has the highest priority.
+    levels: [RunQueue; LEVELS],
+    /// Timer ticks the active task has used of its current time slice.
+    used: u64,
+    /// Timer ticks since the last priority boost.
+    since_boost: u64,
+}
+
+impl Mlfq {
+    pub const fn new() -> Mlfq {
+        Mlfq {
+            realtime: RunQueue::new(),
+            levels: [const { RunQueue::new() }; LEVELS],
+            used: 0,
+            since_boost: 0,
+        }
+    }
+
+    fn queues(&self) -> impl Iterator<Item = &RunQueue> {
+        core::iter::once(&self.realtime).chain(self.levels.iter())
+    }
+
+    fn queues_mut(&mut self) -> impl Iterator<Item = &mut RunQueue> {
+        core::iter::once(&mut self.realtime).chain(self.levels.iter_mut())
+    }
+
+    /// Moves all normal tasks back to the highest level.
+    fn boost(&mut self) {
+        let (top, rest) = self.levels.split_at_mut(1);
+        for level in rest {
+            level.drain_into(&mut top[0]);
+        }
+    }
+
+    /// Accounts the elapsed ticks to the active task and moves it down a level if it has used up
+    /// its time slice. Returns the level of the active task and whether its time slice has not
+    /// been used up yet, if it is a normal task.
+    fn account(&mut self, active: &Rc<RefCell<Task>>, elapsed: u64) -> Option<(usize, bool)> {
+        let level = self
+            .levels
+            .iter()
+            .position(|level| level.contains(active))?;
+
+        self.used += elapsed;
+        if self.used < quantum(level) {
+            return Some((level, true));
+        }
+
+        if level == LEVELS - 1 {
+            return Some((level, false));
+        }
+
+        if let Some(task) = self.levels[level].take(active) {
+            self.levels[level + 1].push(task);
+        }
+
+        Some((level + 1, false))
+    }
+}
+
+impl Policy for Mlfq {
+    fn insert(&mut self, task: Rc<RefCell<Task>>) {
+        // every queue must be able to hold all tasks, since tasks are moved between them from
+        // within the timer interrupt
+        let capacity = self.len() + 1;
+        self.queues_mut().for_each(|queue| queue.reserve(capacity));
+
+        let priority = task.borrow().priority();
+        match priority {
+            Priority::RealTime(_) => self.realtime.push(task),
+            Priority::Normal => self.levels[0].push(task),
+        }
+    }
+
+    fn remove(&mut self, tid: u64) -> Option<Rc<RefCell<Task>>> {
+        self.queues_mut().find_map(|queue| queue.remove(tid))
+    }
+
+    fn get(&self, tid: u64) -> Option<&Rc<RefCell<Task>>> {
+        self.queues().find_map(|queue| queue.get(tid))
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Task>>> {
+        self.queues().flat_map(RunQueue::iter)
+    }
+
+    fn len(&self) -> usize {
+        self.queues().map(RunQueue::len).sum()
+    }
+
+    fn select(&mut self, active: &Rc<RefCell<Task>>, elapsed: u64) -> Option<Rc<RefCell<Task>>> {
+        self.since_boost += elapsed;
+        if self.since_boost >= BOOST_INTERVAL {
+            self.boost();
+            self.since_boost = 0;
+        }
+
+        let active_level = self.account(active, elapsed);
+
+        if let Some(next) = self.realtime.next_runnable() {
+            self.used = 0;
+            return Some(next);
+        }
+
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            // the active task keeps running until its time slice is used up, unless a task on a
+            // higher level is ready or it has given up the CPU
+            if active_level == Some((index, true))
+                && elapsed > 0
+                && active.borrow().state() == TaskState::Running
+            {
+                return Some(active.clone());
+            }
+
+            if let Some(next) = level.next_runnable() {
+                self.used = 0;
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::tests::{switch, task};
+
+    fn tid(task: &Rc<RefCell<Task>>) -> u64 {
+        task.borrow().tid()
+    }
+
+    #[test]
+    fn demotes_tasks_using_up_their_slice() {
+        let mut policy = Mlfq::new();
+        let idle = task(0, Priority::Normal);
+        let (first, second) = (task(1, Priority::Normal), task(2, Priority::Normal));
+        policy.insert(first.clone());
+        policy.insert(second.clone());
+
+        let active = switch(&mut policy, &idle, 0).unwrap();
+        assert_eq!(tid(&active), 1);
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(tid(&active), 2);
+        assert!(policy.levels[1].contains(&first));
+
+        // the slice on the second level lasts two ticks
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(tid(&active), 1);
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(tid(&active), 1);
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(tid(&active), 2);
+        assert!(policy.levels[2].contains(&first));
+        assert!(policy.levels[1].contains(&second));
+    }
+
+    #[test]
+    fn tasks_giving_up_the_cpu_keep_their_level() {
+        let mut policy = Mlfq::new();
+        let idle = task(0, Priority::Normal);
+        let (compute, interactive) = (task(1, Priority::Normal), task(2, Priority::Normal));
+        policy.insert(compute.clone());
+        policy.insert(interactive.clone());
+
+        let active = switch(&mut policy, &idle, 0).unwrap();
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert!(Rc::ptr_eq(&active, &interactive));
+
+        active.borrow_mut().block().unwrap();
+        let active = switch(&mut policy, &active, 0).unwrap();
+        assert!(Rc::ptr_eq(&active, &compute));
+        assert!(policy.levels[0].contains(&interactive));
+
+        // the woken task preempts the demoted one within its slice
+        assert!(interactive.borrow_mut().wake());
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert!(Rc::ptr_eq(&active, &interactive));
+    }
+
+    #[test]
+    fn realtime_tasks_run_first() {
+        let mut policy = Mlfq::new();
+        let idle = task(0, Priority::Normal);
+        policy.insert(task(1, Priority::Normal));
+        policy.insert(task(2, Priority::RealTime(0)));
+
+        let active = switch(&mut policy, &idle, 0).unwrap();
+        assert_eq!(tid(&active), 2);
+        let active = switch(&mut policy, &active, 1).unwrap();
+        assert_eq!(tid(&active), 2);
+
+        active.borrow_mut().block().unwrap();
+        let active = switch(&mut policy, &active, 0).unwrap();
+        assert_eq!(tid(&active), 1);
+    }
+
+    #[test]
+    fn boosts_all_tasks_to_the_highest_level() {
+        let mut policy = Mlfq::new();
+        let (first, second) = (task(1, Priority::Normal), task(2, Priority::Normal));
+        policy.insert(first.clone());
+        policy.insert(second.clone());
+
+        let mut active = switch(&mut policy, &task(0, Priority::Normal), 0).unwrap();
+        for _ in 0..30 {
+            active = switch(&mut policy, &active, 1).unwrap();
+        }
+        assert_eq!(policy.levels[LEVELS - 1].len(), 2);
+
+        // the active task is demoted again after the boost, since it has used up its slice
+        let inactive = if Rc::ptr_eq(&active, &first) {
+            &second
+        } else {
+            &first
+        };
+        let next = switch(&mut policy, &active, BOOST_INTERVAL).unwrap();
+        assert!(Rc::ptr_eq(&next, inactive));
+        assert!(policy.levels[0].contains(inactive));
+        assert!(policy.levels[1].contains(&active));
+    }
+
+    #[test]
+    fn selects_nothing_if_no_task_is_ready() {
+        let mut policy = Mlfq::new();
+        let idle = task(0, Priority::Normal);
+        let blocked = task(1, Priority::Normal);
+        blocked.borrow_mut().block().unwrap();
+        policy.insert(blocked);
+
+        assert!(policy.select(&idle, 1).is_none());
+    }
+}
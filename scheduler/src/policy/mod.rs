@@ -0,0 +1,202 @@
+use core::cell::RefCell;
+
+use alloc::{collections::vec_deque::VecDeque, rc::Rc};
+
+use crate::task::{Task, TaskState};
+
+#[cfg(feature = "fixed-priority")]
+pub mod fixed_priority;
+#[cfg(feature = "mlfq")]
+pub mod mlfq;
+#[cfg(feature = "round-robin")]
+pub mod round_robin;
+
+/// Strategy deciding which task runs next.
+///
+/// Note: [`Policy::select`] is called from within the timer interrupt, possibly while the
+/// interrupted task holds the heap lock. Thus, it must not (de)allocate any memory. Memory
+/// required for moving tasks between queues must be reserved by [`Policy::insert`] instead.
+pub trait Policy {
+    /// Adds a new task to the policy.
+    fn insert(&mut self, task: Rc<RefCell<Task>>);
+
+    /// Removes the task with the specified thread id from the policy.
+    fn remove(&mut self, tid: u64) -> Option<Rc<RefCell<Task>>>;
+
+    /// Returns the task with the specified thread id.
+    fn get(&self, tid: u64) -> Option<&Rc<RefCell<Task>>>;
+
+    /// Returns an iterator over all tasks managed by the policy.
+    fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Task>>>;
+
+    /// Returns the number of tasks managed by the policy.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Selects the task to run next. The `active` task has been interrupted after running for
+    /// `elapsed` timer ticks since the last selection. If no ticks have elapsed, the active task
+    /// has given up the CPU voluntarily. The active task may be returned again, as long as it
+    /// is still running. Returns `None` if no task is ready.
+    fn select(&mut self, active: &Rc<RefCell<Task>>, elapsed: u64) -> Option<Rc<RefCell<Task>>>;
+}
+
+/// Whether the task can be scheduled. The active task is the only one in the running state.
+fn is_runnable(task: &Rc<RefCell<Task>>) -> bool {
+    matches!(task.borrow().state(), TaskState::Ready | TaskState::Running)
+}
+
+/// Round-robin queue of tasks, the building block of all policies.
+#[derive(Debug, Default)]
+pub struct RunQueue {
+    tasks: VecDeque<Rc<RefCell<Task>>>,
+}
+
+impl RunQueue {
+    pub const fn new() -> RunQueue {
+        RunQueue {
+            tasks: VecDeque::new(),
+        }
+    }
+}
+
+impl RunQueue {
+    /// Ensures the queue is able to hold `capacity` tasks without reallocating.
+    pub fn reserve(&mut self, capacity: usize) {
+        self.tasks
+            .reserve(capacity.saturating_sub(self.tasks.len()));
+    }
+
+    /// Appends a task to the back of the queue.
+    ///
+    /// Note: This only allocates memory if the capacity of the queue has not been reserved.
+    pub fn push(&mut self, task: Rc<RefCell<Task>>) {
+        self.tasks.push_back(task);
+    }
+
+    /// Removes the task with the specified thread id.
+    pub fn remove(&mut self, tid: u64) -> Option<Rc<RefCell<Task>>> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.borrow().tid() == tid)?;
+        self.tasks.remove(index)
+    }
+
+    /// Removes the specified task.
+    pub fn take(&mut self, task: &Rc<RefCell<Task>>) -> Option<Rc<RefCell<Task>>> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|queued| Rc::ptr_eq(queued, task))?;
+        self.tasks.remove(index)
+    }
+
+    /// Moves all tasks to the back of the other queue.
+    pub fn drain_into(&mut self, other: &mut RunQueue) {
+        while let Some(task) = self.tasks.pop_front() {
+            other.push(task);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Task>>> {
+        self.tasks.iter()
+    }
+
+    pub fn get(&self, tid: u64) -> Option<&Rc<RefCell<Task>>> {
+        self.tasks.iter().find(|task| task.borrow().tid() == tid)
+    }
+
+    pub fn contains(&self, task: &Rc<RefCell<Task>>) -> bool {
+        self.tasks.iter().any(|queued| Rc::ptr_eq(queued, task))
+    }
+
+    /// Whether any task of the queue can be scheduled.
+    pub fn has_runnable(&self) -> bool {
+        self.tasks.iter().any(is_runnable)
+    }
+
+    /// Rotates through the queue and returns the first task that can be scheduled. The returned
+    /// task is moved to the back of the queue.
+    pub fn next_runnable(&mut self) -> Option<Rc<RefCell<Task>>> {
+        for _ in 0..self.tasks.len() {
+            self.tasks.rotate_left(1);
+
+            let candidate = self.tasks.back()?;
+            if is_runnable(candidate) {
+                return Some(candidate.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use core::ptr::NonNull;
+
+    use hal::fpu::SaveMechanism;
+
+    use super::*;
+    use crate::{fpu::FpuState, memory::AddressSpace, process::Process, task::Priority};
+
+    /// Creates a ready task of its own process, which can be inserted into a policy on the host.
+    pub(crate) fn task(tid: u64, priority: Priority) -> Rc<RefCell<Task>> {
+        let process = Rc::new(RefCell::new(Process::new(tid, 0, AddressSpace::detached())));
+        Rc::new(RefCell::new(Task::new(
+            NonNull::dangling(),
+            process,
+            tid,
+            priority,
+            NonNull::dangling(),
+            FpuState::new(SaveMechanism::Fxsave),
+        )))
+    }
+
+    /// Lets the policy select the next task after the `active` one has run for `elapsed` ticks
+    /// and switches to it like the kernel does.
+    #[cfg(any(feature = "mlfq", feature = "fixed-priority"))]
+    pub(super) fn switch(
+        policy: &mut impl Policy,
+        active: &Rc<RefCell<Task>>,
+        elapsed: u64,
+    ) -> Option<Rc<RefCell<Task>>> {
+        let next = policy.select(active, elapsed)?;
+        if active.borrow().state() == TaskState::Running {
+            active.borrow_mut().state = TaskState::Ready;
+        }
+        next.borrow_mut().state = TaskState::Running;
+        Some(next)
+    }
+
+    #[test]
+    fn run_queue_skips_tasks_that_cannot_run() {
+        let mut queue = RunQueue::new();
+        for tid in 1..=3 {
+            queue.push(task(tid, Priority::Normal));
+        }
+        queue.get(2).unwrap().borrow_mut().block().unwrap();
+
+        let selected: alloc::vec::Vec<u64> = (0..4)
+            .map(|_| queue.next_runnable().unwrap().borrow().tid())
+            .collect();
+        assert_eq!(selected, [1, 3, 1, 3]);
+        assert!(queue.has_runnable());
+
+        queue.get(1).unwrap().borrow_mut().block().unwrap();
+        queue.get(3).unwrap().borrow_mut().block().unwrap();
+        assert!(!queue.has_runnable());
+        assert!(queue.next_runnable().is_none());
+    }
+}
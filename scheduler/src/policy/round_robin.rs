@@ -0,0 +1,46 @@
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
+
+use super::{Policy, RunQueue};
+use crate::task::Task;
+
+/// Switches between all tasks after every timer tick, ignoring their priorities.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    queue: RunQueue,
+}
+
+impl RoundRobin {
+    pub const fn new() -> RoundRobin {
+        RoundRobin {
+            queue: RunQueue::new(),
+        }
+    }
+}
+
+impl Policy for RoundRobin {
+    fn insert(&mut self, task: Rc<RefCell<Task>>) {
+        self.queue.push(task);
+    }
+
+    fn remove(&mut self, tid: u64) -> Option<Rc<RefCell<Task>>> {
+        self.queue.remove(tid)
+    }
+
+    fn get(&self, tid: u64) -> Option<&Rc<RefCell<Task>>> {
+        self.queue.get(tid)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Rc<RefCell<Task>>> {
+        self.queue.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn select(&mut self, _active: &Rc<RefCell<Task>>, _elapsed: u64) -> Option<Rc<RefCell<Task>>> {
+        self.queue.next_runnable()
+    }
+}
@@ -0,0 +1,135 @@
+use alloc::{rc::Rc, vec::Vec};
+use core::cell::RefCell;
+use mem::paging::ptm::PageTableMappings;
+use vfs::fd::FileTable;
+
+use crate::{
+    memory::AddressSpace,
+    task::{Task, TaskError},
+    wait::WaitQueue,
+};
+
+/// A process owns a virtual address space, which is shared by all of its threads. Each thread is
+/// a [`crate::task::Task`] with its own stack and context.
+///
+/// Once its last thread has exited, the process becomes a zombie. It keeps its resources and exit
+/// code until it is reaped by its parent, see [`crate::Scheduler::reap_process`].
+#[derive(Debug)]
+pub struct Process {
+    pid: u64,
+    /// PID of the process responsible for reaping this one.
+    parent: u64,
+    pub(crate) address_space: AddressSpace,
+    /// Files opened by the threads of the process. They are closed once the process has been
+    /// reaped, since [`crate::Scheduler::exit_thread`] does not free any memory.
+    files: FileTable,
+    /// Thread ids of the tasks belonging to the process.
+    threads: Vec<u64>,
+    /// Number of threads that have not exited yet.
+    live_threads: usize,
+    /// Exit code of the last thread, set once the process has become a zombie.
+    exit_code: Option<i32>,
+    /// Threads of this process waiting for a child process to exit.
+    waiters: WaitQueue,
+}
+
+impl Process {
+    /// Creates a new process without any threads.
+    pub fn new(pid: u64, parent: u64, address_space: AddressSpace) -> Process {
+        Process {
+            pid,
+            parent,
+            address_space,
+            files: FileTable::new(),
+            threads: Vec::new(),
+            live_threads: 0,
+            exit_code: None,
+            waiters: WaitQueue::new(),
+        }
+    }
+}
+
+impl Process {
+    pub fn pid(&self) -> u64 {
+        self.pid
+    }
+
+    pub fn parent(&self) -> u64 {
+        self.parent
+    }
+
+    pub fn threads(&self) -> &[u64] {
+        &self.threads
+    }
+
+    /// Returns the exit code of the process, if it is a zombie.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Whether all threads of the process have exited.
+    pub fn is_zombie(&self) -> bool {
+        self.exit_code.is_some()
+    }
+
+    pub fn address_space(&self) -> &AddressSpace {
+        &self.address_space
+    }
+
+    pub fn address_space_mut(&mut self) -> &mut AddressSpace {
+        &mut self.address_space
+    }
+
+    pub fn files(&self) -> &FileTable {
+        &self.files
+    }
+
+    pub fn files_mut(&mut self) -> &mut FileTable {
+        &mut self.files
+    }
+
+    /// Creates a copy of the process' page table mappings and returns it.
+    pub fn mappings(&self) -> PageTableMappings {
+        self.address_space.copy_mappings()
+    }
+
+    /// Blocks the `waiter` until a child process has exited or has been adopted. This fails if
+    /// the waiter has finished already.
+    ///
+    /// Note: The waiter keeps running until the caller gives up the CPU.
+    pub fn park_waiter(&mut self, waiter: Rc<RefCell<Task>>) -> Result<(), TaskError> {
+        self.waiters.park(waiter)
+    }
+
+    /// Wakes all threads waiting for a child process. Returns the number of threads that have
+    /// been woken.
+    pub fn wake_waiters(&mut self) -> usize {
+        self.waiters.wake_all()
+    }
+
+    /// Makes the process a child of `parent`.
+    pub(crate) fn set_parent(&mut self, parent: u64) {
+        self.parent = parent;
+    }
+
+    pub(crate) fn add_thread(&mut self, tid: u64) {
+        self.threads.push(tid);
+        self.live_threads += 1;
+    }
+
+    /// Records that a thread has exited with the exit code. Returns whether it was the last live
+    /// thread, turning the process into a zombie.
+    pub(crate) fn exit_thread(&mut self, code: i32) -> bool {
+        self.live_threads = self.live_threads.saturating_sub(1);
+        if self.live_threads == 0 {
+            self.exit_code = Some(code);
+        }
+        self.is_zombie()
+    }
+
+    /// Removes the thread from the process. Returns whether it was the last one.
+    pub(crate) fn remove_thread(&mut self, tid: u64) -> bool {
+        self.threads.retain(|thread| *thread != tid);
+        self.threads.is_empty()
+    }
+}
@@ -1,88 +1,251 @@
-use core::ptr::NonNull;
+use core::{cell::RefCell, ptr::NonNull};
+
+use alloc::{boxed::Box, rc::Rc};
 
 use hal::cpu_state::CpuState;
 use mem::paging::ptm::PageTableMappings;
 
-use crate::memory::{AddressSpace, AddressSpaceError, State};
+use crate::{
+    Entry,
+    fpu::FpuState,
+    memory::{AddressSpaceError, State},
+    process::Process,
+    wait::WaitQueue,
+};
+use stats::{TaskSnapshot, TaskStats};
+
+pub mod stats;
 
+/// A thread of a [`crate::process::Process`], which is the unit of scheduling.
 #[derive(Debug)]
 pub struct Task {
     pub(crate) stack_top: NonNull<u8>,
-    pub(crate) address_space: AddressSpace,
-    pub(crate) pid: u64,
+    pub(crate) process: Rc<RefCell<Process>>,
+    pub(crate) tid: u64,
     pub(crate) state: TaskState,
+    pub(crate) priority: Priority,
     pub(crate) context: NonNull<CpuState>,
+    /// Floating point and SIMD state, which is not part of the context.
+    pub(crate) fpu: FpuState,
+    /// Tasks waiting for this task to finish.
+    pub(crate) joiners: WaitQueue,
+    pub(crate) stats: TaskStats,
+    /// Exit code, set once the task has finished.
+    pub(crate) exit_code: Option<i32>,
+    /// Entry of a kernel thread passed to the trampoline, which is owned by the task until it is
+    /// activated for the first time.
+    pub(crate) entry: Option<NonNull<Entry>>,
 }
 
 impl Task {
     /// Creates a new task instance with the
-    /// [`crate::task::TaskState::Ready`] state.
+    /// [`crate::task::TaskState::Ready`] state and registers it as a thread of the process.
     pub fn new(
-        stack: NonNull<u8>,
-        address_space: AddressSpace,
-        pid: u64,
+        stack_top: NonNull<u8>,
+        process: Rc<RefCell<Process>>,
+        tid: u64,
+        priority: Priority,
         context: NonNull<CpuState>,
+        fpu: FpuState,
     ) -> Task {
+        process.borrow_mut().add_thread(tid);
+
         Self {
-            stack_top: stack,
-            address_space,
-            pid,
+            stack_top,
+            process,
+            tid,
             state: TaskState::Ready,
+            priority,
             context,
+            fpu,
+            joiners: WaitQueue::new(),
+            stats: TaskStats::default(),
+            exit_code: None,
+            entry: None,
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        // the entry of a thread, which has never run, is not freed by the trampoline
+        if let Some(entry) = self.entry.take() {
+            drop(unsafe { Box::from_raw(entry.as_ptr()) });
         }
     }
 }
 
 impl Task {
+    /// Returns the id of the thread, which is unique across all processes.
+    pub fn tid(&self) -> u64 {
+        self.tid
+    }
+
+    /// Returns the id of the process the thread belongs to.
     pub fn pid(&self) -> u64 {
-        self.pid
+        self.process.borrow().pid()
+    }
+
+    /// Returns the top of the task's kernel stack, which is also used when a user task enters the
+    /// kernel.
+    pub fn stack_top(&self) -> NonNull<u8> {
+        self.stack_top
+    }
+
+    pub fn process(&self) -> &Rc<RefCell<Process>> {
+        &self.process
     }
 
     pub fn state(&self) -> TaskState {
         self.state
     }
 
+    /// Returns the exit code of the task, if it has finished.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
     pub fn context(&self) -> NonNull<CpuState> {
         self.context
     }
+
+    pub fn fpu(&self) -> &FpuState {
+        &self.fpu
+    }
+
+    pub fn fpu_mut(&mut self) -> &mut FpuState {
+        &mut self.fpu
+    }
+
+    pub fn stats(&self) -> TaskStats {
+        self.stats
+    }
+
+    /// Returns a copy of the task's state and accounting information.
+    pub fn snapshot(&self) -> TaskSnapshot {
+        TaskSnapshot {
+            tid: self.tid,
+            pid: self.pid(),
+            state: self.state,
+            priority: self.priority,
+            stats: self.stats,
+        }
+    }
+
+    /// Charges the task with the timer ticks it has been running for since the last scheduling
+    /// decision.
+    pub fn account(&mut self, ticks: u64) {
+        self.stats.runtime += ticks;
+    }
 }
 impl Task {
-    /// Sets the task state to [`crate::task::TaskState::Ready`] and the address space to
-    /// [`crate::memory::State::Inactive`]. This fails if the current task state is
-    /// [`crate::task::TaskState::Done`] or the VAS is poisoned.
+    /// Sets the address space of the process to [`crate::memory::State::Inactive`]. A running
+    /// task is set to [`crate::task::TaskState::Ready`], while a blocked, sleeping or finished
+    /// task keeps its state. This fails if the VAS is poisoned.
     pub fn pause(&mut self) -> Result<(), TaskError> {
+        let mut process = self.process.borrow_mut();
+        if process.address_space.state == State::Poisoned {
+            Err(TaskError::VasPoisoned)
+        } else {
+            if self.state == TaskState::Running {
+                self.state = TaskState::Ready;
+            }
+            process.address_space.state = State::Inactive;
+            Ok(())
+        }
+    }
+
+    /// Sets the task state to [`crate::task::TaskState::Done`], records the exit code and wakes
+    /// all tasks waiting for it to finish. Returns whether it was the last live thread of its
+    /// process, which has become a zombie. Exiting a finished task has no effect.
+    ///
+    /// Note: Parent and children of the process are notified by
+    /// [`crate::Scheduler::exit_thread`].
+    pub fn exit(&mut self, code: i32) -> bool {
+        if self.state == TaskState::Done {
+            return false;
+        }
+
+        self.state = TaskState::Done;
+        self.exit_code = Some(code);
+        self.joiners.wake_all();
+
+        self.process.borrow_mut().exit_thread(code)
+    }
+
+    /// Blocks the `joiner` until this task has finished, see [`Task::exit`]. This fails if the
+    /// joiner has finished already.
+    ///
+    /// Note: The joiner keeps running until the caller gives up the CPU.
+    pub fn park_joiner(&mut self, joiner: Rc<RefCell<Task>>) -> Result<(), TaskError> {
+        self.joiners.park(joiner)
+    }
+
+    /// Sets the task state to [`crate::task::TaskState::Blocked`]. The task is no longer
+    /// scheduled until it is woken. This fails if the current task state is
+    /// [`crate::task::TaskState::Done`].
+    pub fn block(&mut self) -> Result<(), TaskError> {
+        if self.state == TaskState::Done {
+            Err(TaskError::Done)
+        } else {
+            self.state = TaskState::Blocked;
+            Ok(())
+        }
+    }
+
+    /// Sets the task state to [`crate::task::TaskState::Sleeping`]. The task is no longer
+    /// scheduled until its deadline has passed, see [`crate::timer::TimerQueue`]. This fails if
+    /// the current task state is [`crate::task::TaskState::Done`].
+    pub fn sleep(&mut self) -> Result<(), TaskError> {
         if self.state == TaskState::Done {
             Err(TaskError::Done)
-        } else if self.address_space.state == State::Poisoned {
-            Err(TaskError::VasPoisoned)
         } else {
-            self.state = TaskState::Ready;
-            self.address_space.state = State::Inactive;
+            self.state = TaskState::Sleeping;
             Ok(())
         }
     }
 
-    /// Sets the task state to [`crate::task::TaskState::Running`] and the address space to
-    /// [`crate::memory::State::Active`]. This fails if the current task state is
-    /// [`crate::task::TaskState::Done`] or the VAS is poisoned.
+    /// Sets a blocked or sleeping task to [`crate::task::TaskState::Ready`]. Returns whether the
+    /// task has been woken.
+    pub fn wake(&mut self) -> bool {
+        match self.state {
+            TaskState::Blocked | TaskState::Sleeping => {
+                self.state = TaskState::Ready;
+                self.stats.wakeups += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the task state to [`crate::task::TaskState::Running`] and the address space of the
+    /// process to [`crate::memory::State::Active`], counting the context switch. This fails if
+    /// the current task state is [`crate::task::TaskState::Done`] or the VAS is poisoned.
     pub fn activate(&mut self) -> Result<(), TaskError> {
+        let mut process = self.process.borrow_mut();
         if self.state == TaskState::Done {
             Err(TaskError::Done)
-        } else if self.address_space.state == State::Poisoned {
+        } else if process.address_space.state == State::Poisoned {
             Err(TaskError::VasPoisoned)
         } else {
             self.state = TaskState::Running;
-            self.address_space.state = State::Active;
+            self.stats.switches += 1;
+            // the entry is taken by the trampoline, once the task runs
+            self.entry = None;
             unsafe {
-                self.address_space.activate_unchecked();
+                process.address_space.activate_unchecked();
             }
             Ok(())
         }
     }
 
-    /// Creates a copy of the taks's page table mappings and returns it.
+    /// Creates a copy of the page table mappings of the task's process and returns it.
     pub fn mappings(&self) -> PageTableMappings {
-        self.address_space.copy_mappings()
+        self.process.borrow().mappings()
     }
 
     /// Updates the task's context.
@@ -98,9 +261,37 @@ impl Task {
 pub enum TaskState {
     Running,
     Ready,
+    /// Waiting for an event, see [`crate::wait::WaitQueue`].
+    Blocked,
+    /// Waiting for a deadline to pass, see [`crate::timer::TimerQueue`].
+    Sleeping,
     Done,
 }
 
+/// Scheduling priority of a task. How it is interpreted depends on the
+/// [`crate::policy::Policy`] in use.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Regular tasks sharing the CPU.
+    #[default]
+    Normal,
+    /// Latency-sensitive tasks, which preempt normal tasks. Higher levels preempt lower ones.
+    RealTime(u8),
+}
+
+impl Priority {
+    /// Number of distinct real-time levels. Higher levels are treated as the highest one.
+    pub const REALTIME_LEVELS: usize = 8;
+
+    /// Returns the real-time level, limited to [`Priority::REALTIME_LEVELS`].
+    pub fn realtime_level(self) -> Option<usize> {
+        match self {
+            Priority::RealTime(level) => Some((level as usize).min(Self::REALTIME_LEVELS - 1)),
+            Priority::Normal => None,
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TaskError {
     #[error("Requested operation cannot be performed on a task that has finished already.")]
@@ -110,3 +301,25 @@ pub enum TaskError {
     #[error("{0}")]
     Vas(#[from] AddressSpaceError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::tests::task;
+
+    #[test]
+    fn dropping_unstarted_thread_frees_entry() {
+        let captured = Rc::new(());
+        let entry: Entry = Box::new({
+            let captured = captured.clone();
+            move || drop(captured)
+        });
+
+        let thread = task(1, Priority::Normal);
+        thread.borrow_mut().entry = NonNull::new(Box::into_raw(Box::new(entry)));
+        assert_eq!(Rc::strong_count(&captured), 2);
+
+        drop(thread);
+        assert_eq!(Rc::strong_count(&captured), 1);
+    }
+}
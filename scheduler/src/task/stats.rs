@@ -0,0 +1,22 @@
+use crate::task::{Priority, TaskState};
+
+/// Accounting information of a task, used to debug starvation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskStats {
+    /// Number of timer ticks the task has been running for.
+    pub runtime: u64,
+    /// Number of times the task has been switched to.
+    pub switches: u64,
+    /// Number of times the task has been woken after blocking or sleeping.
+    pub wakeups: u64,
+}
+
+/// Copy of the state and accounting information of a task at a specific point in time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TaskSnapshot {
+    pub tid: u64,
+    pub pid: u64,
+    pub state: TaskState,
+    pub priority: Priority,
+    pub stats: TaskStats,
+}
@@ -0,0 +1,88 @@
+use core::cell::RefCell;
+
+use alloc::{collections::vec_deque::VecDeque, rc::Rc};
+
+use crate::task::{Task, TaskError};
+
+/// Queue of sleeping tasks, sorted by their deadline. Deadlines are measured in timer ticks.
+///
+/// Note: Waking expired tasks does not (de)allocate any memory, so it is safe to do so from
+/// within an interrupt handler. Tasks must be removed before they are released, so that the queue
+/// never holds their last reference, see [`TimerQueue::remove`].
+#[derive(Debug, Default)]
+pub struct TimerQueue {
+    sleeping: VecDeque<Sleeper>,
+}
+
+#[derive(Debug)]
+struct Sleeper {
+    deadline: u64,
+    task: Rc<RefCell<Task>>,
+}
+
+impl TimerQueue {
+    pub const fn new() -> TimerQueue {
+        TimerQueue {
+            sleeping: VecDeque::new(),
+        }
+    }
+}
+
+impl TimerQueue {
+    /// Puts the task to sleep until the deadline has passed. If the task is already part of the
+    /// queue, its deadline is updated.
+    ///
+    /// Note: The task keeps running until the caller gives up the CPU.
+    pub fn insert(&mut self, deadline: u64, task: Rc<RefCell<Task>>) -> Result<(), TaskError> {
+        task.borrow_mut().sleep()?;
+
+        self.sleeping
+            .retain(|sleeper| !Rc::ptr_eq(&sleeper.task, &task));
+
+        // tasks with the same deadline are woken in the order they have been inserted
+        let index = self
+            .sleeping
+            .partition_point(|sleeper| sleeper.deadline <= deadline);
+        self.sleeping.insert(index, Sleeper { deadline, task });
+
+        Ok(())
+    }
+
+    /// Wakes all tasks whose deadline is at or before `now`. Returns the number of tasks that
+    /// have been woken.
+    pub fn wake_expired(&mut self, now: u64) -> usize {
+        let mut woken = 0;
+        while self
+            .sleeping
+            .front()
+            .is_some_and(|sleeper| sleeper.deadline <= now)
+        {
+            if let Some(sleeper) = self.sleeping.pop_front() {
+                // skip tasks that have been woken or finished in the meantime
+                if sleeper.task.borrow_mut().wake() {
+                    woken += 1;
+                }
+            }
+        }
+        woken
+    }
+
+    /// Removes the task from the queue, if it is sleeping.
+    pub fn remove(&mut self, task: &Rc<RefCell<Task>>) {
+        self.sleeping
+            .retain(|sleeper| !Rc::ptr_eq(&sleeper.task, task));
+    }
+
+    /// Returns the earliest deadline of all sleeping tasks.
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.sleeping.front().map(|sleeper| sleeper.deadline)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sleeping.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sleeping.len()
+    }
+}
@@ -0,0 +1,118 @@
+use core::cell::RefCell;
+
+use alloc::{collections::vec_deque::VecDeque, rc::Rc};
+
+use crate::task::{Task, TaskError};
+
+/// Queue of tasks waiting for an event. Tasks are woken in the order they have been parked.
+///
+/// Note: Waking tasks does not (de)allocate any memory, so it is safe to do so from within an
+/// interrupt handler. A finished task may have been released while it is still waiting, leaving
+/// the queue with its last reference. Dropping it would free the task, so waking keeps such tasks
+/// in the queue and parking releases them instead.
+#[derive(Debug, Default)]
+pub struct WaitQueue {
+    waiting: VecDeque<Rc<RefCell<Task>>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> WaitQueue {
+        WaitQueue {
+            waiting: VecDeque::new(),
+        }
+    }
+}
+
+impl WaitQueue {
+    /// Blocks the task and appends it to the queue, if it is not already waiting on it.
+    ///
+    /// Note: The task keeps running until the caller gives up the CPU. Released tasks, which have
+    /// been kept by waking, are dropped.
+    pub fn park(&mut self, task: Rc<RefCell<Task>>) -> Result<(), TaskError> {
+        task.borrow_mut().block()?;
+
+        self.waiting.retain(|waiting| Rc::strong_count(waiting) > 1);
+
+        if !self
+            .waiting
+            .iter()
+            .any(|waiting| Rc::ptr_eq(waiting, &task))
+        {
+            self.waiting.push_back(task);
+        }
+
+        Ok(())
+    }
+
+    /// Wakes the longest waiting task. Returns whether a task has been woken.
+    pub fn wake_one(&mut self) -> bool {
+        for _ in 0..self.waiting.len() {
+            let Some(task) = self.waiting.pop_front() else {
+                break;
+            };
+
+            // skip tasks that have been woken or finished in the meantime
+            if task.borrow_mut().wake() {
+                return true;
+            }
+
+            // the queue holds the last reference of a released task, the slot has just been
+            // freed, so this does not allocate
+            if Rc::strong_count(&task) == 1 {
+                self.waiting.push_back(task);
+            }
+        }
+
+        false
+    }
+
+    /// Wakes all waiting tasks. Returns the number of tasks that have been woken.
+    pub fn wake_all(&mut self) -> usize {
+        let mut woken = 0;
+        while self.wake_one() {
+            woken += 1;
+        }
+        woken
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        policy::tests::task,
+        task::{Priority, TaskState},
+    };
+
+    #[test]
+    fn waking_keeps_released_tasks() {
+        let mut queue = WaitQueue::new();
+        let released = task(1, Priority::Normal);
+        let waiting = task(2, Priority::Normal);
+        queue.park(released.clone()).unwrap();
+        queue.park(waiting.clone()).unwrap();
+
+        released.borrow_mut().state = TaskState::Done;
+        let weak = Rc::downgrade(&released);
+        drop(released);
+
+        assert!(queue.wake_one());
+        assert_eq!(waiting.borrow().state(), TaskState::Ready);
+        assert!(!queue.wake_one());
+        assert_eq!(weak.strong_count(), 1);
+        assert_eq!(queue.len(), 1);
+
+        queue.park(waiting.clone()).unwrap();
+        assert_eq!(weak.strong_count(), 0);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.wake_all(), 1);
+    }
+}
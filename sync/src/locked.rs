@@ -21,6 +21,11 @@ impl<T> Locked<T> {
     pub fn locked(&self) -> Guard<'_, OnceCell<T>> {
         self.inner.lock()
     }
+
+    /// Attempts to lock the inner value without spinning. Returns `None` if it is already locked.
+    pub fn try_locked(&self) -> Option<Guard<'_, OnceCell<T>>> {
+        self.inner.try_lock()
+    }
 }
 
 impl<T> Default for Locked<T> {
@@ -3,7 +3,7 @@ use core::{
     ops::{Deref, DerefMut},
     sync::atomic::{
         AtomicBool,
-        Ordering::{Acquire, Release},
+        Ordering::{Acquire, Relaxed, Release},
     },
 };
 
@@ -29,6 +29,14 @@ impl<T> SpinLock<T> {
         Guard { lock: self }
     }
 
+    /// Attempts to acquire the lock without spinning. Returns `None` if the lock is already held.
+    pub fn try_lock(&self) -> Option<Guard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Acquire, Relaxed)
+            .ok()
+            .map(|_| Guard { lock: self })
+    }
+
     pub fn unlock(&self) {
         self.locked.store(false, Release);
     }
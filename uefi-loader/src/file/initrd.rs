@@ -0,0 +1,30 @@
+use bootinfo::Initrd;
+use mem::{PAGE_SIZE, PAS_VIRTUAL_MAX};
+use uefi::boot::{self, AllocateType};
+
+use crate::{error::FileParseError, memory::INITRD_DATA};
+
+/// Loads the initial ramdisk archive into memory, which is kept by the kernel. Returns its
+/// physical range.
+pub(crate) fn load(filename: &'static str) -> Result<Initrd, FileParseError> {
+    let data = super::get_file_data(filename)?;
+
+    // the kernel accesses the archive via the mapped physical address space
+    let num_pages = data.len().div_ceil(PAGE_SIZE).max(1);
+    let address = boot::allocate_pages(
+        AllocateType::MaxAddress(PAS_VIRTUAL_MAX),
+        INITRD_DATA,
+        num_pages,
+    )?
+    .as_ptr();
+
+    // since uefi sets up identity paging the physical address can be used directly
+    unsafe {
+        core::ptr::copy_nonoverlapping(data.as_ptr(), address, data.len());
+    }
+
+    Ok(Initrd {
+        address: address as u64,
+        length: data.len(),
+    })
+}
@@ -8,6 +8,7 @@ use uefi::{
 };
 
 pub(crate) mod elf;
+pub(crate) mod initrd;
 
 /// Retrieve file data from filesystem of given file name
 pub(crate) fn get_file_data(filename: &'static str) -> Result<Vec<u8>, FileParseError> {
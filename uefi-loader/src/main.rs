@@ -19,10 +19,10 @@ use graphics::{
 };
 use hal::{instructions::cpuid::Cpuid, registers::msr::msr_guard::Msr};
 use log::{error, info};
-use mem::{bitmap_allocator::BitMapAllocator, PhysicalAddress, KERNEL_STACK_SIZE, PAGE_SIZE};
+use mem::{FrameAllocator, PhysicalAddress, KERNEL_STACK_SIZE, PAGE_SIZE};
 use memory::{
-    NereusMemoryDescriptor, NereusMemoryMap, NereusMemoryType, KERNEL_CODE, KERNEL_DATA,
-    KERNEL_STACK, MMAP_META_DATA, PSF_DATA,
+    NereusMemoryDescriptor, NereusMemoryMap, NereusMemoryType, INITRD_DATA, KERNEL_CODE,
+    KERNEL_DATA, KERNEL_STACK, MMAP_META_DATA, PSF_DATA,
 };
 use uefi::{
     mem::memory_map::MemoryMap,
@@ -37,6 +37,7 @@ mod memory;
 
 const PSF_FILE_NAME: &str = "font.psf";
 const KERNEL_FILE_NAME: &str = "kernel.elf";
+const INITRD_FILE_NAME: &str = "initrd";
 
 #[entry]
 fn main() -> Status {
@@ -86,6 +87,17 @@ fn main() -> Status {
                 kernel_elf.num_pages()
             );
 
+            let initrd = validate!(
+                file::initrd::load(INITRD_FILE_NAME),
+                "Loading initial ramdisk into memory"
+            );
+
+            loginfo!(
+                "Initial ramdisk address: {:#x}, size: {} bytes",
+                initrd.address,
+                initrd.length
+            );
+
             let kernel_stack = validate!(
                 memory::stack::allocate_kernel_stack(KERNEL_STACK_SIZE),
                 "Allocating memory for kernel stack"
@@ -114,19 +126,20 @@ fn main() -> Status {
             let memory_map = drop_boot_services(mmap_descriptors);
             logln!(OK, "OK");
 
-            // set memory map of boot info to the correct one & assign rsdp
+            // set memory map of boot info to the correct one & assign rsdp and initrd
             unsafe {
                 let bootinfo_ref = bootinfo_ptr.as_mut();
                 bootinfo_ref.mmap = memory_map;
                 bootinfo_ref.rsdp = rsdp as *const u8;
+                bootinfo_ref.initrd = initrd;
             }
 
-            let mut pmm = validate!(
-                BitMapAllocator::try_new(memory_map),
+            let pmm = validate!(
+                FrameAllocator::try_new(memory_map),
                 "Initializing physical memory manager"
             );
             loginfo!(
-                "BitMapAllocator address: {:#x}, page count: {:#x}",
+                "Frame allocator metadata address: {:#x}, page count: {:#x}",
                 pmm.address(),
                 pmm.pages()
             );
@@ -202,6 +215,7 @@ fn drop_boot_services(mut mmap_descriptors: Vec<NereusMemoryDescriptor>) -> Nere
                 KERNEL_CODE => NereusMemoryType::KernelCode,
                 KERNEL_DATA | PSF_DATA => NereusMemoryType::KernelData,
                 KERNEL_STACK => NereusMemoryType::KernelStack,
+                INITRD_DATA => NereusMemoryType::Initrd,
                 MemoryType::ACPI_RECLAIM | MemoryType::ACPI_NON_VOLATILE => {
                     NereusMemoryType::AcpiData
                 }
@@ -6,12 +6,11 @@ use core::{
 use ::bootinfo::BootInfo;
 use hal::registers::msr::{efer::Efer, msr_guard::Msr, ModelSpecificRegister};
 use mem::{
-    bitmap_allocator::BitMapAllocator,
     error::FrameAllocatorError,
     map,
-    paging::{ptm::PageTableManager, PageEntryFlags, PageTable},
-    KERNEL_CODE_VIRTUAL, KERNEL_STACK_SIZE, KERNEL_STACK_VIRTUAL, PAGE_SIZE, PAS_VIRTUAL,
-    PAS_VIRTUAL_MAX,
+    paging::{ptm::PageTableManager, PageEntryFlags, PageSize, PageTable},
+    FrameAllocator, KERNEL_CODE_VIRTUAL, KERNEL_STACK_SIZE, KERNEL_STACK_VIRTUAL, PAGE_SIZE,
+    PAS_VIRTUAL, PAS_VIRTUAL_MAX,
 };
 use stack::KernelStack;
 use uefi::boot::MemoryType;
@@ -27,6 +26,7 @@ pub(crate) const KERNEL_CODE: MemoryType = MemoryType::custom(0x8000_0001);
 pub(crate) const KERNEL_STACK: MemoryType = MemoryType::custom(0x8000_0002);
 pub(crate) const KERNEL_DATA: MemoryType = MemoryType::custom(0x8000_0003);
 pub(crate) const MMAP_META_DATA: MemoryType = MemoryType::custom(0x8000_0004);
+pub(crate) const INITRD_DATA: MemoryType = MemoryType::custom(0x8000_0005);
 
 pub(crate) type NereusMemoryMap = map::MemoryMap;
 pub(crate) type NereusMemoryDescriptor = map::MemoryDescriptor;
@@ -43,7 +43,7 @@ pub(crate) struct VirtualAddressSpace {
 /// Set up higher-half kernel address space
 pub(crate) fn initialize_address_space(
     bootinfo: *mut BootInfo,
-    mut pmm: BitMapAllocator,
+    mut pmm: FrameAllocator,
     old_stack: KernelStack,
     fb_base: u64,
     fb_page_count: usize,
@@ -66,6 +66,7 @@ pub(crate) fn initialize_address_space(
         }
     }
 
+    let huge_pages = msr.is_some_and(|msr| msr.get_cpuid().huge_pages_available());
     let memory_map = bootinfo_ref.mmap;
 
     let pml4_addr = pmm.request_page()?;
@@ -102,7 +103,7 @@ pub(crate) fn initialize_address_space(
                 // map part of physical address space to higher half
                 NereusMemoryType::Available => {
                     if desc.phys_end < PAS_VIRTUAL_MAX {
-                        (PAS_VIRTUAL, desc.phys_start, nx_flags)
+                        return map_direct(&mut manager, desc, nx_flags, huge_pages);
                     } else {
                         return Ok(());
                     }
@@ -115,7 +116,13 @@ pub(crate) fn initialize_address_space(
                     nx_flags,
                 ),
                 // map kernel data same as available PAS
-                NereusMemoryType::KernelData => (PAS_VIRTUAL, desc.phys_start, nx_flags),
+                NereusMemoryType::KernelData => {
+                    return map_direct(&mut manager, desc, nx_flags, huge_pages)
+                }
+                // the initial ramdisk is kept by the kernel
+                NereusMemoryType::Initrd => {
+                    return map_direct(&mut manager, desc, nx_flags, huge_pages)
+                }
                 NereusMemoryType::KernelCode => (
                     KERNEL_CODE_VIRTUAL,
                     desc.phys_start,
@@ -182,8 +189,8 @@ pub(crate) fn initialize_address_space(
         // pml4_offset
         manager.mappings().update_pml4_virtual(new);
 
-        // pmm bit map
-        manager.pmm().update_bit_map_ptr(PAS_VIRTUAL);
+        // pmm metadata
+        manager.pmm().update_metadata_ptr(PAS_VIRTUAL);
 
         // pmm memory map
         manager.pmm().update_memory_map_ptr(PAS_VIRTUAL);
@@ -195,3 +202,36 @@ pub(crate) fn initialize_address_space(
         stack,
     })
 }
+
+/// Maps the memory of the descriptor to the direct mapping at [`PAS_VIRTUAL`], using the largest
+/// pages which fit into the descriptor. 1 GiB pages are only used if `huge_pages` is set.
+fn map_direct(
+    manager: &mut PageTableManager,
+    desc: &NereusMemoryDescriptor,
+    flags: PageEntryFlags,
+    huge_pages: bool,
+) -> Result<(), FrameAllocatorError> {
+    let end = desc.phys_start + desc.num_pages * PAGE_SIZE as u64;
+
+    let mut physical_address = desc.phys_start;
+    while physical_address < end {
+        let size = [PageSize::Huge, PageSize::Large]
+            .into_iter()
+            .filter(|size| huge_pages || *size != PageSize::Huge)
+            .find(|size| {
+                physical_address.is_multiple_of(size.bytes())
+                    && end - physical_address >= size.bytes()
+            })
+            .unwrap_or(PageSize::Small);
+
+        manager.map_page(
+            PAS_VIRTUAL + physical_address,
+            physical_address,
+            flags,
+            size,
+        )?;
+        physical_address += size.bytes();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+# Init process, which is packed into the initial ramdisk as `/sbin/init` and started by the kernel
+# as PID 1 during boot. It reaps all of its children, including the processes orphaned by their
+# parents, and never exits.
+#
+# Built by `nix/img.nix` with:
+#   as --64 -o init.o init.s && ld -static -nostdlib -o init init.o
+
+    .intel_syntax noprefix
+
+    # system call numbers, see `kernel/src/syscall/table.rs`
+    .equ SYS_WRITE, 7
+    .equ SYS_WAIT, 8
+
+    .equ STDOUT, 1
+    # waits for any child
+    .equ ANY_CHILD, -1
+
+    .text
+    .globl _start
+_start:
+    mov eax, SYS_WRITE
+    mov edi, STDOUT
+    lea rsi, [rip + hello]
+    mov edx, OFFSET hello_len
+    syscall
+
+    # init is never without children, since it waits for orphans to be adopted
+reap:
+    mov eax, SYS_WAIT
+    mov rdi, ANY_CHILD
+    xor esi, esi
+    syscall
+    jmp reap
+
+    .section .rodata
+hello:
+    .ascii "[init] started\n"
+    .equ hello_len, . - hello
@@ -0,0 +1,134 @@
+# Statically linked test program, which is packed into the initial ramdisk as `/bin/test` and
+# started by the kernel during boot if it is built with the `selftest` feature. It exercises the
+# system call interface from ring 3: it reads the start of its own executable, forks and waits for
+# the child, which writes to a page shared copy-on-write with the parent. Exits with 0 if all
+# checks succeed and with 1 otherwise.
+#
+# Built by `nix/img.nix` with:
+#   as --64 -o test.o test.s && ld -static -nostdlib -o test test.o
+
+    .intel_syntax noprefix
+
+    # system call numbers, see `kernel/src/syscall/table.rs`
+    .equ SYS_EXIT, 0
+    .equ SYS_WRITE, 7
+    .equ SYS_WAIT, 8
+    .equ SYS_OPEN, 9
+    .equ SYS_READ, 10
+    .equ SYS_CLOSE, 11
+    .equ SYS_FORK, 13
+
+    .equ OPEN_READ, 1
+    .equ STDOUT, 1
+    .equ CHILD_CODE, 42
+
+# Writes the string at `label` with the length `label_len` to standard output.
+.macro print label
+    mov eax, SYS_WRITE
+    mov edi, STDOUT
+    lea rsi, [rip + \label]
+    mov edx, OFFSET \label\()_len
+    syscall
+.endm
+
+    .text
+    .globl _start
+_start:
+    print hello
+
+    # the executable starts with the ELF magic
+    mov eax, SYS_OPEN
+    lea rdi, [rip + path]
+    mov esi, OFFSET path_len
+    mov edx, OPEN_READ
+    syscall
+    test rax, rax
+    js fail
+    mov r12, rax
+
+    mov eax, SYS_READ
+    mov rdi, r12
+    lea rsi, [rip + magic]
+    mov edx, 4
+    syscall
+    cmp rax, 4
+    jne fail
+    cmp dword ptr [rip + magic], 0x464c457f
+    jne fail
+
+    mov eax, SYS_CLOSE
+    mov rdi, r12
+    syscall
+    test rax, rax
+    jnz fail
+    print read
+
+    # the child overwrites the marker, which the parent must not observe
+    mov eax, SYS_FORK
+    syscall
+    test rax, rax
+    js fail
+    jz child
+    mov r12, rax
+
+    mov eax, SYS_WAIT
+    mov rdi, r12
+    lea rsi, [rip + status]
+    syscall
+    cmp rax, r12
+    jne fail
+    cmp dword ptr [rip + status], CHILD_CODE
+    jne fail
+    cmp byte ptr [rip + marker], 'p'
+    jne fail
+
+    print passed
+    mov eax, SYS_EXIT
+    xor edi, edi
+    syscall
+
+child:
+    mov byte ptr [rip + marker], 'c'
+    cmp byte ptr [rip + marker], 'c'
+    jne fail
+    print forked
+    mov eax, SYS_EXIT
+    mov edi, CHILD_CODE
+    syscall
+
+fail:
+    print failed
+    mov eax, SYS_EXIT
+    mov edi, 1
+    syscall
+
+    .section .rodata
+path:
+    .ascii "/bin/test"
+    .equ path_len, . - path
+hello:
+    .ascii "[test] running in user space\n"
+    .equ hello_len, . - hello
+read:
+    .ascii "[test] read the ELF magic of /bin/test\n"
+    .equ read_len, . - read
+forked:
+    .ascii "[test] child writes to a copy-on-write page\n"
+    .equ forked_len, . - forked
+passed:
+    .ascii "[test] passed\n"
+    .equ passed_len, . - passed
+failed:
+    .ascii "[test] failed\n"
+    .equ failed_len, . - failed
+
+    .data
+marker:
+    .byte 'p'
+
+    .bss
+    .balign 4
+status:
+    .skip 4
+magic:
+    .skip 4
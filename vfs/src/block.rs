@@ -0,0 +1,177 @@
+use core::cell::RefCell;
+
+use alloc::{vec, vec::Vec};
+
+use crate::error::VfsError;
+
+/// Device storing data in blocks of a fixed size, e.g. a disk or one of its partitions.
+///
+/// Note: Devices are shared by the filesystems and device files using them. Thus, writing takes a
+/// shared reference and implementations rely on interior mutability.
+pub trait BlockDevice {
+    /// Size of a block in bytes, which is a power of two.
+    fn block_size(&self) -> usize;
+
+    /// Number of blocks of the device.
+    fn block_count(&self) -> u64;
+
+    /// Reads the blocks starting at `block` into the buffer, whose length is a multiple of the
+    /// block size.
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), VfsError>;
+
+    /// Writes the buffer, whose length is a multiple of the block size, to the blocks starting at
+    /// `block`.
+    fn write_blocks(&self, block: u64, buffer: &[u8]) -> Result<(), VfsError>;
+
+    /// Writes cached blocks back to the device.
+    fn flush(&self) -> Result<(), VfsError> {
+        Ok(())
+    }
+
+    /// Returns the size of the device in bytes.
+    fn size(&self) -> u64 {
+        self.block_count() * self.block_size() as u64
+    }
+
+    /// Reads from the device starting at the byte `offset` into the buffer. Partially covered
+    /// blocks are read into a temporary buffer.
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<(), VfsError> {
+        check_range(self.size(), offset, buffer.len())?;
+
+        let block_size = self.block_size();
+        let mut bounce = Vec::new();
+        let mut done = 0;
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let block = position / block_size as u64;
+            let within = (position % block_size as u64) as usize;
+            let remaining = buffer.len() - done;
+
+            if within == 0 && remaining >= block_size {
+                let length = remaining - remaining % block_size;
+                self.read_blocks(block, &mut buffer[done..done + length])?;
+                done += length;
+            } else {
+                let length = remaining.min(block_size - within);
+                bounce.resize(block_size, 0);
+                self.read_blocks(block, &mut bounce)?;
+                buffer[done..done + length].copy_from_slice(&bounce[within..within + length]);
+                done += length;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the buffer to the device starting at the byte `offset`. Partially covered blocks
+    /// are read first and written back modified.
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<(), VfsError> {
+        check_range(self.size(), offset, buffer.len())?;
+
+        let block_size = self.block_size();
+        let mut bounce = Vec::new();
+        let mut done = 0;
+        while done < buffer.len() {
+            let position = offset + done as u64;
+            let block = position / block_size as u64;
+            let within = (position % block_size as u64) as usize;
+            let remaining = buffer.len() - done;
+
+            if within == 0 && remaining >= block_size {
+                let length = remaining - remaining % block_size;
+                self.write_blocks(block, &buffer[done..done + length])?;
+                done += length;
+            } else {
+                let length = remaining.min(block_size - within);
+                bounce.resize(block_size, 0);
+                self.read_blocks(block, &mut bounce)?;
+                bounce[within..within + length].copy_from_slice(&buffer[done..done + length]);
+                self.write_blocks(block, &bounce)?;
+                done += length;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Block device backed by memory, e.g. a disk image loaded into memory.
+#[derive(Debug)]
+pub struct MemoryDevice {
+    data: RefCell<Vec<u8>>,
+    block_size: usize,
+}
+
+impl MemoryDevice {
+    /// Creates a device of the data, whose length must be a multiple of the block size.
+    pub fn new(data: Vec<u8>, block_size: usize) -> Result<MemoryDevice, VfsError> {
+        if !block_size.is_power_of_two() || !data.len().is_multiple_of(block_size) {
+            return Err(VfsError::InvalidArgument);
+        }
+
+        Ok(MemoryDevice {
+            data: RefCell::new(data),
+            block_size,
+        })
+    }
+
+    /// Creates a zeroed device of `block_count` blocks.
+    pub fn zeroed(block_count: usize, block_size: usize) -> Result<MemoryDevice, VfsError> {
+        Self::new(vec![0; block_count * block_size], block_size)
+    }
+
+    /// Returns the content of the device.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data.into_inner()
+    }
+}
+
+impl BlockDevice for MemoryDevice {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        (self.data.borrow().len() / self.block_size) as u64
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), VfsError> {
+        if !buffer.len().is_multiple_of(self.block_size) {
+            return Err(VfsError::InvalidArgument);
+        }
+        self.read_at(block * self.block_size as u64, buffer)
+    }
+
+    fn write_blocks(&self, block: u64, buffer: &[u8]) -> Result<(), VfsError> {
+        if !buffer.len().is_multiple_of(self.block_size) {
+            return Err(VfsError::InvalidArgument);
+        }
+        self.write_at(block * self.block_size as u64, buffer)
+    }
+
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<(), VfsError> {
+        let data = self.data.borrow();
+        check_range(data.len() as u64, offset, buffer.len())?;
+
+        let offset = offset as usize;
+        buffer.copy_from_slice(&data[offset..offset + buffer.len()]);
+        Ok(())
+    }
+
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<(), VfsError> {
+        let mut data = self.data.borrow_mut();
+        check_range(data.len() as u64, offset, buffer.len())?;
+
+        let offset = offset as usize;
+        data[offset..offset + buffer.len()].copy_from_slice(buffer);
+        Ok(())
+    }
+}
+
+/// Checks that `length` bytes starting at `offset` lie within a device of `size` bytes.
+fn check_range(size: u64, offset: u64, length: usize) -> Result<(), VfsError> {
+    match offset.checked_add(length as u64) {
+        Some(end) if end <= size => Ok(()),
+        _ => Err(VfsError::Io),
+    }
+}
@@ -0,0 +1,43 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum VfsError {
+    #[error("No such file or directory.")]
+    NotFound,
+    #[error("File exists already.")]
+    AlreadyExists,
+    #[error("Not a directory.")]
+    NotDirectory,
+    #[error("Is a directory.")]
+    IsDirectory,
+    #[error("Not a symbolic link.")]
+    NotSymlink,
+    #[error("Directory is not empty.")]
+    NotEmpty,
+    #[error("Too many levels of symbolic links.")]
+    TooManyLinks,
+    #[error("Invalid path, an absolute path without empty components is required.")]
+    InvalidPath,
+    #[error("Filesystem is read-only.")]
+    ReadOnly,
+    #[error("Operation is not supported by the file.")]
+    NotSupported,
+    #[error("No space left on the device.")]
+    NoSpace,
+    #[error("I/O error of the underlying device.")]
+    Io,
+    #[error("Invalid argument.")]
+    InvalidArgument,
+    #[error("Bad file descriptor: {0}.")]
+    BadDescriptor(usize),
+    #[error("File has not been opened for reading.")]
+    NotReadable,
+    #[error("File has not been opened for writing.")]
+    NotWritable,
+    #[error("Too many open files.")]
+    TooManyOpenFiles,
+    #[error("A filesystem is mounted at the path already.")]
+    AlreadyMounted,
+    #[error("No filesystem is mounted at the path.")]
+    NotMounted,
+    #[error("Filesystem is busy.")]
+    Busy,
+}
@@ -0,0 +1,105 @@
+use core::cell::RefCell;
+
+use alloc::{rc::Rc, vec::Vec};
+
+use crate::{error::VfsError, file::File};
+
+/// Maximum number of file descriptors of a table.
+pub const FD_MAX: usize = 256;
+
+/// Open file referred to by one or more file descriptors.
+pub type SharedFile = Rc<RefCell<File>>;
+
+/// File descriptor table of a process, mapping file descriptors to open files. New descriptors
+/// are always the lowest unused ones.
+///
+/// Note: Cloning the table shares the open files, including their offsets.
+#[derive(Clone, Debug, Default)]
+pub struct FileTable {
+    files: Vec<Option<SharedFile>>,
+}
+
+impl FileTable {
+    pub const fn new() -> FileTable {
+        FileTable { files: Vec::new() }
+    }
+}
+
+impl FileTable {
+    /// Adds the open file to the table. Returns its file descriptor.
+    pub fn insert(&mut self, file: File) -> Result<usize, VfsError> {
+        self.insert_shared(Rc::new(RefCell::new(file)))
+    }
+
+    /// Adds another file descriptor referring to the open file. Returns the file descriptor.
+    pub fn insert_shared(&mut self, file: SharedFile) -> Result<usize, VfsError> {
+        match self.files.iter().position(Option::is_none) {
+            Some(fd) => {
+                self.files[fd] = Some(file);
+                Ok(fd)
+            }
+            None if self.files.len() < FD_MAX => {
+                self.files.push(Some(file));
+                Ok(self.files.len() - 1)
+            }
+            None => Err(VfsError::TooManyOpenFiles),
+        }
+    }
+
+    /// Returns the open file of the file descriptor.
+    pub fn get(&self, fd: usize) -> Result<SharedFile, VfsError> {
+        self.files
+            .get(fd)
+            .and_then(Option::clone)
+            .ok_or(VfsError::BadDescriptor(fd))
+    }
+
+    /// Closes the file descriptor. Returns the open file, which is closed once all of its file
+    /// descriptors have been closed.
+    pub fn remove(&mut self, fd: usize) -> Result<SharedFile, VfsError> {
+        let file = self
+            .files
+            .get_mut(fd)
+            .and_then(Option::take)
+            .ok_or(VfsError::BadDescriptor(fd))?;
+
+        while self.files.last().is_some_and(Option::is_none) {
+            self.files.pop();
+        }
+        Ok(file)
+    }
+
+    /// Creates a new file descriptor referring to the same open file as `fd`. Returns the new
+    /// file descriptor.
+    pub fn duplicate(&mut self, fd: usize) -> Result<usize, VfsError> {
+        let file = self.get(fd)?;
+        self.insert_shared(file)
+    }
+
+    /// Makes `new` refer to the same open file as `fd`, closing `new` first if necessary.
+    pub fn duplicate_to(&mut self, fd: usize, new: usize) -> Result<(), VfsError> {
+        let file = self.get(fd)?;
+        if new >= FD_MAX {
+            return Err(VfsError::BadDescriptor(new));
+        }
+
+        if new >= self.files.len() {
+            self.files.resize(new + 1, None);
+        }
+        self.files[new] = Some(file);
+        Ok(())
+    }
+
+    /// Closes all file descriptors.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    /// Returns the open file descriptors in ascending order.
+    pub fn descriptors(&self) -> impl Iterator<Item = usize> + '_ {
+        self.files
+            .iter()
+            .enumerate()
+            .filter_map(|(fd, file)| file.as_ref().map(|_| fd))
+    }
+}
@@ -0,0 +1,120 @@
+use core::fmt::Debug;
+
+use alloc::rc::Rc;
+use bitflags::bitflags;
+
+use crate::{
+    error::VfsError,
+    inode::{Inode, InodeType},
+};
+
+bitflags! {
+    /// Flags an [`File`] is opened with, see [`crate::mount::MountTable::open`].
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct OpenFlags: u32 {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        /// Every write appends to the end of the file.
+        const APPEND = 1 << 2;
+        /// Creates a regular file, if it does not exist.
+        const CREATE = 1 << 3;
+        /// Fails if the file exists already, only used together with [`OpenFlags::CREATE`].
+        const EXCLUSIVE = 1 << 4;
+        /// Truncates a regular file opened for writing to size 0.
+        const TRUNCATE = 1 << 5;
+    }
+}
+
+/// Position to seek to, see [`File::seek`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// Open file, which keeps track of the offset for reading and writing. It is shared by all file
+/// descriptors duplicated from the one it has been opened with.
+#[derive(Clone)]
+pub struct File {
+    inode: Rc<dyn Inode>,
+    flags: OpenFlags,
+    offset: u64,
+}
+
+impl File {
+    pub fn new(inode: Rc<dyn Inode>, flags: OpenFlags) -> File {
+        File {
+            inode,
+            flags,
+            offset: 0,
+        }
+    }
+}
+
+impl File {
+    pub fn inode(&self) -> &Rc<dyn Inode> {
+        &self.inode
+    }
+
+    pub fn flags(&self) -> OpenFlags {
+        self.flags
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Reads from the current offset into the buffer and advances the offset. Returns the number
+    /// of bytes read, which is 0 at the end of the file.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        if !self.flags.contains(OpenFlags::READ) {
+            return Err(VfsError::NotReadable);
+        }
+        if self.inode.metadata()?.kind == InodeType::Directory {
+            return Err(VfsError::IsDirectory);
+        }
+
+        let read = self.inode.read_at(self.offset, buffer)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+
+    /// Writes the buffer at the current offset, or the end of the file if it has been opened
+    /// with [`OpenFlags::APPEND`], and advances the offset. Returns the number of bytes written.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize, VfsError> {
+        if !self.flags.contains(OpenFlags::WRITE) {
+            return Err(VfsError::NotWritable);
+        }
+        if self.flags.contains(OpenFlags::APPEND) {
+            self.offset = self.inode.metadata()?.size;
+        }
+
+        let written = self.inode.write_at(self.offset, buffer)?;
+        self.offset += written as u64;
+        Ok(written)
+    }
+
+    /// Sets the offset, which may lie beyond the end of the file. Returns the new offset.
+    pub fn seek(&mut self, position: SeekFrom) -> Result<u64, VfsError> {
+        let offset = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.inode.metadata()?.size.checked_add_signed(delta),
+        }
+        .ok_or(VfsError::InvalidArgument)?;
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
+
+impl Debug for File {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("File")
+            .field("metadata", &self.inode.metadata())
+            .field("flags", &self.flags)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
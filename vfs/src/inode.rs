@@ -0,0 +1,106 @@
+use alloc::{rc::Rc, string::String, vec::Vec};
+
+use crate::error::VfsError;
+
+/// File of a filesystem, which is either a regular file, a directory, a symbolic link or a
+/// device. Operations not supported by a kind of file fail by default.
+///
+/// Note: Inodes are shared by all open files referring to them. Thus, mutating operations take a
+/// shared reference and implementations rely on interior mutability.
+pub trait Inode {
+    /// Returns the metadata of the file.
+    fn metadata(&self) -> Result<Metadata, VfsError>;
+
+    /// Reads from the file starting at `offset` into the buffer. Returns the number of bytes read,
+    /// which is 0 at the end of the file.
+    fn read_at(&self, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        _ = (offset, buffer);
+        Err(VfsError::NotSupported)
+    }
+
+    /// Writes the buffer to the file starting at `offset`, growing it if necessary. Returns the
+    /// number of bytes written.
+    fn write_at(&self, offset: u64, buffer: &[u8]) -> Result<usize, VfsError> {
+        _ = (offset, buffer);
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Sets the size of a regular file, zero-filling the extended part.
+    fn truncate(&self, size: u64) -> Result<(), VfsError> {
+        _ = size;
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Returns the entry named `name` of a directory.
+    fn lookup(&self, name: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        _ = name;
+        Err(VfsError::NotDirectory)
+    }
+
+    /// Returns all entries of a directory, excluding `.` and `..`.
+    fn read_dir(&self) -> Result<Vec<DirEntry>, VfsError> {
+        Err(VfsError::NotDirectory)
+    }
+
+    /// Creates the entry `name` of the kind in a directory and returns it.
+    fn create(&self, name: &str, kind: InodeType) -> Result<Rc<dyn Inode>, VfsError> {
+        _ = (name, kind);
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Removes the entry `name` from a directory. Directories must be empty.
+    fn unlink(&self, name: &str) -> Result<(), VfsError> {
+        _ = name;
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Returns the target of a symbolic link.
+    fn read_link(&self) -> Result<String, VfsError> {
+        Err(VfsError::NotSymlink)
+    }
+}
+
+/// Root of a filesystem, which can be mounted into the namespace of the
+/// [`crate::mount::MountTable`].
+pub trait FileSystem {
+    /// Name of the filesystem type, e.g. `fat`.
+    fn name(&self) -> &str;
+
+    /// Returns the root directory.
+    fn root(&self) -> Result<Rc<dyn Inode>, VfsError>;
+
+    /// Writes cached data back to the underlying device.
+    fn sync(&self) -> Result<(), VfsError> {
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    /// Inode number, unique within the filesystem.
+    pub ino: u64,
+    pub kind: InodeType,
+    /// Size in bytes.
+    pub size: u64,
+    /// Permission bits, e.g. `0o755`.
+    pub mode: u16,
+    /// Number of hard links.
+    pub links: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InodeType {
+    File,
+    Directory,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+}
+
+/// Entry of a directory, see [`Inode::read_dir`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub ino: u64,
+    pub kind: InodeType,
+}
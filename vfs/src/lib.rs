@@ -0,0 +1,16 @@
+#![no_std]
+
+//! Virtual filesystem layer. Filesystems expose their files as [`inode::Inode`]s, which are
+//! combined into a single namespace by the [`mount::MountTable`]. Opened files are tracked per
+//! process in a [`fd::FileTable`]. Disk filesystems access their data through a
+//! [`block::BlockDevice`].
+
+pub mod block;
+pub mod error;
+pub mod fd;
+pub mod file;
+pub mod inode;
+pub mod mount;
+pub mod path;
+
+extern crate alloc;
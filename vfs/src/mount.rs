@@ -0,0 +1,293 @@
+use core::fmt::Debug;
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    error::VfsError,
+    file::{File, OpenFlags},
+    inode::{DirEntry, FileSystem, Inode, InodeType},
+    path,
+};
+
+/// Maximum number of symbolic links followed while resolving a path.
+const SYMLINK_DEPTH_MAX: usize = 8;
+/// Number of bytes the buffer grows by, while reading past the reported size of a file.
+const READ_CHUNK_SIZE: usize = 512;
+
+/// Filesystem mounted at a path of the namespace.
+pub struct Mount {
+    path: String,
+    fs: Rc<dyn FileSystem>,
+}
+
+impl Mount {
+    /// Normalized absolute path of the mount point.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn fs(&self) -> &Rc<dyn FileSystem> {
+        &self.fs
+    }
+}
+
+impl Debug for Mount {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Mount")
+            .field("path", &self.path)
+            .field("fs", &self.fs.name())
+            .finish()
+    }
+}
+
+/// Namespace of all mounted filesystems. Paths are absolute, a filesystem must be mounted at `/`
+/// before any other one.
+///
+/// Note: The mount point of a filesystem does not need to exist in its parent filesystem. It
+/// shadows an existing entry of the same name.
+#[derive(Debug, Default)]
+pub struct MountTable {
+    /// Mounts ordered by the time they have been mounted.
+    mounts: Vec<Mount>,
+}
+
+impl MountTable {
+    pub const fn new() -> MountTable {
+        MountTable { mounts: Vec::new() }
+    }
+}
+
+impl MountTable {
+    /// Mounts the filesystem at the path, whose parent must be an existing directory.
+    pub fn mount(&mut self, path: &str, fs: Rc<dyn FileSystem>) -> Result<(), VfsError> {
+        let path = path::normalize(path)?;
+        if self.find(&path).is_some() {
+            return Err(VfsError::AlreadyMounted);
+        }
+
+        if path != "/" {
+            let (parent, _) = path::split(&path)?;
+            if self.resolve(parent)?.metadata()?.kind != InodeType::Directory {
+                return Err(VfsError::NotDirectory);
+            }
+        }
+
+        self.mounts.push(Mount { path, fs });
+        Ok(())
+    }
+
+    /// Unmounts the filesystem at the path and returns it. Filesystems with other filesystems
+    /// mounted below them cannot be unmounted.
+    pub fn unmount(&mut self, path: &str) -> Result<Rc<dyn FileSystem>, VfsError> {
+        let path = path::normalize(path)?;
+        let index = self
+            .mounts
+            .iter()
+            .position(|mount| mount.path == path)
+            .ok_or(VfsError::NotMounted)?;
+
+        if self
+            .mounts
+            .iter()
+            .any(|mount| mount.path != path && Self::is_below(&mount.path, &path))
+        {
+            return Err(VfsError::Busy);
+        }
+
+        let mount = self.mounts.remove(index);
+        mount.fs.sync()?;
+        Ok(mount.fs)
+    }
+
+    pub fn mounts(&self) -> &[Mount] {
+        &self.mounts
+    }
+
+    /// Returns the file at the path, following symbolic links.
+    pub fn resolve(&self, path: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        self.walk(path, true, 0)
+    }
+
+    /// Returns the file at the path. A symbolic link as the last component is not followed.
+    pub fn resolve_link(&self, path: &str) -> Result<Rc<dyn Inode>, VfsError> {
+        self.walk(path, false, 0)
+    }
+
+    /// Returns the parent directory of the path and the name of the last component.
+    pub fn resolve_parent<'a>(&self, path: &'a str) -> Result<(Rc<dyn Inode>, &'a str), VfsError> {
+        let path = path.trim_end_matches('/');
+        let name = path
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+            .ok_or(VfsError::InvalidPath)?;
+
+        let parent = self.resolve(&path[..path.len() - name.len()])?;
+        if parent.metadata()?.kind != InodeType::Directory {
+            return Err(VfsError::NotDirectory);
+        }
+        Ok((parent, name))
+    }
+
+    /// Opens the file at the path, see [`OpenFlags`].
+    pub fn open(&self, path: &str, flags: OpenFlags) -> Result<File, VfsError> {
+        let inode = match self.resolve(path) {
+            Ok(_) if flags.contains(OpenFlags::CREATE | OpenFlags::EXCLUSIVE) => {
+                return Err(VfsError::AlreadyExists);
+            }
+            Ok(inode) => inode,
+            Err(VfsError::NotFound) if flags.contains(OpenFlags::CREATE) => {
+                self.create(path, InodeType::File)?
+            }
+            Err(err) => return Err(err),
+        };
+
+        let kind = inode.metadata()?.kind;
+        if kind == InodeType::Directory && flags.contains(OpenFlags::WRITE) {
+            return Err(VfsError::IsDirectory);
+        }
+        if kind == InodeType::File && flags.contains(OpenFlags::WRITE | OpenFlags::TRUNCATE) {
+            inode.truncate(0)?;
+        }
+
+        Ok(File::new(inode, flags))
+    }
+
+    /// Creates a file of the kind at the path and returns it.
+    pub fn create(&self, path: &str, kind: InodeType) -> Result<Rc<dyn Inode>, VfsError> {
+        if self.find(&path::normalize(path)?).is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        let (parent, name) = self.resolve_parent(path)?;
+        parent.create(name, kind)
+    }
+
+    /// Removes the file at the path. Directories must be empty and mount points cannot be
+    /// removed.
+    pub fn unlink(&self, path: &str) -> Result<(), VfsError> {
+        let normalized = path::normalize(path)?;
+        if self
+            .mounts
+            .iter()
+            .any(|mount| Self::is_below(&mount.path, &normalized))
+        {
+            return Err(VfsError::Busy);
+        }
+
+        let (parent, name) = self.resolve_parent(path)?;
+        parent.unlink(name)
+    }
+
+    /// Returns the entries of the directory at the path, including filesystems mounted at its
+    /// direct children.
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, VfsError> {
+        let normalized = path::normalize(path)?;
+        let mut entries = self.resolve(&normalized)?.read_dir()?;
+
+        for mount in self.mounts.iter() {
+            let Ok((parent, name)) = path::split(&mount.path) else {
+                continue;
+            };
+            if parent != normalized {
+                continue;
+            }
+
+            let root = mount.fs.root()?.metadata()?;
+            let entry = DirEntry {
+                name: name.to_string(),
+                ino: root.ino,
+                kind: root.kind,
+            };
+            match entries.iter_mut().find(|entry| entry.name == name) {
+                Some(shadowed) => *shadowed = entry,
+                None => entries.push(entry),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Reads the whole file at the path. The size of the file is only used as a hint, since
+    /// files generated on read may report a size of 0.
+    pub fn read_to_end(&self, path: &str) -> Result<Vec<u8>, VfsError> {
+        let inode = self.resolve(path)?;
+        let metadata = inode.metadata()?;
+        if metadata.kind == InodeType::Directory {
+            return Err(VfsError::IsDirectory);
+        }
+
+        let mut data = vec![0; metadata.size as usize];
+        let mut read = 0;
+        loop {
+            if read == data.len() {
+                data.resize(read + READ_CHUNK_SIZE, 0);
+            }
+            match inode.read_at(read as u64, &mut data[read..])? {
+                0 => break,
+                length => read += length,
+            }
+        }
+        data.truncate(read);
+
+        Ok(data)
+    }
+
+    /// Walks the components of the path, starting at the root filesystem. Mount points take
+    /// precedence over the entries of their parent directory.
+    fn walk(&self, path: &str, follow: bool, depth: usize) -> Result<Rc<dyn Inode>, VfsError> {
+        let components = path::components(path)?;
+        let mut current = self.find("/").ok_or(VfsError::NotMounted)?.fs.root()?;
+
+        for (index, name) in components.iter().enumerate() {
+            let walked = path::join(&components[..=index]);
+            let next = match self.find(&walked) {
+                Some(mount) => mount.fs.root()?,
+                None => current.lookup(name)?,
+            };
+
+            let last = index + 1 == components.len();
+            if (follow || !last) && next.metadata()?.kind == InodeType::Symlink {
+                if depth >= SYMLINK_DEPTH_MAX {
+                    return Err(VfsError::TooManyLinks);
+                }
+
+                // relative targets are resolved from the directory containing the link
+                let target = next.read_link()?;
+                let mut resolved = if target.starts_with('/') {
+                    target
+                } else {
+                    path::join(&components[..index]) + "/" + &target
+                };
+                for rest in &components[index + 1..] {
+                    resolved.push('/');
+                    resolved.push_str(rest);
+                }
+
+                return self.walk(&resolved, follow, depth + 1);
+            }
+
+            current = next;
+        }
+
+        Ok(current)
+    }
+
+    /// Returns the filesystem mounted at the normalized path.
+    fn find(&self, path: &str) -> Option<&Mount> {
+        self.mounts.iter().find(|mount| mount.path == path)
+    }
+
+    /// Whether the normalized `path` equals or lies below the normalized `directory`.
+    fn is_below(path: &str, directory: &str) -> bool {
+        directory == "/"
+            || path
+                .strip_prefix(directory)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
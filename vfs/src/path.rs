@@ -0,0 +1,56 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::error::VfsError;
+
+/// Maximum length of a path in bytes.
+pub const PATH_MAX: usize = 4096;
+
+/// Splits an absolute path into its components, resolving `.` and `..` lexically. Empty
+/// components of repeated `/` are ignored. `..` of the root directory is the root directory.
+pub fn components(path: &str) -> Result<Vec<&str>, VfsError> {
+    if !path.starts_with('/') || path.len() > PATH_MAX {
+        return Err(VfsError::InvalidPath);
+    }
+
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+
+    Ok(components)
+}
+
+/// Returns the normalized form of an absolute path, see [`components`].
+pub fn normalize(path: &str) -> Result<String, VfsError> {
+    components(path).map(|components| join(&components))
+}
+
+/// Joins the components to an absolute path.
+pub fn join(components: &[&str]) -> String {
+    if components.is_empty() {
+        return String::from("/");
+    }
+
+    let mut path = String::with_capacity(components.iter().map(|name| name.len() + 1).sum());
+    for name in components {
+        path.push('/');
+        path.push_str(name);
+    }
+    path
+}
+
+/// Splits a normalized absolute path into its parent directory and the last component. Fails
+/// for the root directory.
+pub fn split(path: &str) -> Result<(&str, &str), VfsError> {
+    match path.rsplit_once('/') {
+        Some((_, "")) | None => Err(VfsError::InvalidPath),
+        Some(("", name)) => Ok(("/", name)),
+        Some((parent, name)) => Ok((parent, name)),
+    }
+}